@@ -0,0 +1,105 @@
+use serde::Deserialize;
+
+use crate::api::User;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditLogChange {
+    pub key: String,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditLogEntry {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub target_id: Option<String>,
+    pub action_type: u16,
+    #[serde(default)]
+    pub changes: Vec<AuditLogChange>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditLog {
+    pub audit_log_entries: Vec<AuditLogEntry>,
+    #[serde(default)]
+    pub users: Vec<User>,
+}
+
+/// Audit log event names, see
+/// https://discord.com/developers/docs/resources/audit-log#audit-log-entry-object-audit-log-events
+const ACTION_TYPES: &[(u16, &str)] = &[
+    (1, "GUILD_UPDATE"),
+    (10, "CHANNEL_CREATE"),
+    (11, "CHANNEL_UPDATE"),
+    (12, "CHANNEL_DELETE"),
+    (13, "CHANNEL_OVERWRITE_CREATE"),
+    (14, "CHANNEL_OVERWRITE_UPDATE"),
+    (15, "CHANNEL_OVERWRITE_DELETE"),
+    (20, "MEMBER_KICK"),
+    (21, "MEMBER_PRUNE"),
+    (22, "MEMBER_BAN_ADD"),
+    (23, "MEMBER_BAN_REMOVE"),
+    (24, "MEMBER_UPDATE"),
+    (25, "MEMBER_ROLE_UPDATE"),
+    (26, "MEMBER_MOVE"),
+    (27, "MEMBER_DISCONNECT"),
+    (28, "BOT_ADD"),
+    (30, "ROLE_CREATE"),
+    (31, "ROLE_UPDATE"),
+    (32, "ROLE_DELETE"),
+    (40, "INVITE_CREATE"),
+    (41, "INVITE_UPDATE"),
+    (42, "INVITE_DELETE"),
+    (50, "WEBHOOK_CREATE"),
+    (51, "WEBHOOK_UPDATE"),
+    (52, "WEBHOOK_DELETE"),
+    (60, "EMOJI_CREATE"),
+    (61, "EMOJI_UPDATE"),
+    (62, "EMOJI_DELETE"),
+    (72, "MESSAGE_DELETE"),
+    (73, "MESSAGE_BULK_DELETE"),
+    (74, "MESSAGE_PIN"),
+    (75, "MESSAGE_UNPIN"),
+    (80, "INTEGRATION_CREATE"),
+    (81, "INTEGRATION_UPDATE"),
+    (82, "INTEGRATION_DELETE"),
+    (83, "STAGE_INSTANCE_CREATE"),
+    (84, "STAGE_INSTANCE_UPDATE"),
+    (85, "STAGE_INSTANCE_DELETE"),
+    (90, "STICKER_CREATE"),
+    (91, "STICKER_UPDATE"),
+    (92, "STICKER_DELETE"),
+    (100, "GUILD_SCHEDULED_EVENT_CREATE"),
+    (101, "GUILD_SCHEDULED_EVENT_UPDATE"),
+    (102, "GUILD_SCHEDULED_EVENT_DELETE"),
+    (110, "THREAD_CREATE"),
+    (111, "THREAD_UPDATE"),
+    (112, "THREAD_DELETE"),
+    (121, "APPLICATION_COMMAND_PERMISSION_UPDATE"),
+    (140, "AUTO_MODERATION_RULE_CREATE"),
+    (141, "AUTO_MODERATION_RULE_UPDATE"),
+    (142, "AUTO_MODERATION_RULE_DELETE"),
+    (143, "AUTO_MODERATION_BLOCK_MESSAGE"),
+    (144, "AUTO_MODERATION_FLAG_TO_CHANNEL"),
+    (145, "AUTO_MODERATION_USER_COMMUNICATION_DISABLED"),
+];
+
+pub fn action_name(action_type: u16) -> String {
+    ACTION_TYPES
+        .iter()
+        .find(|(t, _)| *t == action_type)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or(format!("ACTION_{action_type}"))
+}
+
+/// Accepts either the numeric action type or its name, case-insensitively.
+pub fn parse_action_type(text: &str) -> Option<u16> {
+    text.parse().ok().or_else(|| {
+        ACTION_TYPES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(text))
+            .map(|(t, _)| *t)
+    })
+}
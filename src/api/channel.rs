@@ -1,27 +1,91 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::Error;
+use crate::{
+    Error,
+    api::permissions::{Permissions, compute_base_permissions, compute_permissions},
+};
 
-const VIEW_CHANNEL_PERMISSION: u64 = 1 << 10;
-
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Role {
     pub id: String,
     pub name: String,
     pub permissions: String,
+    /// RGB color, 0 when the role has none
+    #[serde(default)]
+    pub color: u32,
+    #[serde(default)]
+    pub position: i64,
+    /// Shown separately in the member list
+    #[serde(default)]
+    pub hoist: bool,
+    #[serde(default)]
+    pub mentionable: bool,
+    /// Owned by an integration, it cannot be assigned by hand
+    #[serde(default)]
+    pub managed: bool,
+}
+
+impl Role {
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        (self.color != 0).then_some((
+            (self.color >> 16) as u8,
+            (self.color >> 8) as u8,
+            self.color as u8,
+        ))
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PermissionContext {
     pub user_id: String,
     pub user_role_ids: Vec<String>,
     pub all_guild_roles: Vec<Role>,
     pub everyone_role_id: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl PermissionContext {
+    /// Guild-wide permissions, for actions that are not tied to a channel.
+    pub fn guild_permissions(&self) -> Permissions {
+        compute_base_permissions(self)
+    }
+
+    pub fn has_guild_permission(&self, permission: Permissions) -> bool {
+        self.guild_permissions().contains(permission)
+    }
+
+    /// The member's roles, highest first.
+    pub fn roles_of<'a>(&'a self, role_ids: &[String]) -> Vec<&'a Role> {
+        let mut roles: Vec<&Role> = self
+            .all_guild_roles
+            .iter()
+            .filter(|r| r.id != self.everyone_role_id && role_ids.contains(&r.id))
+            .collect();
+        roles.sort_by_key(|r| std::cmp::Reverse(r.position));
+        roles
+    }
+
+    /// Color of the highest colored role, which is how Discord colors names.
+    pub fn color_of(&self, role_ids: &[String]) -> Option<(u8, u8, u8)> {
+        self.roles_of(role_ids).into_iter().find_map(Role::rgb)
+    }
+
+    /// Roles can only be managed below one's own highest role, unless owning the guild.
+    pub fn can_manage_role(&self, role: &Role) -> bool {
+        if self.owner_id.as_deref() == Some(self.user_id.as_str()) {
+            return true;
+        }
+
+        self.roles_of(&self.user_role_ids)
+            .first()
+            .is_some_and(|top| top.position > role.position)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Overwrite {
     pub id: String,
     pub r#type: u8,
@@ -29,7 +93,7 @@ pub struct Overwrite {
     pub deny: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Channel {
     pub id: String,
     pub name: String,
@@ -40,99 +104,31 @@ pub struct Channel {
     #[serde(default)]
     pub permission_overwrites: Vec<Overwrite>,
     pub children: Option<Vec<Channel>>,
-}
-
-fn parse_permission_string(hex_string: &str) -> u64 {
-    hex_string
-        .parse::<u64>()
-        .unwrap_or_else(|_| u64::from_str_radix(hex_string, 16).unwrap_or(0))
+    #[serde(default)]
+    pub position: i64,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub nsfw: bool,
+    /// Slowmode, in seconds
+    #[serde(default)]
+    pub rate_limit_per_user: Option<u64>,
+    #[serde(default)]
+    pub last_message_id: Option<String>,
 }
 
 impl Channel {
-    fn calculate_permissions(&self, context: &PermissionContext) -> u64 {
-        let everyone_role = context
-            .all_guild_roles
-            .iter()
-            .find(|r| r.id == context.everyone_role_id)
-            .cloned()
-            .unwrap_or_else(|| Role {
-                id: context.everyone_role_id.clone(),
-                name: "@everyone".to_string(),
-                permissions: "0".to_string(),
-            });
-
-        let mut permissions = parse_permission_string(&everyone_role.permissions);
-        for user_role_id in context
-            .user_role_ids
-            .iter()
-            .filter(|&id| id != &context.everyone_role_id)
-        {
-            if let Some(role) = context
-                .all_guild_roles
-                .iter()
-                .find(|r| &r.id == user_role_id)
-            {
-                permissions |= parse_permission_string(&role.permissions);
-            }
-        }
-
-        if let Some(everyone_overwrite) = self
-            .permission_overwrites
-            .iter()
-            .find(|o| o.r#type == 0 && o.id == context.everyone_role_id)
-        {
-            let deny = parse_permission_string(&everyone_overwrite.deny);
-            let allow = parse_permission_string(&everyone_overwrite.allow);
-
-            permissions &= !deny;
-            permissions |= allow;
-        }
-
-        let mut role_denies = 0u64;
-        let mut role_allows = 0u64;
-
-        for user_role_id in context
-            .user_role_ids
-            .iter()
-            .filter(|&id| id != &context.everyone_role_id)
-        {
-            if let Some(overwrite) = self
-                .permission_overwrites
-                .iter()
-                .find(|o| o.r#type == 0 && &o.id == user_role_id)
-            {
-                role_denies |= parse_permission_string(&overwrite.deny);
-                role_allows |= parse_permission_string(&overwrite.allow);
-            }
-        }
-
-        permissions &= !role_denies;
-        permissions |= role_allows;
-
-        let user_id = context
-            .user_role_ids
-            .first()
-            .unwrap_or(&String::new())
-            .clone();
-
-        if let Some(member_overwrite) = self
-            .permission_overwrites
-            .iter()
-            .find(|o| o.r#type == 1 && o.id == user_id)
-        {
-            let deny = parse_permission_string(&member_overwrite.deny);
-            let allow = parse_permission_string(&member_overwrite.allow);
-
-            permissions &= !deny;
-            permissions |= allow;
-        }
+    /// Our effective permissions in this channel.
+    pub fn permissions(&self, context: &PermissionContext) -> Permissions {
+        compute_permissions(context, &self.permission_overwrites)
+    }
 
-        permissions
+    pub fn has_permission(&self, context: &PermissionContext, permission: Permissions) -> bool {
+        self.permissions(context).contains(permission)
     }
 
     pub fn is_readable(&self, context: &PermissionContext) -> bool {
-        let permissions = self.calculate_permissions(context);
-        (permissions & VIEW_CHANNEL_PERMISSION) != 0
+        self.has_permission(context, Permissions::VIEW_CHANNEL)
     }
 
     pub fn filter_channels_by_categories(channels: Vec<Self>) -> Result<Vec<Self>, Error> {
@@ -160,14 +156,76 @@ impl Channel {
         let mut final_list: Vec<Self> = Vec::new();
 
         for mut category in categories {
-            if let Some(children) = categorized_map.remove(&category.id) {
+            if let Some(mut children) = categorized_map.remove(&category.id) {
+                Self::sort_by_position(&mut children);
                 category.children = Some(children);
             }
             final_list.push(category);
         }
+        Self::sort_by_position(&mut final_list);
 
+        Self::sort_by_position(&mut uncategorized_channels);
         final_list.extend(uncategorized_channels);
 
         Ok(final_list)
     }
+
+    /// Discord's order: by position, ties broken by id.
+    fn sort_by_position(channels: &mut [Self]) {
+        channels.sort_by_key(|c| (c.position, c.id.parse::<u64>().unwrap_or_default()));
+    }
+
+    /// Undoes `filter_channels_by_categories`, back to the flat list the API returns.
+    pub fn flatten_tree(tree: &[Self]) -> Vec<Self> {
+        tree.iter()
+            .flat_map(|c| {
+                let mut parent = c.clone();
+                let children = parent.children.take().unwrap_or_default();
+                std::iter::once(parent).chain(children)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(id: &str, position: i64, color: u32) -> Role {
+        Role {
+            id: id.to_string(),
+            name: id.to_string(),
+            permissions: "0".to_string(),
+            color,
+            position,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn roles_are_managed_below_the_highest_one() {
+        let mut context = PermissionContext {
+            user_id: "200".to_string(),
+            user_role_ids: vec!["100".to_string(), "300".to_string()],
+            all_guild_roles: vec![
+                role("100", 0, 0),
+                role("300", 2, 0x00ff00),
+                role("400", 1, 0xff0000),
+            ],
+            everyone_role_id: "100".to_string(),
+            owner_id: Some("999".to_string()),
+        };
+        let mods = context.all_guild_roles[1].clone();
+        let muted = context.all_guild_roles[2].clone();
+
+        assert!(context.can_manage_role(&muted));
+        assert!(!context.can_manage_role(&mods));
+        assert_eq!(
+            context.color_of(&["400".to_string(), "300".to_string()]),
+            Some((0, 255, 0))
+        );
+
+        context.owner_id = Some("200".to_string());
+        assert!(context.can_manage_role(&mods));
+    }
 }
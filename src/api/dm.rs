@@ -1,8 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::api::User;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DM {
     pub id: String,
     #[serde(rename = "type")]
@@ -10,9 +10,19 @@ pub struct DM {
     pub last_message_id: Option<String>,
     pub recipients: Vec<User>,
     pub name: Option<String>,
+    /// Creator of a group DM, the only one allowed to remove recipients
+    #[serde(default)]
+    pub owner_id: Option<String>,
 }
 
+/// Channel type of a DM with several recipients.
+pub const GROUP_DM: u8 = 3;
+
 impl DM {
+    pub fn is_group(&self) -> bool {
+        self.channel_type == GROUP_DM
+    }
+
     pub fn get_name(&self) -> String {
         let users = self
             .recipients
@@ -7,10 +7,137 @@ use tokio::time::{self, Duration};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
 
 use crate::logs::{LogType, print_log};
-use crate::{AppAction, api::Message as DiscordMessage};
+use crate::{
+    AppAction,
+    api::{
+        DM, Message as DiscordMessage, User,
+        guild::{Guild, GuildMember},
+        user::{GuildSettings, ReadState, Relationship, UserSettings},
+    },
+};
 
 const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
 
+/// Snowflakes are usually strings, but READY sends `0` for channels never read.
+fn snowflake(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(id) => Some(id.clone()),
+        serde_json::Value::Number(id) if id.as_u64() != Some(0) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// READY's read states, a plain list or `{ entries }` with versioned read states.
+fn read_states(d: &serde_json::Value) -> Vec<ReadState> {
+    let entries = d["read_state"]["entries"]
+        .as_array()
+        .or(d["read_state"].as_array());
+
+    entries
+        .into_iter()
+        .flatten()
+        // Other types track guild events and notification center items, not channels
+        .filter(|entry| entry["read_state_type"].as_u64().unwrap_or_default() == 0)
+        .filter_map(|entry| {
+            Some(ReadState {
+                channel_id: entry["id"].as_str()?.to_string(),
+                last_message_id: snowflake(&entry["last_message_id"]),
+                mention_count: entry["mention_count"].as_u64().unwrap_or_default() as u32,
+            })
+        })
+        .collect()
+}
+
+/// READY's notification settings, a plain list or `{ entries }` like read states.
+fn guild_settings(d: &serde_json::Value) -> Vec<GuildSettings> {
+    let entries = d["user_guild_settings"]["entries"]
+        .as_array()
+        .or(d["user_guild_settings"].as_array());
+
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+        .collect()
+}
+
+/// READY's guilds, whose fields are under `properties` in newer payloads.
+fn ready_guilds(d: &serde_json::Value) -> Vec<Guild> {
+    d["guilds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|guild| {
+            let mut fields = match &guild["properties"] {
+                properties @ serde_json::Value::Object(_) => properties.clone(),
+                _ => guild.clone(),
+            };
+            fields["id"] = guild["id"].clone();
+            // Unavailable guilds come without a name and are left out
+            serde_json::from_value(fields).ok()
+        })
+        .collect()
+}
+
+/// The newest message of every channel READY knows about: `(channel_id, guild_id, message_id)`.
+fn channel_heads(d: &serde_json::Value) -> Vec<(String, Option<String>, String)> {
+    let guild_channels = d["guilds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|guild| {
+            let guild_id = guild["id"].as_str().map(|id| id.to_string());
+            guild["channels"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(move |channel| (channel, guild_id.clone()))
+        });
+    let private_channels = d["private_channels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|channel| (channel, None));
+
+    guild_channels
+        .chain(private_channels)
+        .filter_map(|(channel, guild_id)| {
+            Some((
+                channel["id"].as_str()?.to_string(),
+                guild_id,
+                snowflake(&channel["last_message_id"])?,
+            ))
+        })
+        .collect()
+}
+
+/// Describes the first activity of a presence the way Discord's member list does.
+fn activity_text(activities: &serde_json::Value) -> Option<String> {
+    let activity = activities.as_array()?.first()?;
+    let name = activity["name"].as_str().unwrap_or_default();
+
+    let text = match activity["type"].as_u64()? {
+        0 => format!("Playing {name}"),
+        1 => format!("Streaming {name}"),
+        2 => format!("Listening to {name}"),
+        3 => format!("Watching {name}"),
+        // Custom status, its text is in `state`
+        4 => {
+            let emoji = activity["emoji"]["name"].as_str();
+            let state = activity["state"].as_str();
+            match (emoji, state) {
+                (Some(emoji), Some(state)) => format!("{emoji} {state}"),
+                (Some(text), None) | (None, Some(text)) => text.to_string(),
+                (None, None) => return None,
+            }
+        }
+        5 => format!("Competing in {name}"),
+        _ => return None,
+    };
+
+    Some(text)
+}
+
 #[derive(Serialize)]
 struct GatewayCommand {
     op: u8,
@@ -167,6 +294,18 @@ impl GatewayClient {
                     let _ = action_tx.send(AppAction::GatewayMessageUpdate(msg)).await;
                 }
             }
+            "MESSAGE_ACK" => {
+                if let (Some(channel_id), Some(message_id)) =
+                    (d["channel_id"].as_str(), d["message_id"].as_str())
+                {
+                    let _ = action_tx
+                        .send(AppAction::GatewayMessageAck(
+                            channel_id.to_string(),
+                            message_id.to_string(),
+                        ))
+                        .await;
+                }
+            }
             "MESSAGE_DELETE" => {
                 if let (Some(id), Some(channel_id)) = (d["id"].as_str(), d["channel_id"].as_str()) {
                     let _ = action_tx
@@ -198,34 +337,167 @@ impl GatewayClient {
                         .await;
                 }
             }
-            "READY_SUPPLEMENTAL" => {
-                let mut statuses = std::collections::HashMap::new();
-                if let Some(guilds) = d["merged_presences"]["guilds"].as_array() {
-                    for guild in guilds {
-                        if let Some(users) = guild.as_array() {
-                            for user in users {
-                                if let (Some(user_id), Some(status)) =
-                                    (user["user_id"].as_str(), user["status"].as_str())
-                                {
-                                    statuses.insert(user_id.to_string(), status.to_string());
-                                }
-                            }
+            "READY" => {
+                // Newer READY payloads only reference users, who are listed separately
+                let users: Vec<User> =
+                    serde_json::from_value(d["users"].clone()).unwrap_or_default();
+                let relationships = d["relationships"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|relationship| {
+                        let mut relationship = relationship.clone();
+                        if relationship["user"].is_null()
+                            && let Some(user) = users
+                                .iter()
+                                .find(|u| Some(u.id.as_str()) == relationship["user_id"].as_str())
+                        {
+                            relationship["user"] = serde_json::to_value(user).ok()?;
                         }
+                        serde_json::from_value::<Relationship>(relationship).ok()
+                    })
+                    .collect();
+                let _ = action_tx
+                    .send(AppAction::GatewayRelationships(relationships))
+                    .await;
+
+                let _ = action_tx
+                    .send(AppAction::GatewayReadState(
+                        read_states(&d),
+                        channel_heads(&d),
+                    ))
+                    .await;
+
+                let _ = action_tx
+                    .send(AppAction::GatewayGuilds(ready_guilds(&d)))
+                    .await;
+
+                let _ = action_tx
+                    .send(AppAction::GatewayGuildSettings(guild_settings(&d)))
+                    .await;
+
+                // Only sent to clients that don't ask for the protobuf settings
+                if let Ok(settings) =
+                    serde_json::from_value::<UserSettings>(d["user_settings"].clone())
+                {
+                    if let Some(status) = settings.status.clone() {
+                        let _ = action_tx.send(AppAction::GatewayOwnStatus(status)).await;
                     }
+                    let _ = action_tx
+                        .send(AppAction::GatewayGuildFolders(settings.into_folders()))
+                        .await;
                 }
-                if let Some(friends) = d["merged_presences"]["friends"].as_array() {
-                    for friend in friends {
-                        if let (Some(user_id), Some(status)) =
-                            (friend["user_id"].as_str(), friend["status"].as_str())
-                        {
-                            statuses.insert(user_id.to_string(), status.to_string());
+            }
+            "USER_GUILD_SETTINGS_UPDATE" => {
+                if let Ok(settings) = serde_json::from_value::<GuildSettings>(d) {
+                    let _ = action_tx
+                        .send(AppAction::GatewayGuildSettingsUpdate(settings))
+                        .await;
+                }
+            }
+            "RELATIONSHIP_ADD" | "RELATIONSHIP_UPDATE" => {
+                if let Ok(relationship) = serde_json::from_value::<Relationship>(d) {
+                    let _ = action_tx
+                        .send(AppAction::GatewayRelationshipAdd(relationship))
+                        .await;
+                }
+            }
+            "RELATIONSHIP_REMOVE" => {
+                if let Some(user_id) = d["id"].as_str() {
+                    let _ = action_tx
+                        .send(AppAction::GatewayRelationshipRemove(user_id.to_string()))
+                        .await;
+                }
+            }
+            "CHANNEL_CREATE" | "CHANNEL_UPDATE" if d["guild_id"].is_null() => {
+                if let Ok(dm) = serde_json::from_value::<DM>(d) {
+                    let _ = action_tx.send(AppAction::GatewayDMUpdate(dm)).await;
+                }
+            }
+            "CHANNEL_DELETE" if d["guild_id"].is_null() => {
+                if let Some(channel_id) = d["id"].as_str() {
+                    let _ = action_tx
+                        .send(AppAction::GatewayDMDelete(channel_id.to_string()))
+                        .await;
+                }
+            }
+            "CHANNEL_RECIPIENT_ADD" => {
+                if let Some(channel_id) = d["channel_id"].as_str()
+                    && let Ok(user) = serde_json::from_value::<User>(d["user"].clone())
+                {
+                    let _ = action_tx
+                        .send(AppAction::GatewayRecipientAdd(channel_id.to_string(), user))
+                        .await;
+                }
+            }
+            "CHANNEL_RECIPIENT_REMOVE" => {
+                if let (Some(channel_id), Some(user_id)) =
+                    (d["channel_id"].as_str(), d["user"]["id"].as_str())
+                {
+                    let _ = action_tx
+                        .send(AppAction::GatewayRecipientRemove(
+                            channel_id.to_string(),
+                            user_id.to_string(),
+                        ))
+                        .await;
+                }
+            }
+            "READY_SUPPLEMENTAL" => {
+                let mut statuses = std::collections::HashMap::new();
+                let mut activities = std::collections::HashMap::new();
+                let guild_presences = d["merged_presences"]["guilds"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|guild| guild.as_array())
+                    .flatten();
+                let friend_presences = d["merged_presences"]["friends"]
+                    .as_array()
+                    .into_iter()
+                    .flatten();
+
+                for presence in guild_presences.chain(friend_presences) {
+                    if let (Some(user_id), Some(status)) =
+                        (presence["user_id"].as_str(), presence["status"].as_str())
+                    {
+                        statuses.insert(user_id.to_string(), status.to_string());
+                        if let Some(activity) = activity_text(&presence["activities"]) {
+                            activities.insert(user_id.to_string(), activity);
                         }
                     }
                 }
                 let _ = action_tx
-                    .send(AppAction::GatewayReadySupplemental(statuses))
+                    .send(AppAction::GatewayReadySupplemental(statuses, activities))
                     .await;
             }
+            "GUILD_MEMBER_ADD" | "GUILD_MEMBER_UPDATE" => {
+                if let Some(guild_id) = d["guild_id"].as_str().map(|s| s.to_string())
+                    && let Ok(member) = serde_json::from_value::<GuildMember>(d)
+                {
+                    let _ = action_tx
+                        .send(AppAction::ApiUpdateMembers(guild_id, vec![member]))
+                        .await;
+                }
+            }
+            "USER_SETTINGS_UPDATE" => {
+                if let Some(status) = d["status"].as_str() {
+                    let _ = action_tx
+                        .send(AppAction::GatewayOwnStatus(status.to_string()))
+                        .await;
+                }
+            }
+            // Our sessions on every client, "all" being the status others see
+            "SESSIONS_REPLACE" => {
+                let sessions = d.as_array().into_iter().flatten();
+                if let Some(status) = sessions
+                    .filter(|session| session["session_id"].as_str() == Some("all"))
+                    .find_map(|session| session["status"].as_str())
+                {
+                    let _ = action_tx
+                        .send(AppAction::GatewayOwnStatus(status.to_string()))
+                        .await;
+                }
+            }
             "PRESENCE_UPDATE" => {
                 if let (Some(user_id), Some(status)) =
                     (d["user"]["id"].as_str(), d["status"].as_str())
@@ -234,6 +506,7 @@ impl GatewayClient {
                         .send(AppAction::GatewayPresenceUpdate(
                             user_id.to_string(),
                             status.to_string(),
+                            activity_text(&d["activities"]),
                         ))
                         .await;
                 }
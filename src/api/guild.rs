@@ -2,14 +2,46 @@ use serde::{Deserialize, Serialize};
 
 use crate::api::User;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GuildMember {
     pub user: User,
     pub roles: Vec<String>,
+    #[serde(default)]
+    pub nick: Option<String>,
+    #[serde(default)]
+    pub joined_at: Option<String>,
+}
+
+/// The member object attached to gateway messages, which leaves out the user.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PartialMember {
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub nick: Option<String>,
+    #[serde(default)]
+    pub joined_at: Option<String>,
+}
+
+impl GuildMember {
+    pub fn from_partial(user: User, member: PartialMember) -> Self {
+        Self {
+            user,
+            roles: member.roles,
+            nick: member.nick,
+            joined_at: member.joined_at,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Guild {
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+    /// The level members get until they pick one, `NOTIFY_ALL` or `NOTIFY_MENTIONS`.
+    /// Only full guilds have it, not the list of our guilds
+    #[serde(default)]
+    pub default_message_notifications: Option<u8>,
 }
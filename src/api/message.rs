@@ -1,10 +1,24 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::api::User;
+use crate::api::{User, guild::PartialMember};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MessageReference {
+    pub message_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub guild_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Message {
     pub id: String,
     pub channel_id: String,
@@ -12,18 +26,54 @@ pub struct Message {
     pub content: Option<String>,
     pub timestamp: String,
     pub mentions: Vec<User>,
+    #[serde(default)]
+    pub mention_roles: Vec<String>,
+    /// Set for @everyone and @here
+    #[serde(default)]
+    pub mention_everyone: bool,
+    #[serde(default)]
+    pub edited_timestamp: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub message_reference: Option<MessageReference>,
+    #[serde(default)]
+    pub referenced_message: Option<Box<Message>>,
+    /// Only sent by the gateway, for guild messages
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    #[serde(default)]
+    pub member: Option<PartialMember>,
+    /// Set for messages sent through webhooks and application interactions
+    #[serde(default)]
+    pub webhook_id: Option<String>,
+    #[serde(default)]
+    pub application_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PartialMessage {
     pub id: String,
     pub channel_id: String,
     pub author: Option<User>,
     pub content: Option<String>,
     pub timestamp: Option<String>,
+    #[serde(default)]
+    pub edited_timestamp: Option<String>,
 }
 
 impl Message {
+    /// The badge Discord shows next to automated authors.
+    pub fn author_badge(&self) -> Option<&'static str> {
+        if self.webhook_id.is_some() || self.application_id.is_some() {
+            Some("APP")
+        } else if self.author.bot {
+            Some("BOT")
+        } else {
+            None
+        }
+    }
+
     pub fn map_mentions(&self) -> String {
         let Some(content) = self.content.as_ref() else {
             return "(*non-text*)".to_string();
@@ -1,13 +1,21 @@
+pub mod audit_log;
 pub mod channel;
 pub mod dm;
 pub mod emoji;
 pub mod gateway;
 pub mod guild;
 pub mod message;
+pub mod permissions;
 pub mod user;
 
-use reqwest::{Client, Method};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::{Client, Method, Response, StatusCode};
 use serde::de::DeserializeOwned;
+use tokio::time::{self, Duration, Instant};
 
 pub use channel::Channel;
 pub use dm::DM;
@@ -21,16 +29,56 @@ pub use user::User;
 use crate::{
     Error,
     api::{
-        channel::{PermissionContext, Role},
+        audit_log::AuditLog,
+        channel::{Overwrite, PermissionContext, Role},
         guild::GuildMember,
+        user::{Relationship, UserProfile, UserSettings},
     },
 };
 
+const MAX_RATE_LIMIT_RETRIES: usize = 5;
+const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+
+/// Milliseconds since the Unix epoch at which a snowflake id was created.
+pub fn snowflake_timestamp_ms(id: &str) -> i64 {
+    (id.parse::<u64>().unwrap_or_default() >> 22) as i64 + DISCORD_EPOCH_MS
+}
+
+fn header_value(response: &Response, name: &str) -> Option<f64> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok())
+}
+
+/// Audit log reasons travel in a header, so anything outside of unreserved ASCII is percent-encoded.
+fn encode_reason(reason: &str) -> String {
+    reason
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// The rate limit bucket a request counts against: Discord buckets routes by their major
+/// id (channel, guild), which the path carries, so the query is left out.
+fn bucket_key(method: &Method, endpoint: &str) -> String {
+    let path = endpoint.split('?').next().unwrap_or(endpoint);
+    format!("{method} {path}")
+}
+
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     pub http_client: Client,
     pub auth_token: String,
     pub base_url: String,
+    /// When each exhausted bucket resets, shared by every clone of the client
+    rate_limit_resets: Arc<Mutex<HashMap<String, Instant>>>,
 }
 
 impl ApiClient {
@@ -39,26 +87,89 @@ impl ApiClient {
             http_client,
             auth_token,
             base_url,
+            rate_limit_resets: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    async fn api_request<T: DeserializeOwned>(
+    /// Waits out the reset of the request's bucket if an earlier response exhausted it.
+    async fn wait_for_bucket(&self, bucket: &str) {
+        let reset = self
+            .rate_limit_resets
+            .lock()
+            .ok()
+            .and_then(|mut resets| resets.remove(bucket));
+        if let Some(reset) = reset {
+            time::sleep_until(reset).await;
+        }
+    }
+
+    /// Sends a request, waiting out Discord rate limits instead of failing on them.
+    /// `reason` is recorded in the guild audit log for moderation requests.
+    async fn send_request(
         &self,
         endpoint: &str,
         method: Method,
         body: Option<serde_json::Value>,
-    ) -> Result<T, Error> {
+        reason: Option<&str>,
+    ) -> Result<Response, Error> {
         let url = format!("{}/{}", self.base_url, endpoint);
-        let mut request = self
-            .http_client
-            .request(method, &url)
-            .header("Authorization", self.auth_token.as_str());
-
-        if let Some(data) = body {
-            request = request.json(&data);
+        let bucket = bucket_key(&method, endpoint);
+
+        for _ in 0..MAX_RATE_LIMIT_RETRIES {
+            self.wait_for_bucket(&bucket).await;
+
+            let mut request = self
+                .http_client
+                .request(method.clone(), &url)
+                .header("Authorization", self.auth_token.as_str());
+
+            if let Some(data) = &body {
+                request = request.json(data);
+            }
+
+            if let Some(reason) = reason.filter(|r| !r.is_empty()) {
+                request = request.header("X-Audit-Log-Reason", encode_reason(reason));
+            }
+
+            let response = request.send().await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .ok()
+                    .and_then(|v| v["retry_after"].as_f64())
+                    .unwrap_or(1.0);
+                time::sleep(Duration::from_secs_f64(retry_after)).await;
+                continue;
+            }
+
+            // Bucket exhausted: the next request to it waits for the reset instead of a 429
+            let remaining = header_value(&response, "x-ratelimit-remaining");
+            let reset_after = header_value(&response, "x-ratelimit-reset-after");
+            if remaining == Some(0.0)
+                && let Some(reset_after) = reset_after
+                && let Ok(mut resets) = self.rate_limit_resets.lock()
+            {
+                resets.insert(
+                    bucket,
+                    Instant::now() + Duration::from_secs_f64(reset_after),
+                );
+            }
+
+            return Ok(response);
         }
 
-        let response = request.send().await?;
+        Err(format!("API Error: still rate limited after {MAX_RATE_LIMIT_RETRIES} retries").into())
+    }
+
+    async fn api_request<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        method: Method,
+        body: Option<serde_json::Value>,
+    ) -> Result<T, Error> {
+        let response = self.send_request(endpoint, method, body, None).await?;
         let status = response.status();
 
         if status.is_success() {
@@ -78,17 +189,18 @@ impl ApiClient {
         method: Method,
         body: Option<serde_json::Value>,
     ) -> Result<(), Error> {
-        let url = format!("{}/{}", self.base_url, endpoint);
-        let mut request = self
-            .http_client
-            .request(method, &url)
-            .header("Authorization", self.auth_token.as_str());
-
-        if let Some(data) = body {
-            request = request.json(&data);
-        }
+        self.audited_request(endpoint, method, body, None).await
+    }
 
-        let response = request.send().await?;
+    /// Like `api_request_no_content`, recording `reason` in the guild audit log.
+    async fn audited_request(
+        &self,
+        endpoint: &str,
+        method: Method,
+        body: Option<serde_json::Value>,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        let response = self.send_request(endpoint, method, body, reason).await?;
         let status = response.status();
 
         if status.is_success() {
@@ -111,6 +223,11 @@ impl ApiClient {
             .await
     }
 
+    pub async fn get_user_settings(&self) -> Result<UserSettings, Error> {
+        self.api_request("users/@me/settings", Method::GET, None)
+            .await
+    }
+
     pub async fn get_dms(&self) -> Result<Vec<DM>, Error> {
         self.api_request("users/@me/channels", Method::GET, None)
             .await
@@ -158,9 +275,60 @@ impl ApiClient {
         .await
     }
 
+    pub async fn get_member(&self, guild_id: &str, user_id: &str) -> Result<GuildMember, Error> {
+        self.api_request(
+            format!("guilds/{guild_id}/members/{user_id}").as_str(),
+            Method::GET,
+            None,
+        )
+        .await
+    }
+
+    /// Only works for accounts allowed to list members, others have to learn them as they go.
+    pub async fn list_guild_members(
+        &self,
+        guild_id: &str,
+        limit: u16,
+    ) -> Result<Vec<GuildMember>, Error> {
+        self.api_request(
+            format!("guilds/{guild_id}/members?limit={limit}").as_str(),
+            Method::GET,
+            None,
+        )
+        .await
+    }
+
+    /// The profile of a user, with their membership of `guild_id` when given.
+    pub async fn get_user_profile(
+        &self,
+        user_id: &str,
+        guild_id: Option<&str>,
+    ) -> Result<UserProfile, Error> {
+        let mut endpoint = format!("users/{user_id}/profile?with_mutual_guilds=true");
+        if let Some(guild_id) = guild_id {
+            endpoint.push_str(&format!("&guild_id={guild_id}"));
+        }
+
+        self.api_request(endpoint.as_str(), Method::GET, None).await
+    }
+
+    /// Our private note about a user. Discord answers 404 when there is none.
+    pub async fn get_user_note(&self, user_id: &str) -> Result<String, Error> {
+        let note: serde_json::Value = self
+            .api_request(
+                format!("users/@me/notes/{user_id}").as_str(),
+                Method::GET,
+                None,
+            )
+            .await?;
+
+        Ok(note["note"].as_str().unwrap_or_default().to_string())
+    }
+
     pub async fn get_permission_context(&self, guild_id: &str) -> Result<PermissionContext, Error> {
         let all_guild_roles: Vec<Role> = self.get_guild_roles(guild_id).await?;
         let member_info: GuildMember = self.get_guild_member(guild_id).await?;
+        let guild = self.get_guild(guild_id).await?;
 
         Ok(PermissionContext {
             user_id: member_info.user.id,
@@ -174,6 +342,7 @@ impl ApiClient {
             },
             all_guild_roles,
             everyone_role_id: guild_id.to_string(),
+            owner_id: guild.owner_id,
         })
     }
 
@@ -205,11 +374,18 @@ impl ApiClient {
         .await
     }
 
-    pub async fn delete_message(&self, channel_id: &str, message_id: &str) -> Result<(), Error> {
-        self.api_request_no_content(
+    /// `reason` is recorded in the audit log when deleting someone else's message.
+    pub async fn delete_message(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
             format!("channels/{channel_id}/messages/{message_id}").as_str(),
             Method::DELETE,
             None,
+            reason,
         )
         .await
     }
@@ -268,4 +444,377 @@ impl ApiClient {
         self.api_request("/users/@me/guilds", Method::GET, None)
             .await
     }
+
+    pub async fn bulk_delete_messages(
+        &self,
+        channel_id: &str,
+        message_ids: &[String],
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
+            format!("channels/{channel_id}/messages/bulk-delete").as_str(),
+            Method::POST,
+            Some(serde_json::json!({ "messages": message_ids })),
+            reason,
+        )
+        .await
+    }
+
+    pub async fn kick_member(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
+            format!("guilds/{guild_id}/members/{user_id}").as_str(),
+            Method::DELETE,
+            None,
+            reason,
+        )
+        .await
+    }
+
+    pub async fn ban_member(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        delete_message_seconds: u64,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
+            format!("guilds/{guild_id}/bans/{user_id}").as_str(),
+            Method::PUT,
+            Some(serde_json::json!({ "delete_message_seconds": delete_message_seconds })),
+            reason,
+        )
+        .await
+    }
+
+    pub async fn unban_member(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
+            format!("guilds/{guild_id}/bans/{user_id}").as_str(),
+            Method::DELETE,
+            None,
+            reason,
+        )
+        .await
+    }
+
+    /// Times a member out until `until` (ISO 8601), or lifts the timeout with `None`.
+    pub async fn timeout_member(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        until: Option<String>,
+        reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.audited_request(
+            format!("guilds/{guild_id}/members/{user_id}").as_str(),
+            Method::PATCH,
+            Some(serde_json::json!({ "communication_disabled_until": until })),
+            reason,
+        )
+        .await
+    }
+
+    pub async fn get_audit_log(
+        &self,
+        guild_id: &str,
+        user_id: Option<&str>,
+        action_type: Option<u16>,
+        before: Option<&str>,
+    ) -> Result<AuditLog, Error> {
+        let mut query = vec!["limit=50".to_string()];
+
+        if let Some(u) = user_id {
+            query.push(format!("user_id={u}"));
+        }
+        if let Some(a) = action_type {
+            query.push(format!("action_type={a}"));
+        }
+        if let Some(b) = before {
+            query.push(format!("before={b}"));
+        }
+
+        self.api_request(
+            format!("guilds/{guild_id}/audit-logs?{}", query.join("&")).as_str(),
+            Method::GET,
+            None,
+        )
+        .await
+    }
+
+    pub async fn create_guild_channel(
+        &self,
+        guild_id: &str,
+        body: serde_json::Value,
+    ) -> Result<Channel, Error> {
+        self.api_request(
+            format!("guilds/{guild_id}/channels").as_str(),
+            Method::POST,
+            Some(body),
+        )
+        .await
+    }
+
+    pub async fn modify_channel(
+        &self,
+        channel_id: &str,
+        body: serde_json::Value,
+    ) -> Result<Channel, Error> {
+        self.api_request(
+            format!("channels/{channel_id}").as_str(),
+            Method::PATCH,
+            Some(body),
+        )
+        .await
+    }
+
+    pub async fn delete_channel(&self, channel_id: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("channels/{channel_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    /// Moves channels, `positions` being `(channel_id, position)` pairs.
+    pub async fn modify_channel_positions(
+        &self,
+        guild_id: &str,
+        positions: &[(String, i64)],
+    ) -> Result<(), Error> {
+        let body: Vec<serde_json::Value> = positions
+            .iter()
+            .map(|(id, position)| serde_json::json!({ "id": id, "position": position }))
+            .collect();
+
+        self.api_request_no_content(
+            format!("guilds/{guild_id}/channels").as_str(),
+            Method::PATCH,
+            Some(serde_json::Value::Array(body)),
+        )
+        .await
+    }
+
+    pub async fn edit_channel_permissions(
+        &self,
+        channel_id: &str,
+        overwrite: &Overwrite,
+    ) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("channels/{channel_id}/permissions/{}", overwrite.id).as_str(),
+            Method::PUT,
+            Some(serde_json::json!({
+                "type": overwrite.r#type,
+                "allow": overwrite.allow,
+                "deny": overwrite.deny,
+            })),
+        )
+        .await
+    }
+
+    pub async fn delete_channel_permission(
+        &self,
+        channel_id: &str,
+        overwrite_id: &str,
+    ) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("channels/{channel_id}/permissions/{overwrite_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    pub async fn create_guild_role(
+        &self,
+        guild_id: &str,
+        body: serde_json::Value,
+    ) -> Result<Role, Error> {
+        self.api_request(
+            format!("guilds/{guild_id}/roles").as_str(),
+            Method::POST,
+            Some(body),
+        )
+        .await
+    }
+
+    pub async fn modify_guild_role(
+        &self,
+        guild_id: &str,
+        role_id: &str,
+        body: serde_json::Value,
+    ) -> Result<Role, Error> {
+        self.api_request(
+            format!("guilds/{guild_id}/roles/{role_id}").as_str(),
+            Method::PATCH,
+            Some(body),
+        )
+        .await
+    }
+
+    pub async fn delete_guild_role(&self, guild_id: &str, role_id: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("guilds/{guild_id}/roles/{role_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    /// Moves roles, `positions` being `(role_id, position)` pairs. Returns every role of the guild.
+    pub async fn modify_guild_role_positions(
+        &self,
+        guild_id: &str,
+        positions: &[(String, i64)],
+    ) -> Result<Vec<Role>, Error> {
+        let body: Vec<serde_json::Value> = positions
+            .iter()
+            .map(|(id, position)| serde_json::json!({ "id": id, "position": position }))
+            .collect();
+
+        self.api_request(
+            format!("guilds/{guild_id}/roles").as_str(),
+            Method::PATCH,
+            Some(serde_json::Value::Array(body)),
+        )
+        .await
+    }
+
+    pub async fn add_member_role(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        role_id: &str,
+    ) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("guilds/{guild_id}/members/{user_id}/roles/{role_id}").as_str(),
+            Method::PUT,
+            None,
+        )
+        .await
+    }
+
+    pub async fn remove_member_role(
+        &self,
+        guild_id: &str,
+        user_id: &str,
+        role_id: &str,
+    ) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("guilds/{guild_id}/members/{user_id}/roles/{role_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    pub async fn get_relationships(&self) -> Result<Vec<Relationship>, Error> {
+        self.api_request("users/@me/relationships", Method::GET, None)
+            .await
+    }
+
+    pub async fn send_friend_request(&self, username: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            "users/@me/relationships",
+            Method::POST,
+            Some(serde_json::json!({ "username": username, "discriminator": null })),
+        )
+        .await
+    }
+
+    /// Accepts a friend request, or blocks the user with `RELATIONSHIP_BLOCKED`.
+    pub async fn put_relationship(&self, user_id: &str, kind: Option<u8>) -> Result<(), Error> {
+        let body = match kind {
+            Some(kind) => serde_json::json!({ "type": kind }),
+            None => serde_json::json!({}),
+        };
+
+        self.api_request_no_content(
+            format!("users/@me/relationships/{user_id}").as_str(),
+            Method::PUT,
+            Some(body),
+        )
+        .await
+    }
+
+    /// Removes a friend, declines or cancels a request, or unblocks.
+    pub async fn delete_relationship(&self, user_id: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("users/@me/relationships/{user_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    /// Opens the DM with `recipients`, a group DM when there are several of them.
+    pub async fn create_dm(&self, recipients: &[String]) -> Result<DM, Error> {
+        self.api_request(
+            "users/@me/channels",
+            Method::POST,
+            Some(serde_json::json!({ "recipients": recipients })),
+        )
+        .await
+    }
+
+    /// Renames a group DM, `None` going back to the list of recipients.
+    pub async fn rename_group_dm(&self, channel_id: &str, name: Option<&str>) -> Result<DM, Error> {
+        self.api_request(
+            format!("channels/{channel_id}").as_str(),
+            Method::PATCH,
+            Some(serde_json::json!({ "name": name })),
+        )
+        .await
+    }
+
+    pub async fn add_group_recipient(&self, channel_id: &str, user_id: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("channels/{channel_id}/recipients/{user_id}").as_str(),
+            Method::PUT,
+            Some(serde_json::json!({})),
+        )
+        .await
+    }
+
+    pub async fn remove_group_recipient(
+        &self,
+        channel_id: &str,
+        user_id: &str,
+    ) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("channels/{channel_id}/recipients/{user_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
+
+    /// Our recent mentions across every guild, newest first.
+    pub async fn get_mentions(&self, limit: usize) -> Result<Vec<Message>, Error> {
+        self.api_request(
+            format!("users/@me/mentions?limit={limit}&roles=true&everyone=true").as_str(),
+            Method::GET,
+            None,
+        )
+        .await
+    }
+
+    /// Removes a message from our recent mentions.
+    pub async fn delete_mention(&self, message_id: &str) -> Result<(), Error> {
+        self.api_request_no_content(
+            format!("users/@me/mentions/{message_id}").as_str(),
+            Method::DELETE,
+            None,
+        )
+        .await
+    }
 }
@@ -0,0 +1,654 @@
+use bitflags::bitflags;
+
+use crate::api::channel::{Overwrite, PermissionContext, Role};
+
+bitflags! {
+    /// Every Discord permission bit, see
+    /// https://discord.com/developers/docs/topics/permissions#permissions-bitwise-permission-flags
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Permissions: u64 {
+        const CREATE_INSTANT_INVITE = 1 << 0;
+        const KICK_MEMBERS = 1 << 1;
+        const BAN_MEMBERS = 1 << 2;
+        const ADMINISTRATOR = 1 << 3;
+        const MANAGE_CHANNELS = 1 << 4;
+        const MANAGE_GUILD = 1 << 5;
+        const ADD_REACTIONS = 1 << 6;
+        const VIEW_AUDIT_LOG = 1 << 7;
+        const PRIORITY_SPEAKER = 1 << 8;
+        const STREAM = 1 << 9;
+        const VIEW_CHANNEL = 1 << 10;
+        const SEND_MESSAGES = 1 << 11;
+        const SEND_TTS_MESSAGES = 1 << 12;
+        const MANAGE_MESSAGES = 1 << 13;
+        const EMBED_LINKS = 1 << 14;
+        const ATTACH_FILES = 1 << 15;
+        const READ_MESSAGE_HISTORY = 1 << 16;
+        const MENTION_EVERYONE = 1 << 17;
+        const USE_EXTERNAL_EMOJIS = 1 << 18;
+        const VIEW_GUILD_INSIGHTS = 1 << 19;
+        const CONNECT = 1 << 20;
+        const SPEAK = 1 << 21;
+        const MUTE_MEMBERS = 1 << 22;
+        const DEAFEN_MEMBERS = 1 << 23;
+        const MOVE_MEMBERS = 1 << 24;
+        const USE_VAD = 1 << 25;
+        const CHANGE_NICKNAME = 1 << 26;
+        const MANAGE_NICKNAMES = 1 << 27;
+        const MANAGE_ROLES = 1 << 28;
+        const MANAGE_WEBHOOKS = 1 << 29;
+        const MANAGE_GUILD_EXPRESSIONS = 1 << 30;
+        const USE_APPLICATION_COMMANDS = 1 << 31;
+        const REQUEST_TO_SPEAK = 1 << 32;
+        const MANAGE_EVENTS = 1 << 33;
+        const MANAGE_THREADS = 1 << 34;
+        const CREATE_PUBLIC_THREADS = 1 << 35;
+        const CREATE_PRIVATE_THREADS = 1 << 36;
+        const USE_EXTERNAL_STICKERS = 1 << 37;
+        const SEND_MESSAGES_IN_THREADS = 1 << 38;
+        const USE_EMBEDDED_ACTIVITIES = 1 << 39;
+        const MODERATE_MEMBERS = 1 << 40;
+        const VIEW_CREATOR_MONETIZATION_ANALYTICS = 1 << 41;
+        const USE_SOUNDBOARD = 1 << 42;
+        const CREATE_GUILD_EXPRESSIONS = 1 << 43;
+        const CREATE_EVENTS = 1 << 44;
+        const USE_EXTERNAL_SOUNDS = 1 << 45;
+        const SEND_VOICE_MESSAGES = 1 << 46;
+        const SEND_POLLS = 1 << 49;
+        const USE_EXTERNAL_APPS = 1 << 50;
+        const PIN_MESSAGES = 1 << 51;
+        const BYPASS_SLOWMODE = 1 << 52;
+    }
+}
+
+impl Permissions {
+    /// Parses the decimal string the API sends, accepting hex as a fallback.
+    /// Unknown bits are kept so newer permissions survive a round trip.
+    pub fn parse(value: &str) -> Self {
+        let bits = value
+            .parse::<u64>()
+            .unwrap_or_else(|_| u64::from_str_radix(value, 16).unwrap_or(0));
+        Self::from_bits_retain(bits)
+    }
+
+    /// Permissions that only make sense when the member can send messages.
+    fn requiring_send_messages() -> Self {
+        Self::SEND_TTS_MESSAGES | Self::MENTION_EVERYONE | Self::EMBED_LINKS | Self::ATTACH_FILES
+    }
+}
+
+fn is_owner(context: &PermissionContext) -> bool {
+    context
+        .owner_id
+        .as_ref()
+        .is_some_and(|owner_id| owner_id == &context.user_id)
+}
+
+fn everyone_permissions(context: &PermissionContext) -> Permissions {
+    context
+        .all_guild_roles
+        .iter()
+        .find(|r| r.id == context.everyone_role_id)
+        .map(|r| Permissions::parse(&r.permissions))
+        .unwrap_or_default()
+}
+
+/// The member's roles besides @everyone.
+fn member_roles(context: &PermissionContext) -> impl Iterator<Item = &Role> {
+    context
+        .all_guild_roles
+        .iter()
+        .filter(|r| r.id != context.everyone_role_id && context.user_role_ids.contains(&r.id))
+}
+
+fn everyone_overwrite<'a>(
+    overwrites: &'a [Overwrite],
+    context: &PermissionContext,
+) -> Option<&'a Overwrite> {
+    overwrites
+        .iter()
+        .find(|o| o.r#type == 0 && o.id == context.everyone_role_id)
+}
+
+/// Overwrites for the member's roles besides @everyone.
+fn role_overwrites<'a>(
+    overwrites: &'a [Overwrite],
+    context: &'a PermissionContext,
+) -> impl Iterator<Item = &'a Overwrite> {
+    overwrites.iter().filter(|o| {
+        o.r#type == 0 && o.id != context.everyone_role_id && context.user_role_ids.contains(&o.id)
+    })
+}
+
+fn member_overwrite<'a>(
+    overwrites: &'a [Overwrite],
+    context: &PermissionContext,
+) -> Option<&'a Overwrite> {
+    overwrites
+        .iter()
+        .find(|o| o.r#type == 1 && o.id == context.user_id)
+}
+
+/// The allow and deny bits of an overwrite.
+fn overwrite_bits(overwrite: &Overwrite) -> (Permissions, Permissions) {
+    (
+        Permissions::parse(&overwrite.allow),
+        Permissions::parse(&overwrite.deny),
+    )
+}
+
+/// Denies first, so what is both allowed and denied ends up allowed.
+fn apply_overwrite(permissions: Permissions, allow: Permissions, deny: Permissions) -> Permissions {
+    (permissions & !deny) | allow
+}
+
+/// Guild-wide permissions: owner, then @everyone and every role of the member, then ADMINISTRATOR.
+pub fn compute_base_permissions(context: &PermissionContext) -> Permissions {
+    if is_owner(context) {
+        return Permissions::all();
+    }
+
+    let permissions = member_roles(context).fold(everyone_permissions(context), |p, role| {
+        p | Permissions::parse(&role.permissions)
+    });
+
+    if permissions.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::all();
+    }
+
+    permissions
+}
+
+/// Applies channel overwrites on top of `base`, in Discord's order:
+/// @everyone, then all of the member's roles together, then the member itself.
+pub fn compute_overwrites(
+    base: Permissions,
+    overwrites: &[Overwrite],
+    context: &PermissionContext,
+) -> Permissions {
+    if base.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::all();
+    }
+
+    let mut permissions = base;
+
+    if let Some(overwrite) = everyone_overwrite(overwrites, context) {
+        let (allow, deny) = overwrite_bits(overwrite);
+        permissions = apply_overwrite(permissions, allow, deny);
+    }
+
+    let (role_allow, role_deny) = role_overwrites(overwrites, context)
+        .map(overwrite_bits)
+        .fold((Permissions::empty(), Permissions::empty()), |acc, bits| {
+            (acc.0 | bits.0, acc.1 | bits.1)
+        });
+    permissions = apply_overwrite(permissions, role_allow, role_deny);
+
+    if let Some(overwrite) = member_overwrite(overwrites, context) {
+        let (allow, deny) = overwrite_bits(overwrite);
+        permissions = apply_overwrite(permissions, allow, deny);
+    }
+
+    apply_implicit_permissions(permissions)
+}
+
+/// Without VIEW_CHANNEL nothing else applies, without SEND_MESSAGES nothing that needs sending does.
+fn apply_implicit_permissions(mut permissions: Permissions) -> Permissions {
+    if !permissions.contains(Permissions::VIEW_CHANNEL) {
+        return Permissions::empty();
+    }
+
+    if !permissions.contains(Permissions::SEND_MESSAGES) {
+        permissions &= !Permissions::requiring_send_messages();
+    }
+
+    permissions
+}
+
+pub fn compute_permissions(context: &PermissionContext, overwrites: &[Overwrite]) -> Permissions {
+    compute_overwrites(compute_base_permissions(context), overwrites, context)
+}
+
+/// One stage of the permission computation, for explaining the result to the user.
+#[derive(Debug, Clone)]
+pub struct PermissionStep {
+    pub label: String,
+    pub allow: Permissions,
+    pub deny: Permissions,
+    /// Permissions after this step, `None` for steps that are only applied combined with others
+    pub result: Option<Permissions>,
+}
+
+impl PermissionStep {
+    fn new(
+        label: String,
+        allow: Permissions,
+        deny: Permissions,
+        result: Option<Permissions>,
+    ) -> Self {
+        Self {
+            label,
+            allow,
+            deny,
+            result,
+        }
+    }
+}
+
+/// Replays `compute_permissions` step by step, through the same stages, recording what
+/// every stage changed.
+pub fn explain_permissions(
+    context: &PermissionContext,
+    overwrites: &[Overwrite],
+) -> (Vec<PermissionStep>, Permissions) {
+    let role_name = |id: &str| {
+        context
+            .all_guild_roles
+            .iter()
+            .find(|r| r.id == id)
+            .map(|r| r.name.clone())
+            .unwrap_or(id.to_string())
+    };
+
+    let mut steps = Vec::new();
+
+    let everyone = everyone_permissions(context);
+    let mut permissions = everyone;
+    steps.push(PermissionStep::new(
+        "Base permissions of @everyone".to_string(),
+        everyone,
+        Permissions::empty(),
+        Some(permissions),
+    ));
+
+    for role in member_roles(context) {
+        let role_permissions = Permissions::parse(&role.permissions);
+        permissions |= role_permissions;
+        steps.push(PermissionStep::new(
+            format!("Role {}", role.name),
+            role_permissions,
+            Permissions::empty(),
+            Some(permissions),
+        ));
+    }
+
+    if is_owner(context) {
+        steps.push(PermissionStep::new(
+            "Server owner: every permission, overwrites are ignored".to_string(),
+            Permissions::all(),
+            Permissions::empty(),
+            Some(Permissions::all()),
+        ));
+        return (steps, Permissions::all());
+    }
+
+    if permissions.contains(Permissions::ADMINISTRATOR) {
+        steps.push(PermissionStep::new(
+            "ADMINISTRATOR: every permission, overwrites are ignored".to_string(),
+            Permissions::all(),
+            Permissions::empty(),
+            Some(Permissions::all()),
+        ));
+        return (steps, Permissions::all());
+    }
+
+    if let Some(overwrite) = everyone_overwrite(overwrites, context) {
+        let (allow, deny) = overwrite_bits(overwrite);
+        permissions = apply_overwrite(permissions, allow, deny);
+        steps.push(PermissionStep::new(
+            "Channel overwrite for @everyone".to_string(),
+            allow,
+            deny,
+            Some(permissions),
+        ));
+    }
+
+    let mut role_allow = Permissions::empty();
+    let mut role_deny = Permissions::empty();
+    for overwrite in role_overwrites(overwrites, context) {
+        let (allow, deny) = overwrite_bits(overwrite);
+        role_allow |= allow;
+        role_deny |= deny;
+        steps.push(PermissionStep::new(
+            format!("Channel overwrite for role {}", role_name(&overwrite.id)),
+            allow,
+            deny,
+            None,
+        ));
+    }
+    if role_overwrites(overwrites, context).next().is_some() {
+        permissions = apply_overwrite(permissions, role_allow, role_deny);
+        steps.push(PermissionStep::new(
+            "Role overwrites combined (allows win over denies)".to_string(),
+            role_allow,
+            role_deny,
+            Some(permissions),
+        ));
+    }
+
+    if let Some(overwrite) = member_overwrite(overwrites, context) {
+        let (allow, deny) = overwrite_bits(overwrite);
+        permissions = apply_overwrite(permissions, allow, deny);
+        steps.push(PermissionStep::new(
+            "Channel overwrite for you".to_string(),
+            allow,
+            deny,
+            Some(permissions),
+        ));
+    }
+
+    let implicit = apply_implicit_permissions(permissions);
+    if implicit != permissions {
+        let label = if permissions.contains(Permissions::VIEW_CHANNEL) {
+            "Implicit: no SEND_MESSAGES removes what depends on it"
+        } else {
+            "Implicit: no VIEW_CHANNEL removes everything"
+        };
+        steps.push(PermissionStep::new(
+            label.to_string(),
+            Permissions::empty(),
+            permissions & !implicit,
+            Some(implicit),
+        ));
+    }
+
+    (steps, implicit)
+}
+
+/// Whether a channel's overwrites are identical to its category's, as Discord shows "synced".
+pub fn is_synced(overwrites: &[Overwrite], category_overwrites: &[Overwrite]) -> bool {
+    let key = |o: &Overwrite| {
+        (
+            o.id.clone(),
+            o.r#type,
+            Permissions::parse(&o.allow),
+            Permissions::parse(&o.deny),
+        )
+    };
+    let mut a: Vec<_> = overwrites.iter().map(key).collect();
+    let mut b: Vec<_> = category_overwrites.iter().map(key).collect();
+    a.sort_by(|x, y| x.0.cmp(&y.0));
+    b.sort_by(|x, y| x.0.cmp(&y.0));
+    a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: &str = "100";
+    const ME: &str = "200";
+    const MODS: &str = "300";
+    const MUTED: &str = "400";
+
+    fn role(id: &str, permissions: Permissions) -> Role {
+        Role {
+            id: id.to_string(),
+            name: id.to_string(),
+            permissions: permissions.bits().to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn overwrite(id: &str, r#type: u8, allow: Permissions, deny: Permissions) -> Overwrite {
+        Overwrite {
+            id: id.to_string(),
+            r#type,
+            allow: allow.bits().to_string(),
+            deny: deny.bits().to_string(),
+        }
+    }
+
+    fn context(roles: &[&str], everyone: Permissions) -> PermissionContext {
+        PermissionContext {
+            user_id: ME.to_string(),
+            user_role_ids: roles
+                .iter()
+                .map(|r| r.to_string())
+                .chain(std::iter::once(GUILD.to_string()))
+                .collect(),
+            all_guild_roles: vec![
+                role(GUILD, everyone),
+                role(
+                    MODS,
+                    Permissions::MANAGE_MESSAGES | Permissions::KICK_MEMBERS,
+                ),
+                role(MUTED, Permissions::empty()),
+            ],
+            everyone_role_id: GUILD.to_string(),
+            owner_id: Some("999".to_string()),
+        }
+    }
+
+    /// `compute_permissions`, checking that the explanation reaches the same result.
+    fn computed(context: &PermissionContext, overwrites: &[Overwrite]) -> Permissions {
+        let computed = compute_permissions(context, overwrites);
+        let (steps, explained) = explain_permissions(context, overwrites);
+        assert_eq!(explained, computed);
+        assert_eq!(steps.last().and_then(|s| s.result), Some(computed));
+        computed
+    }
+
+    fn member() -> Permissions {
+        Permissions::VIEW_CHANNEL
+            | Permissions::SEND_MESSAGES
+            | Permissions::ADD_REACTIONS
+            | Permissions::ATTACH_FILES
+            | Permissions::READ_MESSAGE_HISTORY
+    }
+
+    #[test]
+    fn base_combines_everyone_and_member_roles() {
+        let base = compute_base_permissions(&context(&[MODS], member()));
+        assert_eq!(
+            base,
+            member() | Permissions::MANAGE_MESSAGES | Permissions::KICK_MEMBERS
+        );
+    }
+
+    #[test]
+    fn roles_the_member_lacks_are_ignored() {
+        let base = compute_base_permissions(&context(&[], member()));
+        assert_eq!(base, member());
+    }
+
+    #[test]
+    fn owner_has_everything() {
+        let mut context = context(&[], Permissions::empty());
+        context.owner_id = Some(ME.to_string());
+        let deny_all = [overwrite(
+            GUILD,
+            0,
+            Permissions::empty(),
+            Permissions::all(),
+        )];
+
+        assert_eq!(computed(&context, &deny_all), Permissions::all());
+    }
+
+    #[test]
+    fn administrator_ignores_overwrites() {
+        let context = context(&[], member() | Permissions::ADMINISTRATOR);
+        let overwrites = [
+            overwrite(GUILD, 0, Permissions::empty(), Permissions::all()),
+            overwrite(ME, 1, Permissions::empty(), Permissions::all()),
+        ];
+
+        assert_eq!(computed(&context, &overwrites), Permissions::all());
+    }
+
+    #[test]
+    fn administrator_from_a_role() {
+        let mut context = context(&[MODS], member());
+        context.all_guild_roles[1] = role(MODS, Permissions::ADMINISTRATOR);
+
+        assert_eq!(compute_base_permissions(&context), Permissions::all());
+    }
+
+    #[test]
+    fn everyone_overwrite_denies_then_allows() {
+        let context = context(&[], member());
+        let overwrites = [overwrite(
+            GUILD,
+            0,
+            Permissions::EMBED_LINKS,
+            Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS,
+        )];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(!permissions.contains(Permissions::SEND_MESSAGES));
+        assert!(!permissions.contains(Permissions::ADD_REACTIONS));
+        // EMBED_LINKS is allowed but implicitly removed without SEND_MESSAGES
+        assert!(!permissions.contains(Permissions::EMBED_LINKS));
+        assert!(permissions.contains(Permissions::READ_MESSAGE_HISTORY));
+    }
+
+    #[test]
+    fn role_allow_beats_role_deny() {
+        let context = context(&[MODS, MUTED], member());
+        let overwrites = [
+            overwrite(MUTED, 0, Permissions::empty(), Permissions::SEND_MESSAGES),
+            overwrite(MODS, 0, Permissions::SEND_MESSAGES, Permissions::empty()),
+        ];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(permissions.contains(Permissions::SEND_MESSAGES));
+    }
+
+    #[test]
+    fn role_overwrite_beats_everyone_overwrite() {
+        let context = context(&[MODS], member());
+        let overwrites = [
+            overwrite(GUILD, 0, Permissions::empty(), Permissions::VIEW_CHANNEL),
+            overwrite(MODS, 0, Permissions::VIEW_CHANNEL, Permissions::empty()),
+        ];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(permissions.contains(Permissions::VIEW_CHANNEL));
+    }
+
+    #[test]
+    fn member_overwrite_beats_role_overwrite() {
+        let context = context(&[MODS], member());
+        let overwrites = [
+            overwrite(MODS, 0, Permissions::SEND_MESSAGES, Permissions::empty()),
+            overwrite(ME, 1, Permissions::empty(), Permissions::SEND_MESSAGES),
+        ];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(!permissions.contains(Permissions::SEND_MESSAGES));
+    }
+
+    #[test]
+    fn member_overwrite_matches_the_user_id() {
+        let context = context(&[MODS], member());
+        // A member overwrite for someone whose id happens to be one of our roles
+        let overwrites = [overwrite(
+            MODS,
+            1,
+            Permissions::empty(),
+            Permissions::VIEW_CHANNEL,
+        )];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(permissions.contains(Permissions::VIEW_CHANNEL));
+    }
+
+    #[test]
+    fn overwrites_for_other_roles_are_ignored() {
+        let context = context(&[], member());
+        let overwrites = [overwrite(
+            MUTED,
+            0,
+            Permissions::empty(),
+            Permissions::all(),
+        )];
+
+        assert_eq!(computed(&context, &overwrites), member());
+    }
+
+    #[test]
+    fn no_view_channel_means_no_permissions() {
+        let context = context(&[MODS], member());
+        let overwrites = [overwrite(
+            GUILD,
+            0,
+            Permissions::empty(),
+            Permissions::VIEW_CHANNEL,
+        )];
+
+        assert_eq!(computed(&context, &overwrites), Permissions::empty());
+    }
+
+    #[test]
+    fn no_send_messages_strips_dependent_permissions() {
+        let context = context(&[], member() | Permissions::MENTION_EVERYONE);
+        let overwrites = [overwrite(
+            GUILD,
+            0,
+            Permissions::empty(),
+            Permissions::SEND_MESSAGES,
+        )];
+
+        let permissions = computed(&context, &overwrites);
+        assert!(!permissions.contains(Permissions::ATTACH_FILES));
+        assert!(!permissions.contains(Permissions::MENTION_EVERYONE));
+        assert!(permissions.contains(Permissions::ADD_REACTIONS));
+    }
+
+    #[test]
+    fn parses_decimal_and_hex_strings() {
+        assert_eq!(Permissions::parse("1024"), Permissions::VIEW_CHANNEL);
+        assert_eq!(
+            Permissions::parse("4c00"),
+            Permissions::from_bits_retain(0x4c00)
+        );
+        assert_eq!(Permissions::parse("garbage"), Permissions::empty());
+    }
+
+    #[test]
+    fn explanation_matches_the_computation() {
+        let context = context(&[MODS, MUTED], member());
+        let overwrites = [
+            overwrite(GUILD, 0, Permissions::empty(), Permissions::SEND_MESSAGES),
+            overwrite(MUTED, 0, Permissions::empty(), Permissions::ADD_REACTIONS),
+            overwrite(MODS, 0, Permissions::SEND_MESSAGES, Permissions::empty()),
+            overwrite(ME, 1, Permissions::empty(), Permissions::ATTACH_FILES),
+        ];
+
+        let (steps, result) = explain_permissions(&context, &overwrites);
+        assert_eq!(result, compute_permissions(&context, &overwrites));
+        assert_eq!(steps.last().and_then(|s| s.result), Some(result));
+    }
+
+    #[test]
+    fn explanation_stops_at_administrator() {
+        let context = context(&[], member() | Permissions::ADMINISTRATOR);
+        let overwrites = [overwrite(
+            GUILD,
+            0,
+            Permissions::empty(),
+            Permissions::all(),
+        )];
+
+        let (steps, result) = explain_permissions(&context, &overwrites);
+        assert_eq!(result, Permissions::all());
+        assert!(
+            steps
+                .last()
+                .is_some_and(|s| s.label.starts_with("ADMINISTRATOR"))
+        );
+    }
+
+    #[test]
+    fn sync_ignores_overwrite_order() {
+        let a = [
+            overwrite(GUILD, 0, Permissions::empty(), Permissions::VIEW_CHANNEL),
+            overwrite(MODS, 0, Permissions::VIEW_CHANNEL, Permissions::empty()),
+        ];
+        let b = [a[1].clone(), a[0].clone()];
+
+        assert!(is_synced(&a, &b));
+        assert!(!is_synced(&a, &a[..1]));
+    }
+}
@@ -1,11 +1,192 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+use crate::api::guild::GuildMember;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct User {
     pub id: String,
     pub username: String,
-    //pub discriminator: String,
+    /// "0" for users on the new username system
+    #[serde(default)]
+    pub discriminator: Option<String>,
     pub global_name: Option<String>,
-    //pub avatar : Option<String>,
-    //pub bot: Option<bool>,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub bot: bool,
+}
+
+impl User {
+    /// `name#1234` for legacy accounts, the bare username otherwise.
+    pub fn tag(&self) -> String {
+        match self.discriminator.as_deref() {
+            Some(discriminator) if discriminator != "0" => {
+                format!("{}#{discriminator}", self.username)
+            }
+            _ => self.username.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProfileMetadata {
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub pronouns: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MutualGuild {
+    pub id: String,
+    #[serde(default)]
+    pub nick: Option<String>,
+}
+
+/// What `/users/{id}/profile` returns.
+#[derive(Debug, Deserialize, Clone)]
+pub struct UserProfile {
+    pub user: User,
+    #[serde(default)]
+    pub user_profile: Option<ProfileMetadata>,
+    #[serde(default)]
+    pub mutual_guilds: Vec<MutualGuild>,
+    /// Only with a `guild_id` query
+    #[serde(default)]
+    pub guild_member: Option<GuildMember>,
+    #[serde(default)]
+    pub premium_since: Option<String>,
+}
+
+pub const RELATIONSHIP_FRIEND: u8 = 1;
+pub const RELATIONSHIP_BLOCKED: u8 = 2;
+pub const RELATIONSHIP_INCOMING: u8 = 3;
+pub const RELATIONSHIP_OUTGOING: u8 = 4;
+
+/// A friend, a pending friend request or a blocked user.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Relationship {
+    /// The other user's id
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub user: User,
+    #[serde(default)]
+    pub nickname: Option<String>,
+}
+
+/// A folder of the server list. Guilds outside of any folder come as folders of one
+/// guild without an id.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GuildFolder {
+    /// A number for real folders, `null` otherwise
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<u32>,
+    pub guild_ids: Vec<String>,
+}
+
+impl GuildFolder {
+    /// Stable key of a real folder, used in the config.
+    pub fn key(&self) -> Option<String> {
+        match self.id.as_ref()? {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(id) => Some(id.clone()),
+            id => Some(id.to_string()),
+        }
+    }
+
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        self.color
+            .map(|c| ((c >> 16) as u8, (c >> 8) as u8, c as u8))
+    }
+}
+
+/// The parts of `/users/@me/settings` we use.
+#[derive(Debug, Deserialize, Clone)]
+pub struct UserSettings {
+    #[serde(default)]
+    pub guild_folders: Vec<GuildFolder>,
+    /// Older accounts only have a flat order
+    #[serde(default)]
+    pub guild_positions: Vec<String>,
+    /// Our own presence: online, idle, dnd or invisible
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+impl UserSettings {
+    pub fn into_folders(self) -> Vec<GuildFolder> {
+        if !self.guild_folders.is_empty() {
+            return self.guild_folders;
+        }
+        self.guild_positions
+            .into_iter()
+            .map(|guild_id| GuildFolder {
+                id: None,
+                name: None,
+                color: None,
+                guild_ids: vec![guild_id],
+            })
+            .collect()
+    }
+}
+
+/// How far we read a channel, from READY's `read_state`.
+#[derive(Debug, Clone)]
+pub struct ReadState {
+    pub channel_id: String,
+    pub last_message_id: Option<String>,
+    pub mention_count: u32,
+}
+
+pub const NOTIFY_ALL: u8 = 0;
+pub const NOTIFY_MENTIONS: u8 = 1;
+pub const NOTIFY_NOTHING: u8 = 2;
+/// Use the level of the layer above: the guild's for channel overrides, the server's
+/// default for guilds
+pub const NOTIFY_INHERIT: u8 = 3;
+
+fn notify_inherit() -> u8 {
+    NOTIFY_INHERIT
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct MuteConfig {
+    /// RFC 3339, `None` while muted until turned back on
+    #[serde(default)]
+    pub end_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ChannelOverride {
+    pub channel_id: String,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub mute_config: Option<MuteConfig>,
+    #[serde(default = "notify_inherit")]
+    pub message_notifications: u8,
+}
+
+/// Notification settings of a guild, or of DMs when `guild_id` is `None`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GuildSettings {
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub mute_config: Option<MuteConfig>,
+    #[serde(default = "notify_inherit")]
+    pub message_notifications: u8,
+    #[serde(default)]
+    pub suppress_everyone: bool,
+    #[serde(default)]
+    pub suppress_roles: bool,
+    #[serde(default)]
+    pub channel_overrides: Vec<ChannelOverride>,
 }
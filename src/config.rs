@@ -1,4 +1,6 @@
-use crate::{logs::LogType, print_log};
+use std::collections::HashMap;
+
+use crate::{logs::LogType, notifier::NotifierConfig, print_log};
 use serde::{Deserialize, Serialize};
 
 const DEFAULT_EMOJIS_JSON: &str = include_str!("../emojis.json");
@@ -12,7 +14,94 @@ pub struct Config {
     pub discreet_notifs: bool,
     #[serde(default)]
     pub silent_typing: bool,
+    /// Let `/` and `?` search the on-disk cache beyond the loaded messages
+    #[serde(default)]
+    pub search_index: bool,
     pub emoji_map: Vec<(String, String)>,
+    /// Guild ids shown first in the server list
+    #[serde(default)]
+    pub pinned_guilds: Vec<String>,
+    /// Local order of the server list: guild ids and `folder:<id>` keys
+    #[serde(default)]
+    pub guild_order: Vec<String>,
+    /// Folder ids collapsed in the server list
+    #[serde(default)]
+    pub collapsed_folders: Vec<String>,
+    /// Local notification settings by guild or channel id, over Discord's
+    #[serde(default)]
+    pub notifications: HashMap<String, NotificationOverride>,
+    /// Keywords that notify like mentions and are highlighted in the chat
+    #[serde(default)]
+    pub highlights: Vec<HighlightRule>,
+    #[serde(default)]
+    pub notifier: NotifierConfig,
+    /// Times of day when nothing notifies
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+    /// Keep notifying while our Discord status is Do Not Disturb
+    #[serde(default)]
+    pub notify_when_dnd: bool,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+}
+
+/// The side by side server, channel and chat panes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub panes: bool,
+    pub guild_width: u16,
+    pub channel_width: u16,
+    /// Narrower terminals fall back to one screen at a time
+    pub min_width: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            panes: false,
+            guild_width: 24,
+            channel_width: 30,
+            min_width: 110,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QuietHours {
+    /// Local time as `HH:MM`, `end` before `start` spanning midnight
+    pub start: String,
+    pub end: String,
+    /// Days the quiet hours start on, as `mon` to `sun`, every day when empty
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HighlightRule {
+    /// A whole word matched case-insensitively, or a regex with `regex = true`
+    pub pattern: String,
+    #[serde(default)]
+    pub regex: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyLevel {
+    All,
+    Mentions,
+    Nothing,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NotificationOverride {
+    #[serde(default)]
+    pub level: Option<NotifyLevel>,
+    #[serde(default)]
+    pub muted: bool,
+    /// Unix time the mute ends at, muted until turned back on without it
+    #[serde(default)]
+    pub muted_until: Option<i64>,
 }
 
 fn load_emojis() -> Vec<(String, String)> {
@@ -35,20 +124,36 @@ impl Default for Config {
             vim_mode: true,
             discreet_notifs: false,
             silent_typing: false,
+            search_index: false,
             emoji_map: Vec::new(),
+            pinned_guilds: Vec::new(),
+            guild_order: Vec::new(),
+            collapsed_folders: Vec::new(),
+            notifications: HashMap::new(),
+            highlights: Vec::new(),
+            notifier: NotifierConfig::default(),
+            quiet_hours: Vec::new(),
+            notify_when_dnd: false,
+            layout: LayoutConfig::default(),
         }
     }
 }
 
+const APP_NAME: &str = "vimcord";
+
+/// Writes settings changed from the UI back to the config file.
+pub fn save_config(cfg: &Config) {
+    if let Err(e) = confy::store::<Config>(APP_NAME, "config", cfg.clone()) {
+        let _ = print_log(format!("Error storing config: {e}").into(), LogType::Error);
+    }
+}
+
 pub fn load_config() -> Config {
-    let app_name = "vimcord";
-    match confy::load::<Config>(app_name, "config") {
+    match confy::load::<Config>(APP_NAME, "config") {
         Ok(mut cfg) => {
             if cfg.emoji_map.is_empty() {
                 cfg.emoji_map = load_emojis();
-                if let Err(e) = confy::store::<Config>(app_name, "config", cfg.clone()) {
-                    let _ = print_log(format!("Error storing config: {e}").into(), LogType::Error);
-                }
+                save_config(&cfg);
             }
             cfg
         }
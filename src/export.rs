@@ -0,0 +1,410 @@
+use std::path::PathBuf;
+
+use crate::{
+    Error,
+    api::{ApiClient, Message},
+};
+
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    Html,
+    Text,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            "txt" | "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Text => "txt",
+        }
+    }
+}
+
+/// Pages backwards through the whole history of a channel.
+/// `on_page` receives the running total after every page. Returns messages oldest first.
+pub async fn fetch_history(
+    api_client: &ApiClient,
+    channel_id: &str,
+    mut on_page: impl FnMut(usize),
+) -> Result<Vec<Message>, Error> {
+    let mut history: Vec<Message> = Vec::new();
+    let mut before: Option<String> = None;
+
+    loop {
+        // Rate limits are waited out inside the client, so we can page as fast as allowed
+        let page = api_client
+            .get_channel_messages(channel_id, None, before.clone(), None, Some(PAGE_SIZE))
+            .await?;
+
+        let page_len = page.len();
+        before = page.last().map(|m| m.id.clone());
+        history.extend(page);
+        on_page(history.len());
+
+        if page_len < PAGE_SIZE || before.is_none() {
+            break;
+        }
+    }
+
+    history.reverse();
+    Ok(history)
+}
+
+fn author_name(message: &Message) -> String {
+    message
+        .author
+        .global_name
+        .clone()
+        .unwrap_or(message.author.username.clone())
+}
+
+fn format_timestamp(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S %:z").to_string())
+        .unwrap_or(timestamp.to_string())
+}
+
+fn reply_summary(message: &Message) -> Option<String> {
+    if let Some(referenced) = &message.referenced_message {
+        let content: String = referenced.map_mentions().chars().take(60).collect();
+        return Some(format!("{}: {content}", author_name(referenced)));
+    }
+
+    message
+        .message_reference
+        .as_ref()
+        .and_then(|r| r.message_id.clone())
+        .map(|id| format!("message {id}"))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Backslash-escapes what Markdown would read as emphasis, links, code or HTML in `text`.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '~' | '|' | '[' | ']' | '<' | '>'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn render_text(messages: &[Message]) -> String {
+    let mut out = String::new();
+
+    for message in messages {
+        if let Some(reply) = reply_summary(message) {
+            out.push_str(&format!("  ┌ reply to {reply}\n"));
+        }
+        out.push_str(&format!(
+            "[{}] {}: {}",
+            format_timestamp(&message.timestamp),
+            author_name(message),
+            message.map_mentions()
+        ));
+        if let Some(edited) = &message.edited_timestamp {
+            out.push_str(&format!(" (edited {})", format_timestamp(edited)));
+        }
+        out.push('\n');
+        for attachment in &message.attachments {
+            out.push_str(&format!(
+                "  attachment: {} <{}>\n",
+                attachment.filename, attachment.url
+            ));
+        }
+    }
+
+    out
+}
+
+fn render_markdown(messages: &[Message], channel_name: &str) -> String {
+    let mut out = format!("# {}\n\n", escape_markdown(channel_name));
+
+    for message in messages {
+        out.push_str(&format!(
+            "**{}** · {}",
+            escape_markdown(&author_name(message)),
+            format_timestamp(&message.timestamp)
+        ));
+        if let Some(edited) = &message.edited_timestamp {
+            out.push_str(&format!(" · *edited {}*", format_timestamp(edited)));
+        }
+        out.push('\n');
+
+        if let Some(reply) = reply_summary(message) {
+            out.push_str(&format!("> ↪ {}\n\n", escape_markdown(&reply)));
+        }
+
+        out.push_str(&message.map_mentions());
+        out.push('\n');
+
+        for attachment in &message.attachments {
+            out.push_str(&format!(
+                "- [{}]({})\n",
+                escape_markdown(&attachment.filename),
+                attachment.url
+            ));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn render_html(messages: &[Message], channel_name: &str) -> String {
+    let channel_name = escape_html(channel_name);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{channel_name}</title>\n\
+         <style>body{{font-family:sans-serif}}.message{{margin:0.75em 0}}.meta{{color:#777}}\
+         .reply{{color:#777;border-left:3px solid #ccc;padding-left:0.5em}}\
+         .content{{white-space:pre-wrap}}</style>\n</head>\n<body>\n<h1>{channel_name}</h1>\n"
+    );
+
+    for message in messages {
+        out.push_str(&format!("<div class=\"message\" id=\"{}\">\n", message.id));
+
+        if let Some(reply) = reply_summary(message) {
+            let target = message
+                .message_reference
+                .as_ref()
+                .and_then(|r| r.message_id.clone())
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "<div class=\"reply\"><a href=\"#{target}\">↪ {}</a></div>\n",
+                escape_html(&reply)
+            ));
+        }
+
+        out.push_str(&format!(
+            "<div class=\"meta\"><strong>{}</strong> {}",
+            escape_html(&author_name(message)),
+            escape_html(&format_timestamp(&message.timestamp))
+        ));
+        if let Some(edited) = &message.edited_timestamp {
+            out.push_str(&format!(
+                " <em>(edited {})</em>",
+                escape_html(&format_timestamp(edited))
+            ));
+        }
+        out.push_str("</div>\n");
+
+        out.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape_html(&message.map_mentions())
+        ));
+
+        for attachment in &message.attachments {
+            out.push_str(&format!(
+                "<div class=\"attachment\"><a href=\"{}\">{}</a></div>\n",
+                escape_html(&attachment.url),
+                escape_html(&attachment.filename)
+            ));
+        }
+        out.push_str("</div>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn render(
+    messages: &[Message],
+    format: ExportFormat,
+    channel_name: &str,
+) -> Result<String, Error> {
+    Ok(match format {
+        ExportFormat::Json => serde_json::to_string_pretty(messages)?,
+        ExportFormat::Markdown => render_markdown(messages, channel_name),
+        ExportFormat::Html => render_html(messages, channel_name),
+        ExportFormat::Text => render_text(messages),
+    })
+}
+
+/// `<channel name>-<channel id>.<ext>` in the working directory.
+pub fn default_path(channel_name: &str, channel_id: &str, format: ExportFormat) -> PathBuf {
+    let safe_name: String = channel_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    PathBuf::from(format!("{safe_name}-{channel_id}.{}", format.extension()))
+}
+
+/// Renders `messages` (oldest first) and writes them to `path`, or the default path.
+pub fn write_export(
+    messages: &[Message],
+    channel_id: &str,
+    channel_name: &str,
+    format: ExportFormat,
+    path: Option<PathBuf>,
+) -> Result<PathBuf, Error> {
+    let path = path.unwrap_or_else(|| default_path(channel_name, channel_id, format));
+    std::fs::write(&path, render(messages, format, channel_name)?)?;
+    Ok(path)
+}
+
+/// Downloads the full history of a channel and writes it out.
+/// Returns the written path and the number of exported messages.
+pub async fn export_channel(
+    api_client: &ApiClient,
+    channel_id: &str,
+    channel_name: &str,
+    format: ExportFormat,
+    path: Option<PathBuf>,
+    on_page: impl FnMut(usize),
+) -> Result<(PathBuf, usize), Error> {
+    let messages = fetch_history(api_client, channel_id, on_page).await?;
+    let path = write_export(&messages, channel_id, channel_name, format, path)?;
+    Ok((path, messages.len()))
+}
+
+/// `vimcord export <channel_id> [--format json|md|html|txt] [--output path]`, without the TUI.
+pub async fn run_headless(api_client: &ApiClient, args: &[String]) -> Result<(), Error> {
+    let mut channel_id: Option<&str> = None;
+    let mut format = ExportFormat::Markdown;
+    let mut path: Option<PathBuf> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => {
+                let name = args.next().ok_or("--format needs a value")?;
+                format = ExportFormat::parse(name).ok_or(format!(
+                    "Unknown export format: {name} (json, md, html or txt)"
+                ))?;
+            }
+            "--output" | "-o" => {
+                path = Some(PathBuf::from(args.next().ok_or("--output needs a value")?));
+            }
+            _ if channel_id.is_none() && !arg.starts_with('-') => channel_id = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}").into()),
+        }
+    }
+
+    let channel_id = channel_id
+        .ok_or("Usage: vimcord export <channel_id> [--format json|md|html|txt] [--output path]")?;
+
+    // DMs have no name, so fall back to the id for the file name and heading
+    let channel_name = api_client
+        .get_channel(channel_id)
+        .await
+        .map(|c| c.name)
+        .unwrap_or(channel_id.to_string());
+
+    let (path, count) = export_channel(
+        api_client,
+        channel_id,
+        &channel_name,
+        format,
+        path,
+        |count| {
+            eprint!("\rFetched {count} messages...");
+        },
+    )
+    .await?;
+
+    eprintln!("\rExported {count} messages to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::message::{Attachment, MessageReference},
+        ui::testing::{CHANNEL, FRIEND, ME, message},
+    };
+
+    fn history() -> Vec<Message> {
+        let mut first = message("100", CHANNEL, None, FRIEND, "ship it?");
+        first.author.global_name = Some("*star_[dev]*".to_string());
+        let mut reply = message("101", CHANNEL, None, ME, "<b>shipped</b> & done");
+        reply.edited_timestamp = Some("2026-01-01T00:05:00+00:00".to_string());
+        reply.message_reference = Some(MessageReference {
+            message_id: Some(first.id.clone()),
+            channel_id: Some(CHANNEL.to_string()),
+            guild_id: None,
+        });
+        reply.referenced_message = Some(Box::new(first.clone()));
+        reply.attachments = vec![Attachment {
+            id: "1".to_string(),
+            filename: "log_[1].txt".to_string(),
+            url: "https://cdn.example/log.txt".to_string(),
+        }];
+        vec![first, reply]
+    }
+
+    #[test]
+    fn text_has_replies_edits_and_attachments() {
+        assert_eq!(
+            render_text(&history()),
+            "[2026-01-01 00:00:00 +00:00] *star_[dev]*: ship it?\n\
+             \x20 ┌ reply to *star_[dev]*: ship it?\n\
+             [2026-01-01 00:00:00 +00:00] user1: <b>shipped</b> & done \
+             (edited 2026-01-01 00:05:00 +00:00)\n\
+             \x20 attachment: log_[1].txt <https://cdn.example/log.txt>\n"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_names_but_keeps_message_formatting() {
+        let out = render_markdown(&history(), "dev_chat");
+        assert!(out.starts_with("# dev\\_chat\n\n"));
+        assert!(
+            out.contains("**\\*star\\_\\[dev\\]\\*** · 2026-01-01 00:00:00 +00:00\nship it?\n")
+        );
+        assert!(out.contains("> ↪ \\*star\\_\\[dev\\]\\*: ship it?\n"));
+        assert!(out.contains("*edited 2026-01-01 00:05:00 +00:00*"));
+        assert!(out.contains("- [log\\_\\[1\\].txt](https://cdn.example/log.txt)\n"));
+    }
+
+    #[test]
+    fn html_escapes_names_and_content() {
+        let out = render_html(&history(), "<dev>");
+        assert!(out.contains("<title>&lt;dev&gt;</title>"));
+        assert!(out.contains("<div class=\"content\">&lt;b&gt;shipped&lt;/b&gt; &amp; done</div>"));
+        assert!(out.contains("<a href=\"#100\">↪ *star_[dev]*: ship it?</a>"));
+        assert!(!out.contains("<b>"));
+    }
+
+    #[test]
+    fn html_escaping_covers_quotes() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+}
@@ -39,6 +39,11 @@ pub fn print_log(msg: Error, log_type: LogType) -> Result<(), Error> {
     Ok(())
 }
 
+/// Directory holding the log file, also used for other on-disk caches.
+pub fn get_cache_directory() -> Option<PathBuf> {
+    get_log_directory(APP_NAME)
+}
+
 fn get_log_directory(app_name: &str) -> Option<PathBuf> {
     #[cfg(target_os = "windows")]
     {
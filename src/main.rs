@@ -25,17 +25,47 @@ use tokio::{
 use crate::{
     api::{
         ApiClient, Channel, Emoji, Guild, Message, PartialMessage, User,
-        channel::PermissionContext, dm::DM,
+        audit_log::AuditLog,
+        channel::{PermissionContext, Role},
+        dm::DM,
+        guild::GuildMember,
+        permissions::Permissions,
+        user::{GuildFolder, GuildSettings, ReadState, Relationship, UserProfile},
     },
     logs::{LogType, print_log},
+    notifier::Notifier,
     signals::{restore_terminal, setup_ctrlc_handler},
-    ui::{draw_ui, handle_input_events, handle_keys_events, vim::VimState},
+    store::Store,
+    ui::{
+        audit_log::{AuditLogFilter, AuditLogView},
+        buffers::Buffers,
+        draw_ui,
+        events::HistoryGoal,
+        form::Form,
+        friends::FriendsView,
+        handle_input_events, handle_keys_events,
+        highlights::Highlights,
+        inbox::{InboxEntry, InboxView, PendingJump},
+        layout::PaneLayout,
+        members::MemberList,
+        notifications::Burst,
+        popup::Popup,
+        prompt::Prompt,
+        roles::RoleView,
+        search::SearchState,
+        unread::ReadStates,
+        vim::VimState,
+        windows::Windows,
+    },
 };
 
 mod api;
 mod config;
+mod export;
 mod logs;
+mod notifier;
 mod signals;
+mod store;
 mod ui;
 
 const DISCORD_BASE_URL: &str = "https://discord.com/api/v10";
@@ -81,21 +111,54 @@ pub enum AppAction {
     SelectPrevious,
     SelectLeft,
     SelectRight,
-    ApiDeleteMessage(String, String),
+    ApiDeleteMessage(String, String, Option<String>), // channel_id, message_id, reason
+    ApiBulkDeleteMessages(String, Vec<String>, Option<String>), // channel_id, message_ids, reason
     ApiEditMessage(String, String, String),
     ApiUpdateMessages(String, Vec<Message>),
     ApiUpdateChannel(Vec<Channel>),
     ApiUpdateEmojis(Vec<Emoji>),
     ApiUpdateGuilds(Vec<Guild>),
+    ApiUpdateGuildFolders(Vec<GuildFolder>),
     ApiUpdateDMs(Vec<DM>),
     ApiUpdateContext(Option<PermissionContext>),
     ApiUpdateCurrentUser(User),
+    ApiUpdateAuditLog(String, AuditLogFilter, AuditLog), // guild_id, filter the page was fetched with
+    ApiAuditLogFailed(String, AuditLogFilter, String),   // guild_id, filter, error
+    ApiChannelChanged(Channel),
+    ApiChannelDeleted(String),
+    ApiUpdateRoles(String, Vec<Role>), // guild_id, every role of the guild
+    ApiUpdateMembers(String, Vec<GuildMember>), // guild_id, members
+    ApiOpenMember(String, GuildMember), // guild_id, member to edit the roles of
+    ApiOpenProfile(User, Option<UserProfile>, Option<String>), // user, profile, note
+    ApiUpdateRelationships(Vec<Relationship>),
+    ApiOpenDM(DM),
+    ApiDMChanged(DM),
+    ApiDMLeft(String), // channel_id
+    ApiUpdateMentions(Vec<Message>),
+    ApiInboxGuild(Message, Option<String>), // inbox message to open, guild of its channel
+    ApiUpdateOwnStatus(String),
+    ApiHistoryPage(String, Vec<Message>), // channel_id, an older page of its history
+    HistoryLoaded(String, HistoryGoal),   // channel_id, what the walk back was for
     GatewayMessageCreate(Message),
     GatewayMessageUpdate(PartialMessage),
     GatewayMessageDelete(String, String), // message_id, channel_id
+    GatewayMessageAck(String, String),    // channel_id, message_id
+    GatewayReadState(Vec<ReadState>, Vec<(String, Option<String>, String)>), // read states, (channel_id, guild_id, newest message_id)
     GatewayTypingStart(String, String, Option<String>), // channel_id, user_id, display_name
-    GatewayReadySupplemental(std::collections::HashMap<String, String>), // user_id -> status
-    GatewayPresenceUpdate(String, String), // user_id, status
+    GatewayReadySupplemental(HashMap<String, String>, HashMap<String, String>), // user_id -> status, user_id -> activity
+    GatewayPresenceUpdate(String, String, Option<String>), // user_id, status, activity
+    GatewayRelationships(Vec<Relationship>),
+    GatewayGuildFolders(Vec<GuildFolder>),
+    GatewayGuilds(Vec<Guild>),
+    GatewayGuildSettings(Vec<GuildSettings>),
+    GatewayGuildSettingsUpdate(GuildSettings),
+    GatewayOwnStatus(String),
+    GatewayRelationshipAdd(Relationship),
+    GatewayRelationshipRemove(String), // user_id
+    GatewayDMUpdate(DM),
+    GatewayDMDelete(String),                // channel_id
+    GatewayRecipientAdd(String, User),      // channel_id, user
+    GatewayRecipientRemove(String, String), // channel_id, user_id
     TransitionToChat(String),
     TransitionToEditing(String, Message, String, char),
     TransitionToChannels(String),
@@ -106,7 +169,9 @@ pub enum AppAction {
     TransitionToLoadingMessages,
     EndLoading,
     EndLoadingMessages,
+    UpdateStatus(String),
     SelectEmoji,
+    WindowCommand, // Ctrl-w, the next key moves between panes
     Paste(String),
     Tick,
 }
@@ -122,6 +187,7 @@ pub struct App {
     api_client: ApiClient,
     state: AppState,
     guilds: Vec<Guild>,
+    guild_folders: Vec<GuildFolder>,
     channels: Vec<Channel>,
     messages: Vec<Message>,
     custom_emojis: Vec<Emoji>,
@@ -140,24 +206,151 @@ pub struct App {
     chat_scroll_offset: usize,
     tick_count: usize,
     context: Option<PermissionContext>,
+    /// Our permissions in the open channel, `None` for DMs or when unknown
+    channel_permissions: Option<Permissions>,
+    /// Message where a visual-line selection of messages started
+    visual_anchor: Option<String>,
     mode: InputMode,
     cursor_position: usize,
     vim_mode: bool,
     vim_state: Option<VimState>,
     current_user: Option<User>,
     pub last_message_ids: HashMap<String, String>,
+    read_states: ReadStates,
+    /// Open channel and the last message we had read in it, for the "new messages" divider
+    unread_marker: Option<(String, String)>,
     pub discreet_notifs: bool,
+    /// Discord's notification settings by guild id, "@me" for DMs
+    guild_settings: HashMap<String, GuildSettings>,
+    /// Keywords from the config that notify and are highlighted
+    highlights: Highlights,
     deleted_message_ids: HashSet<String>,
     last_typing_sent: Option<std::time::Instant>,
     typing_users: HashMap<String, HashMap<String, std::time::Instant>>, // channel_id -> user_id -> timestamp
     user_names: HashMap<String, String>,
     user_statuses: HashMap<String, String>, // user id -> status string (online, offline, etc.)
+    user_activities: HashMap<String, String>, // user id -> current activity, e.g. "Playing X"
     silent_typing: bool,
     is_loading: bool,
-    pub active_notifications: HashMap<String, Vec<notify_rust::NotificationHandle>>,
+    notifier: Box<dyn Notifier>,
+    notification_bursts: HashMap<String, Burst>,
+    /// Our Discord status, Do Not Disturb silences notifications
+    own_status: Option<String>,
+    prompt: Option<Prompt>,
+    popup: Option<Popup>,
+    form: Option<Form>,
+    role_view: Option<RoleView>,
+    friends_view: Option<FriendsView>,
+    /// Recent mentions, DMs and keyword hits, newest first
+    inbox: Vec<InboxEntry>,
+    inbox_view: Option<InboxView>,
+    inbox_jump: Option<PendingJump>,
+    relationships: Vec<Relationship>,
+    member_list: MemberList,
+    panes: PaneLayout,
+    /// Channels open as tabs, with the messages of those in the background
+    buffers: Buffers,
+    /// Channels shown side by side or stacked after `:vsplit` or `:split`
+    windows: Windows,
+    members: HashMap<String, HashMap<String, GuildMember>>, // guild_id -> user_id -> member
+    requested_members: HashSet<String>,                     // "guild_id:user_id" already fetched
+    audit_log: Option<AuditLogView>,
+    search: Option<SearchState>,
+    store: Option<Store>,
+    offline: bool,
+    config: config::Config,
+}
+
+impl App {
+    /// A fresh session, with nothing loaded yet.
+    fn new(
+        api_client: ApiClient,
+        config: config::Config,
+        store: Option<Store>,
+        offline: bool,
+        vim_mode: bool,
+    ) -> Self {
+        Self {
+            api_client,
+            state: AppState::Loading(Window::Home),
+            guilds: Vec::new(),
+            guild_folders: Vec::new(),
+            channels: Vec::new(),
+            messages: Vec::new(),
+            custom_emojis: Vec::new(),
+            dms: Vec::new(),
+            input: String::new(),
+            saved_input: None,
+            selection_index: 0,
+            status_message: if offline {
+                "Offline mode: browsing cached DMs and Servers (read-only). Enter to select & Esc to quit"
+                    .to_string()
+            } else {
+                "Browse either DMs or Servers. Use arrows to navigate, Enter to select & Esc to quit"
+                    .to_string()
+            },
+            terminal_height: 20,
+            terminal_width: 80,
+            emoji_map: config.emoji_map.clone(),
+            emoji_filter: String::new(),
+            emoji_filter_start: None,
+            emoji_index: 0,
+            chat_scroll_offset: 0,
+            tick_count: 0,
+            context: None,
+            channel_permissions: None,
+            visual_anchor: None,
+            mode: InputMode::Normal,
+            cursor_position: 0,
+            vim_mode,
+            vim_state: if vim_mode {
+                Some(VimState::default())
+            } else {
+                None
+            },
+            current_user: None,
+            last_message_ids: HashMap::new(),
+            read_states: ReadStates::default(),
+            unread_marker: None,
+            discreet_notifs: config.discreet_notifs,
+            guild_settings: HashMap::new(),
+            highlights: Highlights::new(&config.highlights),
+            deleted_message_ids: HashSet::new(),
+            last_typing_sent: None,
+            typing_users: HashMap::new(),
+            user_names: HashMap::new(),
+            user_statuses: HashMap::new(),
+            user_activities: HashMap::new(),
+            silent_typing: config.silent_typing,
+            is_loading: false,
+            notifier: notifier::from_config(&config.notifier),
+            notification_bursts: HashMap::new(),
+            own_status: None,
+            prompt: None,
+            popup: None,
+            form: None,
+            role_view: None,
+            friends_view: None,
+            inbox: Vec::new(),
+            inbox_view: None,
+            inbox_jump: None,
+            relationships: Vec::new(),
+            member_list: MemberList::default(),
+            panes: PaneLayout::default(),
+            buffers: Buffers::default(),
+            windows: Windows::default(),
+            members: HashMap::new(),
+            requested_members: HashSet::new(),
+            audit_log: None,
+            search: None,
+            store,
+            offline,
+            config,
+        }
+    }
 }
 
-async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
+async fn run_app(token: String, config: config::Config, offline: bool) -> Result<(), Error> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
@@ -166,48 +359,32 @@ async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
 
     let vim_mode = config.vim_mode || env::args().any(|arg| arg == "--vim");
 
+    // Render whatever was cached last session straight away, the API reconciles it below
+    let store = Store::open();
+    let cached_user: Option<User> = store.as_ref().and_then(|s| s.load_value("current_user"));
+    let cached_guilds = store.as_ref().map(|s| s.load_guilds()).unwrap_or_default();
+    let cached_folders: Vec<GuildFolder> = store
+        .as_ref()
+        .and_then(|s| s.load_value("guild_folders"))
+        .unwrap_or_default();
+    let cached_dms = store.as_ref().map(|s| s.load_dms()).unwrap_or_default();
+    let cached_user_names: HashMap<String, String> = store
+        .as_ref()
+        .map(|s| s.load_users())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|u| (u.id, u.username))
+        .collect();
+    let has_cache = !cached_guilds.is_empty() || !cached_dms.is_empty();
+
+    let api_client = ApiClient::new(Client::new(), token.clone(), DISCORD_BASE_URL.to_string());
     let app_state = Arc::new(Mutex::new(App {
-        api_client: ApiClient::new(Client::new(), token.clone(), DISCORD_BASE_URL.to_string()),
-        state: AppState::Loading(Window::Home),
-        guilds: Vec::new(),
-        channels: Vec::new(),
-        messages: Vec::new(),
-        custom_emojis: Vec::new(),
-        dms: Vec::new(),
-        input: String::new(),
-        saved_input: None,
-        selection_index: 0,
-        status_message:
-            "Browse either DMs or Servers. Use arrows to navigate, Enter to select & Esc to quit"
-                .to_string(),
-        terminal_height: 20,
-        terminal_width: 80,
-        emoji_map: config.emoji_map,
-        emoji_filter: String::new(),
-        emoji_filter_start: None,
-        emoji_index: 0,
-        chat_scroll_offset: 0,
-        tick_count: 0,
-        context: None,
-        mode: InputMode::Normal,
-        cursor_position: 0,
-        vim_mode,
-        vim_state: if vim_mode {
-            Some(VimState::default())
-        } else {
-            None
-        },
-        current_user: None,
-        last_message_ids: HashMap::new(),
-        discreet_notifs: config.discreet_notifs,
-        deleted_message_ids: HashSet::new(),
-        last_typing_sent: None,
-        typing_users: HashMap::new(),
-        user_names: HashMap::new(),
-        user_statuses: HashMap::new(),
-        silent_typing: config.silent_typing,
-        is_loading: false,
-        active_notifications: HashMap::new(),
+        guilds: cached_guilds,
+        guild_folders: cached_folders,
+        dms: cached_dms,
+        current_user: cached_user,
+        user_names: cached_user_names,
+        ..App::new(api_client, config, store, offline, vim_mode)
     }));
 
     let (tx_action, mut rx_action) = mpsc::channel::<AppAction>(32);
@@ -254,6 +431,9 @@ async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
     let gateway_token = token.clone();
     let gateway_tx = tx_action.clone();
     let gateway_handle: JoinHandle<()> = tokio::spawn(async move {
+        if offline {
+            return;
+        }
         let client = crate::api::GatewayClient::new(gateway_token, gateway_tx);
         if let Err(e) = client.connect(rx_shutdown_gateway).await {
             let _ = print_log(
@@ -264,6 +444,14 @@ async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
     });
 
     let api_handle: JoinHandle<()> = tokio::spawn(async move {
+        if offline || has_cache {
+            tx_api.send(AppAction::EndLoading).await.ok();
+        }
+        if offline {
+            let _ = rx_shutdown_api.recv().await;
+            return;
+        }
+
         let api_client_clone;
         {
             let state = api_state.lock().await;
@@ -300,6 +488,28 @@ async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
             }
         }
 
+        // Bots have no settings, their server list simply stays unsorted
+        match api_client_clone.get_user_settings().await {
+            Ok(settings) => {
+                if let Some(status) = settings.status.clone() {
+                    tx_api
+                        .send(AppAction::ApiUpdateOwnStatus(status))
+                        .await
+                        .ok();
+                }
+                tx_api
+                    .send(AppAction::ApiUpdateGuildFolders(settings.into_folders()))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load the server folders: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+
         match api_client_clone.get_dms().await {
             Ok(dms) => {
                 if let Err(e) = tx_api.send(AppAction::ApiUpdateDMs(dms)).await {
@@ -315,7 +525,9 @@ async fn run_app(token: String, config: config::Config) -> Result<(), Error> {
             }
         }
 
-        tx_api.send(AppAction::EndLoading).await.ok();
+        if !has_cache {
+            tx_api.send(AppAction::EndLoading).await.ok();
+        }
 
         // Wait for shutdown now since HTTP polling is removed
         let _ = rx_shutdown_api.recv().await;
@@ -368,18 +580,35 @@ async fn main() -> Result<(), Error> {
     dotenvy::dotenv().ok();
     const ENV_TOKEN: &str = "DISCORD_TOKEN";
 
+    let args: Vec<String> = env::args().collect();
+    let offline = args.iter().any(|arg| arg == "--offline");
+    let headless_export = args.get(1).is_some_and(|arg| arg == "export");
+
     let token: String = env::var(ENV_TOKEN).unwrap_or_else(|_| {
+        if offline && !headless_export {
+            return String::new();
+        }
         let msg = "Env Error: DISCORD_TOKEN variable is missing.";
         eprintln!("{msg}");
         let _ = print_log(msg.into(), LogType::Error);
         process::exit(1);
     });
 
+    if headless_export {
+        let api_client = ApiClient::new(Client::new(), token, DISCORD_BASE_URL.to_string());
+        if let Err(e) = export::run_headless(&api_client, &args[2..]).await {
+            eprintln!("Export Error: {e}");
+            let _ = print_log(format!("Export Error: {e}").into(), LogType::Error);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     setup_ctrlc_handler();
 
     let config = config::load_config();
 
-    if let Err(e) = run_app(token, config).await {
+    if let Err(e) = run_app(token, config, offline).await {
         restore_terminal();
         return Err(e);
     }
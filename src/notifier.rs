@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logs::{LogType, print_log};
+
+/// How notifications reach us, picked in the config's `[notifier]` table.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    /// Desktop notifications over D-Bus, or the platform's notification center
+    #[default]
+    Desktop,
+    /// The terminal bell
+    Bell,
+    /// OSC 9 escape sequences, understood by iTerm2, kitty, WezTerm and foot
+    Osc9,
+    /// OSC 777 escape sequences, understood by urxvt, foot and VTE based terminals
+    Osc777,
+    /// Runs `command` with `sh -c`, the message in `VIMCORD_*` environment variables
+    Command {
+        command: String,
+    },
+    None,
+}
+
+/// A message worth telling about, whatever the backend does with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub channel_id: String,
+    pub guild_id: Option<String>,
+    pub message_id: String,
+    pub author: String,
+    /// The author, and the guild for guild messages
+    pub summary: String,
+    pub body: String,
+}
+
+pub trait Notifier: Send + std::fmt::Debug {
+    /// Shows a notification, replacing the one of the same channel when `updates_in_place`.
+    fn notify(&mut self, notification: &Notification);
+
+    /// Whether a channel's notification can be updated rather than stacked, the others
+    /// get rate limited instead.
+    fn updates_in_place(&self) -> bool {
+        false
+    }
+
+    /// The channel was read, takes back what is still shown for it.
+    fn clear(&mut self, _channel_id: &str) {}
+}
+
+pub fn from_config(config: &NotifierConfig) -> Box<dyn Notifier> {
+    match config {
+        NotifierConfig::Desktop => Box::new(DesktopNotifier::default()),
+        NotifierConfig::Bell => Box::new(TerminalNotifier(TerminalKind::Bell)),
+        NotifierConfig::Osc9 => Box::new(TerminalNotifier(TerminalKind::Osc9)),
+        NotifierConfig::Osc777 => Box::new(TerminalNotifier(TerminalKind::Osc777)),
+        NotifierConfig::Command { command } => Box::new(CommandNotifier {
+            command: command.clone(),
+        }),
+        NotifierConfig::None => Box::new(NoNotifier),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DesktopNotifier {
+    /// channel_id -> the notification on screen for it
+    active_notifications: HashMap<String, notify_rust::NotificationHandle>,
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, notification: &Notification) {
+        if let Some(handle) = self.active_notifications.get_mut(&notification.channel_id) {
+            handle
+                .summary(&notification.summary)
+                .body(&notification.body);
+            if handle.update().is_ok() {
+                return;
+            }
+            // Dismissed or lost by the daemon, show a new one instead
+            self.active_notifications.remove(&notification.channel_id);
+        }
+
+        match notify_rust::Notification::new()
+            .summary(&notification.summary)
+            .body(&notification.body)
+            .appname("vimcord")
+            .show()
+        {
+            Ok(handle) => {
+                self.active_notifications
+                    .insert(notification.channel_id.clone(), handle);
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to show a notification: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+    }
+
+    fn updates_in_place(&self) -> bool {
+        true
+    }
+
+    fn clear(&mut self, channel_id: &str) {
+        if let Some(handle) = self.active_notifications.remove(channel_id) {
+            handle.close();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TerminalKind {
+    Bell,
+    Osc9,
+    Osc777,
+}
+
+/// Escape sequences for sessions without a notification daemon, like over SSH.
+#[derive(Debug)]
+pub struct TerminalNotifier(TerminalKind);
+
+/// Control characters would end the sequence early, `;` separates OSC 777's fields.
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() || c == ';' { ' ' } else { c })
+        .collect()
+}
+
+/// tmux only forwards escape sequences to the outer terminal inside a DCS passthrough.
+fn passthrough(sequence: &str) -> String {
+    if env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence.to_string()
+    }
+}
+
+impl Notifier for TerminalNotifier {
+    fn notify(&mut self, notification: &Notification) {
+        let summary = sanitize(&notification.summary);
+        let body = sanitize(&notification.body);
+        let sequence = match self.0 {
+            TerminalKind::Bell => "\x07".to_string(),
+            TerminalKind::Osc9 => passthrough(&format!("\x1b]9;{summary}: {body}\x07")),
+            TerminalKind::Osc777 => passthrough(&format!("\x1b]777;notify;{summary};{body}\x07")),
+        };
+
+        let mut stdout = io::stdout();
+        if let Err(e) = stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+        {
+            let _ = print_log(
+                format!("Failed to write a terminal notification: {e}").into(),
+                LogType::Error,
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandNotifier {
+    command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&mut self, notification: &Notification) {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("VIMCORD_SUMMARY", &notification.summary)
+            .env("VIMCORD_BODY", &notification.body)
+            .env("VIMCORD_AUTHOR", &notification.author)
+            .env("VIMCORD_CHANNEL_ID", &notification.channel_id)
+            .env(
+                "VIMCORD_GUILD_ID",
+                notification.guild_id.as_deref().unwrap_or_default(),
+            )
+            .env("VIMCORD_MESSAGE_ID", &notification.message_id)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match child {
+            // Reaped in the background so it doesn't linger as a zombie
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to run the notification command: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NoNotifier;
+
+impl Notifier for NoNotifier {
+    fn notify(&mut self, _notification: &Notification) {}
+}
+
+/// Keeps everything it is told, for tests.
+#[cfg(test)]
+#[derive(Debug, Default, Clone)]
+pub struct RecordingNotifier {
+    pub in_place: bool,
+    pub shown: std::sync::Arc<std::sync::Mutex<Vec<Notification>>>,
+    pub cleared: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl Notifier for RecordingNotifier {
+    fn notify(&mut self, notification: &Notification) {
+        self.shown.lock().unwrap().push(notification.clone());
+    }
+
+    fn updates_in_place(&self) -> bool {
+        self.in_place
+    }
+
+    fn clear(&mut self, channel_id: &str) {
+        self.cleared.lock().unwrap().push(channel_id.to_string());
+    }
+}
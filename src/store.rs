@@ -0,0 +1,489 @@
+use std::{path::Path, sync::mpsc, thread, time::Duration};
+
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    Error,
+    api::{Channel, DM, Guild, Message, PartialMessage, User},
+    logs::{LogType, get_cache_directory, print_log},
+    ui::search::message_matches,
+};
+
+const DATABASE_FILE: &str = "cache.sqlite3";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS guilds (
+    id TEXT PRIMARY KEY,
+    position INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS channels (
+    id TEXT PRIMARY KEY,
+    guild_id TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS dms (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    snowflake INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS messages_by_channel ON messages (channel_id, snowflake);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+";
+
+/// A message change queued for the writer thread.
+#[derive(Debug)]
+enum MessageWrite {
+    Save(Vec<Message>),
+    Update(Box<PartialMessage>),
+    Delete(String),
+}
+
+/// Local SQLite cache of everything vimcord has loaded, so the UI can start from
+/// cached state and browse history without a connection.
+/// Rows are stored as the JSON the API returned, keyed by id.
+/// Messages change on every gateway event, so they are written by a thread of their own
+/// instead of under the app lock.
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+    writer: mpsc::Sender<MessageWrite>,
+}
+
+fn log_error(context: &str, e: Error) {
+    let _ = print_log(
+        format!("Cache {context} failed: {e}").into(),
+        LogType::Error,
+    );
+}
+
+/// A `LIKE` pattern keeping the rows that may match a search: the longest ASCII word of
+/// it must be in the JSON, which holds the content and the names mentions are shown with.
+/// LIKE ignores ASCII case, so smart-case searches are only narrowed further in Rust.
+fn like_filter(pattern: &str) -> String {
+    if !pattern.is_ascii() {
+        // LIKE is case-sensitive beyond ASCII and JSON may escape the rest
+        return "%".to_string();
+    }
+    let word = pattern
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .max_by_key(|word| word.len())
+        .unwrap_or_default();
+    format!("%{word}%")
+}
+
+fn snowflake(id: &str) -> i64 {
+    id.parse::<i64>().unwrap_or_default()
+}
+
+fn connect(path: &Path) -> Result<Connection, Error> {
+    let conn = Connection::open(path)?;
+    // The writer thread and the app share the file, neither should fail on the other's lock
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    Ok(conn)
+}
+
+fn write_users<'a>(
+    conn: &mut Connection,
+    users: impl IntoIterator<Item = &'a User>,
+) -> Result<(), Error> {
+    let tx = conn.transaction()?;
+    for user in users {
+        tx.execute(
+            "INSERT OR REPLACE INTO users (id, data) VALUES (?1, ?2)",
+            params![user.id, serde_json::to_string(user)?],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn write_messages(conn: &mut Connection, messages: &[Message]) -> Result<(), Error> {
+    let tx = conn.transaction()?;
+    for message in messages {
+        tx.execute(
+            "INSERT OR REPLACE INTO messages (id, channel_id, snowflake, data)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                message.id,
+                message.channel_id,
+                snowflake(&message.id),
+                serde_json::to_string(message)?
+            ],
+        )?;
+    }
+    tx.commit()?;
+
+    write_users(conn, messages.iter().map(|m| &m.author))
+}
+
+fn update_message(conn: &mut Connection, partial: &PartialMessage) -> Result<(), Error> {
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT data FROM messages WHERE id = ?1",
+            [&partial.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(mut message) = data.and_then(|data| serde_json::from_str::<Message>(&data).ok())
+    else {
+        return Ok(());
+    };
+
+    if let Some(content) = &partial.content {
+        message.content = Some(content.clone());
+    }
+    if let Some(author) = &partial.author {
+        message.author = author.clone();
+    }
+    if let Some(timestamp) = &partial.timestamp {
+        message.timestamp = timestamp.clone();
+    }
+    if let Some(edited_timestamp) = &partial.edited_timestamp {
+        message.edited_timestamp = Some(edited_timestamp.clone());
+    }
+
+    write_messages(conn, &[message])
+}
+
+fn apply(conn: &mut Connection, write: MessageWrite) {
+    let result = match write {
+        MessageWrite::Save(messages) => write_messages(conn, &messages),
+        MessageWrite::Update(partial) => update_message(conn, &partial),
+        MessageWrite::Delete(id) => conn
+            .execute("DELETE FROM messages WHERE id = ?1", [id])
+            .map(|_| ())
+            .map_err(Into::into),
+    };
+
+    if let Err(e) = result {
+        log_error("write", e);
+    }
+}
+
+impl Store {
+    pub fn open() -> Option<Self> {
+        let result: Result<Self, Error> = (|| {
+            let mut path = get_cache_directory().ok_or("no cache directory")?;
+            std::fs::create_dir_all(&path)?;
+            path.push(DATABASE_FILE);
+
+            let conn = connect(&path)?;
+            conn.execute_batch(SCHEMA)?;
+            let mut writer_conn = connect(&path)?;
+
+            let (writer, rx) = mpsc::channel();
+            thread::spawn(move || {
+                for write in rx {
+                    apply(&mut writer_conn, write);
+                }
+            });
+
+            Ok(Self { conn, writer })
+        })();
+
+        result.map_err(|e| log_error("open", e)).ok()
+    }
+
+    /// Another connection to the same cache, for long reads away from the app lock.
+    pub fn reopen(&self) -> Option<Self> {
+        let result: Result<Self, Error> = (|| {
+            let path = self.conn.path().ok_or("no database file")?;
+            Ok(Self {
+                conn: connect(Path::new(path))?,
+                writer: self.writer.clone(),
+            })
+        })();
+
+        result.map_err(|e| log_error("open", e)).ok()
+    }
+
+    fn queue(&self, write: MessageWrite) {
+        if self.writer.send(write).is_err() {
+            log_error("write", "writer thread stopped".into());
+        }
+    }
+
+    fn load_rows<T: DeserializeOwned>(&self, sql: &str, params: impl rusqlite::Params) -> Vec<T> {
+        let result: Result<Vec<T>, Error> = (|| {
+            let mut stmt = self.conn.prepare(sql)?;
+            let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+            Ok(rows
+                .filter_map(Result::ok)
+                .filter_map(|data| serde_json::from_str(&data).ok())
+                .collect())
+        })();
+
+        result.unwrap_or_else(|e| {
+            log_error("read", e);
+            Vec::new()
+        })
+    }
+
+    pub fn save_value<T: Serialize>(&self, key: &str, value: &T) {
+        let result: Result<(), Error> = (|| {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO meta (key, data) VALUES (?1, ?2)",
+                params![key, serde_json::to_string(value)?],
+            )?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            log_error("write", e);
+        }
+    }
+
+    pub fn load_value<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.conn
+            .query_row("SELECT data FROM meta WHERE key = ?1", [key], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+            .ok()
+            .flatten()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    pub fn save_guilds(&mut self, guilds: &[Guild]) {
+        let result: Result<(), Error> = (|| {
+            let tx = self.conn.transaction()?;
+            tx.execute("DELETE FROM guilds", [])?;
+            for (position, guild) in guilds.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO guilds (id, position, data) VALUES (?1, ?2, ?3)",
+                    params![guild.id, position as i64, serde_json::to_string(guild)?],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            log_error("write", e);
+        }
+    }
+
+    pub fn load_guilds(&self) -> Vec<Guild> {
+        self.load_rows("SELECT data FROM guilds ORDER BY position", [])
+    }
+
+    /// Replaces every cached channel of `guild_id` with `channels`.
+    /// Expects the flat list returned by the API, not the categorized tree.
+    pub fn save_channels(&mut self, guild_id: &str, channels: &[Channel]) {
+        let result: Result<(), Error> = (|| {
+            let tx = self.conn.transaction()?;
+            tx.execute("DELETE FROM channels WHERE guild_id = ?1", [guild_id])?;
+            for channel in channels {
+                tx.execute(
+                    "INSERT OR REPLACE INTO channels (id, guild_id, data) VALUES (?1, ?2, ?3)",
+                    params![channel.id, guild_id, serde_json::to_string(channel)?],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            log_error("write", e);
+        }
+    }
+
+    pub fn load_channels(&self, guild_id: &str) -> Vec<Channel> {
+        self.load_rows("SELECT data FROM channels WHERE guild_id = ?1", [guild_id])
+    }
+
+    pub fn load_channel(&self, channel_id: &str) -> Option<Channel> {
+        self.load_rows("SELECT data FROM channels WHERE id = ?1", [channel_id])
+            .into_iter()
+            .next()
+    }
+
+    pub fn save_dms(&mut self, dms: &[DM]) {
+        let result: Result<(), Error> = (|| {
+            let tx = self.conn.transaction()?;
+            tx.execute("DELETE FROM dms", [])?;
+            for dm in dms {
+                tx.execute(
+                    "INSERT INTO dms (id, data) VALUES (?1, ?2)",
+                    params![dm.id, serde_json::to_string(dm)?],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            log_error("write", e);
+        }
+    }
+
+    pub fn load_dms(&self) -> Vec<DM> {
+        self.load_rows("SELECT data FROM dms", [])
+    }
+
+    pub fn save_users<'a>(&mut self, users: impl IntoIterator<Item = &'a User>) {
+        if let Err(e) = write_users(&mut self.conn, users) {
+            log_error("write", e);
+        }
+    }
+
+    pub fn load_users(&self) -> Vec<User> {
+        self.load_rows("SELECT data FROM users", [])
+    }
+
+    pub fn save_messages(&self, messages: &[Message]) {
+        self.queue(MessageWrite::Save(messages.to_vec()));
+    }
+
+    /// Applies a gateway MESSAGE_UPDATE to the cached copy, if we have one.
+    pub fn update_message(&self, partial: &PartialMessage) {
+        self.queue(MessageWrite::Update(Box::new(partial.clone())));
+    }
+
+    pub fn delete_message(&self, message_id: &str) {
+        self.queue(MessageWrite::Delete(message_id.to_string()));
+    }
+
+    /// Returns up to `limit` cached messages of a channel, newest first like the REST API.
+    pub fn load_messages(
+        &self,
+        channel_id: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Vec<Message> {
+        let before = before.map(snowflake).unwrap_or(i64::MAX);
+        self.load_rows(
+            "SELECT data FROM messages WHERE channel_id = ?1 AND snowflake < ?2
+             ORDER BY snowflake DESC LIMIT ?3",
+            params![channel_id, before, limit as i64],
+        )
+    }
+
+    /// Returns the id of the newest cached message older than `before` matching `pattern`.
+    /// SQLite only returns the rows whose JSON contains the pattern's longest word, those
+    /// are deserialized and matched newest first until one does.
+    pub fn find_message_before(
+        &self,
+        channel_id: &str,
+        pattern: &str,
+        before: Option<&str>,
+    ) -> Option<String> {
+        let before = before.map(snowflake).unwrap_or(i64::MAX);
+        let result: Result<Option<String>, Error> = (|| {
+            let mut stmt = self.conn.prepare(
+                "SELECT data FROM messages WHERE channel_id = ?1 AND snowflake < ?2
+                 AND data LIKE ?3 ORDER BY snowflake DESC",
+            )?;
+            let mut rows = stmt.query(params![channel_id, before, like_filter(pattern)])?;
+            while let Some(row) = rows.next()? {
+                let data: String = row.get(0)?;
+                if let Ok(message) = serde_json::from_str::<Message>(&data)
+                    && message_matches(&message, pattern)
+                {
+                    return Ok(Some(message.id));
+                }
+            }
+            Ok(None)
+        })();
+
+        result.unwrap_or_else(|e| {
+            log_error("read", e);
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{CHANNEL, FRIEND, ME, message, user};
+
+    /// An in-memory store applying message writes in place of the writer thread.
+    fn store() -> (Store, mpsc::Receiver<MessageWrite>) {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let (writer, rx) = mpsc::channel();
+        (Store { conn, writer }, rx)
+    }
+
+    fn flush(store: &mut Store, rx: &mpsc::Receiver<MessageWrite>) {
+        for write in rx.try_iter() {
+            apply(&mut store.conn, write);
+        }
+    }
+
+    #[test]
+    fn reopened_stores_read_the_same_file() {
+        let path = std::env::temp_dir().join(format!("vimcord-{}.sqlite3", std::process::id()));
+        let conn = connect(&path).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let (writer, rx) = mpsc::channel();
+        let mut store = Store { conn, writer };
+        store.save_messages(&[message("100", CHANNEL, None, FRIEND, "cached")]);
+        flush(&mut store, &rx);
+
+        let reader = store.reopen().unwrap();
+        let ids: Vec<String> = reader
+            .load_messages(CHANNEL, None, 10)
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        drop((store, reader));
+        std::fs::remove_file(&path).ok();
+        assert_eq!(ids, vec!["100"]);
+    }
+
+    #[test]
+    fn searches_only_look_at_older_matching_rows() {
+        let (mut store, rx) = store();
+        store.save_messages(&[
+            message("100", CHANNEL, None, FRIEND, "Deployed to prod"),
+            message("101", CHANNEL, None, FRIEND, "lunch?"),
+            message("102", CHANNEL, None, FRIEND, "deploying again"),
+        ]);
+        flush(&mut store, &rx);
+        assert_eq!(
+            store.find_message_before(CHANNEL, "deploy", None),
+            Some("102".to_string())
+        );
+        assert_eq!(
+            store.find_message_before(CHANNEL, "deploy", Some("102")),
+            Some("100".to_string())
+        );
+        // Smart-case, LIKE alone would take the lowercase one
+        assert_eq!(
+            store.find_message_before(CHANNEL, "Deploy", None),
+            Some("100".to_string())
+        );
+        assert_eq!(store.find_message_before(CHANNEL, "dinner", None), None);
+    }
+
+    #[test]
+    fn searches_match_mentions_like_loaded_messages() {
+        let (mut store, rx) = store();
+        let mut msg = message("100", CHANNEL, None, ME, "ping <@2>");
+        msg.mentions = vec![user(FRIEND)];
+        store.save_messages(&[msg]);
+        flush(&mut store, &rx);
+        assert_eq!(
+            store.find_message_before(CHANNEL, "@user2", None),
+            Some("100".to_string())
+        );
+        assert_eq!(store.find_message_before(CHANNEL, "<@2>", None), None);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction,
+    api::{
+        audit_log::{AuditLog, AuditLogEntry, parse_action_type},
+        permissions::Permissions,
+    },
+    logs::{LogType, print_log},
+    ui::events::current_guild_id,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditLogFilter {
+    pub user_id: Option<String>,
+    pub action_type: Option<u16>,
+}
+
+/// Read-only audit log of a guild, drawn over the main view.
+#[derive(Debug, Clone)]
+pub struct AuditLogView {
+    pub guild_id: String,
+    pub filter: AuditLogFilter,
+    pub entries: Vec<AuditLogEntry>,
+    /// Names of the users referenced by the loaded entries
+    pub users: HashMap<String, String>,
+    pub selected: usize,
+    pub expanded: bool,
+    pub is_loading: bool,
+    pub reached_end: bool,
+}
+
+impl AuditLogView {
+    fn new(guild_id: String, filter: AuditLogFilter) -> Self {
+        Self {
+            guild_id,
+            filter,
+            entries: Vec::new(),
+            users: HashMap::new(),
+            selected: 0,
+            expanded: false,
+            is_loading: false,
+            reached_end: false,
+        }
+    }
+}
+
+/// Resolves a user, channel or role id to a name through everything we have loaded.
+pub fn resolve_name(state: &App, view: &AuditLogView, id: &str) -> String {
+    if let Some(name) = view.users.get(id).or(state.user_names.get(id)) {
+        return name.clone();
+    }
+
+    if let Some(channel) = state
+        .channels
+        .iter()
+        .flat_map(|c| std::iter::once(c).chain(c.children.iter().flatten()))
+        .find(|c| c.id == id)
+    {
+        return format!("#{}", channel.name);
+    }
+
+    if let Some(role) = state
+        .context
+        .as_ref()
+        .and_then(|context| context.all_guild_roles.iter().find(|r| r.id == id))
+    {
+        return format!("@{}", role.name);
+    }
+
+    id.to_string()
+}
+
+fn request_page(state: &mut App, tx_action: &Sender<AppAction>) {
+    let Some(view) = state.audit_log.as_mut() else {
+        return;
+    };
+    if view.is_loading || view.reached_end {
+        return;
+    }
+    view.is_loading = true;
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    let guild_id = view.guild_id.clone();
+    let filter = view.filter.clone();
+    let before = view.entries.last().map(|e| e.id.clone());
+
+    tokio::spawn(async move {
+        match api_client
+            .get_audit_log(
+                &guild_id,
+                filter.user_id.as_deref(),
+                filter.action_type,
+                before.as_deref(),
+            )
+            .await
+        {
+            Ok(log) => {
+                tx_action
+                    .send(AppAction::ApiUpdateAuditLog(guild_id, filter, log))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load audit log: {e}").into(),
+                    LogType::Error,
+                );
+                tx_action
+                    .send(AppAction::ApiAuditLogFailed(
+                        guild_id,
+                        filter,
+                        e.to_string(),
+                    ))
+                    .await
+                    .ok();
+            }
+        }
+    });
+}
+
+fn reload(state: &mut App, filter: AuditLogFilter, tx_action: &Sender<AppAction>) {
+    let Some(view) = state.audit_log.as_ref() else {
+        return;
+    };
+    state.audit_log = Some(AuditLogView::new(view.guild_id.clone(), filter));
+    request_page(state, tx_action);
+}
+
+/// `:auditlog [user <id or name>] [action <type>]`
+pub fn open_audit_log(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    let Some(guild_id) = current_guild_id(state) else {
+        state.status_message = "Open a server to view its audit log.".to_string();
+        return;
+    };
+
+    if state.offline {
+        state.status_message = "The audit log is not available offline.".to_string();
+        return;
+    }
+    if state
+        .context
+        .as_ref()
+        .is_some_and(|context| !context.has_guild_permission(Permissions::VIEW_AUDIT_LOG))
+    {
+        state.status_message = "You need VIEW_AUDIT_LOG to see the audit log.".to_string();
+        return;
+    }
+
+    let mut filter = AuditLogFilter::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (*arg, args.next()) {
+            ("user", Some(user)) => {
+                // Accept a known username as well as an id
+                let user_id = state
+                    .user_names
+                    .iter()
+                    .find(|(_, name)| name.eq_ignore_ascii_case(user))
+                    .map(|(id, _)| id.clone())
+                    .unwrap_or(user.to_string());
+                filter.user_id = Some(user_id);
+            }
+            ("action", Some(action)) => match parse_action_type(action) {
+                Some(action_type) => filter.action_type = Some(action_type),
+                None => {
+                    state.status_message = format!("Unknown audit log action: {action}");
+                    return;
+                }
+            },
+            _ => {
+                state.status_message =
+                    "Usage: :auditlog [user <id or name>] [action <type>]".to_string();
+                return;
+            }
+        }
+    }
+
+    state.audit_log = Some(AuditLogView::new(guild_id, filter));
+    state.status_message =
+        "Audit log: Enter expands changes, u/a filter by user/action, c clears, Esc closes"
+            .to_string();
+    request_page(state, tx_action);
+}
+
+/// A page failed to load: the view can ask for it again.
+pub fn page_failed(state: &mut App, guild_id: &str, filter: &AuditLogFilter, error: &str) {
+    state.status_message = format!("Failed to load audit log: {error}");
+    if let Some(view) = state.audit_log.as_mut()
+        && view.guild_id == guild_id
+        && &view.filter == filter
+    {
+        view.is_loading = false;
+    }
+}
+
+/// Merges a fetched page, unless the view was closed or its filters changed meanwhile.
+pub fn apply_page(state: &mut App, guild_id: &str, filter: &AuditLogFilter, log: AuditLog) {
+    let Some(view) = state.audit_log.as_mut() else {
+        return;
+    };
+    if view.guild_id != guild_id || &view.filter != filter {
+        return;
+    }
+
+    view.is_loading = false;
+    view.reached_end = log.audit_log_entries.is_empty();
+    view.entries.extend(log.audit_log_entries);
+    view.users.extend(
+        log.users
+            .into_iter()
+            .map(|u| (u.id, u.global_name.unwrap_or(u.username))),
+    );
+}
+
+/// Routes input actions to the open audit log.
+/// Returns `true` when the action was consumed by the view.
+pub fn handle_audit_log_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let Some(view) = state.audit_log.as_mut() else {
+        return false;
+    };
+    let last = view.entries.len().saturating_sub(1);
+    let selected = view.entries.get(view.selected).cloned();
+
+    match action {
+        AppAction::InputEscape | AppAction::InputChar('q') => {
+            state.audit_log = None;
+        }
+        AppAction::SelectNext | AppAction::InputChar('j') => {
+            if view.selected < last {
+                view.selected += 1;
+            } else {
+                request_page(state, tx_action);
+            }
+        }
+        AppAction::SelectPrevious | AppAction::InputChar('k') => {
+            view.selected = view.selected.saturating_sub(1);
+        }
+        AppAction::InputSubmit => {
+            view.expanded = !view.expanded;
+        }
+        AppAction::InputChar('u') => {
+            let mut filter = view.filter.clone();
+            filter.user_id = match filter.user_id {
+                Some(_) => None,
+                None => selected.and_then(|e| e.user_id),
+            };
+            reload(state, filter, tx_action);
+        }
+        AppAction::InputChar('a') => {
+            let mut filter = view.filter.clone();
+            filter.action_type = match filter.action_type {
+                Some(_) => None,
+                None => selected.map(|e| e.action_type),
+            };
+            reload(state, filter, tx_action);
+        }
+        AppAction::InputChar('c') => {
+            reload(state, AuditLogFilter::default(), tx_action);
+        }
+        AppAction::InputChar(_)
+        | AppAction::InputBackspace
+        | AppAction::InputDelete
+        | AppAction::SelectLeft
+        | AppAction::SelectRight
+        | AppAction::Paste(_) => {}
+        _ => return false,
+    }
+
+    true
+}
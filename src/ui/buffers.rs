@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState, Window,
+    api::Message,
+    ui::{
+        events::open_chat,
+        layout::{self, focus_left},
+        notifications, unread, windows,
+    },
+};
+
+/// Where we were in a chat: the selected message, the scroll and the unsent message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatView {
+    pub selection_index: usize,
+    pub scroll: usize,
+    pub draft: String,
+    pub cursor: usize,
+}
+
+/// An open channel without the focus.
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    /// Kept live by the gateway while the tab is live, empty for the focused channel,
+    /// whose messages are `App::messages`
+    pub messages: Vec<Message>,
+    /// Saved when the chat lost the focus, given back when it gets it again
+    pub view: Option<ChatView>,
+}
+
+/// Background tabs kept live by the gateway, the others drop their messages and load
+/// them again when reopened.
+pub const LIVE_BUFFERS: usize = 8;
+
+/// Messages a background tab keeps, as many as opening it fetches.
+pub const BUFFER_MESSAGES: usize = 100;
+
+/// Every channel opened this session, shown as tabs.
+#[derive(Debug, Clone, Default)]
+pub struct Buffers {
+    /// Channel id and name, in the order they were opened
+    pub tabs: Vec<(String, String)>,
+    pub buffers: HashMap<String, Buffer>,
+    /// Channel ids of the tabs, the one focused last first
+    pub recent: Vec<String>,
+}
+
+/// The channel whose messages are in `App::messages`, focused or not.
+pub fn current_chat(state: &App) -> Option<&str> {
+    match &state.state {
+        AppState::Chatting(channel_id, _)
+        | AppState::EmojiSelection(channel_id, _)
+        | AppState::Editing(channel_id, _, _, _)
+        | AppState::Loading(Window::Chat(channel_id)) => Some(channel_id),
+        _ => state.panes.chat.as_ref().map(|(id, _)| id.as_str()),
+    }
+}
+
+/// The loaded messages of an open channel, to apply gateway events to.
+pub fn messages_mut<'a>(state: &'a mut App, channel_id: &str) -> Option<&'a mut Vec<Message>> {
+    if current_chat(state) == Some(channel_id) {
+        return Some(&mut state.messages);
+    }
+    // A tab may have no messages yet, its first ones still belong in it
+    if !is_live(state, channel_id) {
+        return None;
+    }
+    Some(
+        &mut state
+            .buffers
+            .buffers
+            .entry(channel_id.to_string())
+            .or_default()
+            .messages,
+    )
+}
+
+/// Whether a tab keeps its messages in the background: it was focused lately or has a
+/// window.
+fn is_live(state: &App, channel_id: &str) -> bool {
+    state
+        .buffers
+        .recent
+        .iter()
+        .take(LIVE_BUFFERS)
+        .any(|id| id == channel_id)
+        || state.windows.channels.iter().any(|id| id == channel_id)
+}
+
+/// Keeps a background tab to its newest `BUFFER_MESSAGES` after a new one came in.
+pub fn trim(state: &mut App, channel_id: &str) {
+    if current_chat(state) == Some(channel_id) {
+        return;
+    }
+    if let Some(buffer) = state.buffers.buffers.get_mut(channel_id) {
+        buffer.messages.truncate(BUFFER_MESSAGES);
+    }
+}
+
+/// Drops the messages of the tabs that are no longer live, their views stay.
+fn evict(state: &mut App) {
+    let evicted: Vec<String> = state
+        .buffers
+        .recent
+        .iter()
+        .filter(|id| !is_live(state, id))
+        .cloned()
+        .collect();
+    for channel_id in evicted {
+        if let Some(buffer) = state.buffers.buffers.get_mut(&channel_id) {
+            buffer.messages = Vec::new();
+        }
+    }
+}
+
+/// Remembers the view of the chat we are leaving, its draft included.
+pub fn save_view(state: &mut App) {
+    let AppState::Chatting(channel_id, _) = &state.state else {
+        return;
+    };
+    let view = ChatView {
+        selection_index: state.selection_index,
+        scroll: state.chat_scroll_offset,
+        draft: state.input.clone(),
+        cursor: state.cursor_position,
+    };
+    state
+        .buffers
+        .buffers
+        .entry(channel_id.clone())
+        .or_default()
+        .view = Some(view);
+}
+
+/// The chat got the focus: adds its tab and gives back its view, if it had one.
+pub fn enter(state: &mut App, channel_id: &str, channel_name: &str) {
+    match state
+        .buffers
+        .tabs
+        .iter_mut()
+        .find(|(id, _)| id == channel_id)
+    {
+        Some((_, name)) => *name = channel_name.to_string(),
+        None => state
+            .buffers
+            .tabs
+            .push((channel_id.to_string(), channel_name.to_string())),
+    }
+    state.buffers.recent.retain(|id| id != channel_id);
+    state.buffers.recent.insert(0, channel_id.to_string());
+    evict(state);
+
+    let Some(view) = state
+        .buffers
+        .buffers
+        .get_mut(channel_id)
+        .and_then(|buffer| buffer.view.take())
+    else {
+        return;
+    };
+    state.selection_index = view.selection_index.min(state.messages.len());
+    state.chat_scroll_offset = view.scroll;
+    state.input = view.draft;
+    state.cursor_position = view.cursor.min(state.input.len());
+}
+
+/// Before `open_chat` replaces `App::messages`: the chat we leave keeps its messages in
+/// its buffer, and the one we open takes its own back if it has a live buffer.
+pub fn switch_to(state: &mut App, channel_id: &str) -> Option<Vec<Message>> {
+    save_view(state);
+    if let Some(current) = current_chat(state).map(str::to_string)
+        && current != channel_id
+        && state.buffers.tabs.iter().any(|(id, _)| *id == current)
+    {
+        let messages = std::mem::take(&mut state.messages);
+        state.buffers.buffers.entry(current).or_default().messages = messages;
+    }
+    if let Some(current) = current_chat(state).map(str::to_string)
+        && current != channel_id
+    {
+        windows::chat_replaced(state, &current, channel_id);
+        // Until the new chat is open `App::messages` belongs to neither
+        state.panes.chat = None;
+    }
+
+    state
+        .buffers
+        .buffers
+        .get_mut(channel_id)
+        .map(|buffer| std::mem::take(&mut buffer.messages))
+        .filter(|messages| !messages.is_empty())
+}
+
+/// A live buffer was opened without fetching: what the fetch would have acked is acked.
+pub fn reopened(state: &mut App, channel_id: &str) {
+    notifications::channel_read(state, channel_id);
+    if let Some(newest) = state.messages.first().map(|m| m.id.clone())
+        && !unread::is_read(state, channel_id, &newest)
+    {
+        unread::ack(state, channel_id, &newest);
+    }
+}
+
+/// Forgets a channel's tab and buffer.
+pub fn close(state: &mut App, channel_id: &str) {
+    state.buffers.tabs.retain(|(id, _)| id != channel_id);
+    state.buffers.recent.retain(|id| id != channel_id);
+    state.buffers.buffers.remove(channel_id);
+    windows::remove(state, channel_id);
+}
+
+/// Index of the focused tab, or of the chat shown last.
+fn current_tab(state: &App) -> Option<usize> {
+    let current = current_chat(state)?;
+    state.buffers.tabs.iter().position(|(id, _)| id == current)
+}
+
+/// `gt` and `gT`: opens the next or previous tab, wrapping around.
+pub async fn cycle(state: &mut MutexGuard<'_, App>, tx_action: &Sender<AppAction>, step: isize) {
+    let len = state.buffers.tabs.len();
+    if len == 0 {
+        state.status_message = "No open channels.".to_string();
+        return;
+    }
+    let index = match current_tab(state) {
+        Some(current) => (current as isize + step).rem_euclid(len as isize) as usize,
+        None => 0,
+    };
+    let channel_id = state.buffers.tabs[index].0.clone();
+    open_chat(state, tx_action, channel_id).await;
+}
+
+/// `:b <name|number>` opens a tab by its number or part of its name, `:bd` closes the
+/// focused one.
+pub async fn run_command(
+    state: &mut MutexGuard<'_, App>,
+    name: &str,
+    args: &[&str],
+    tx_action: &Sender<AppAction>,
+) {
+    if name == "bd" {
+        close_current(state, tx_action).await;
+        return;
+    }
+
+    let query = args.join(" ");
+    if query.is_empty() {
+        state.status_message = "Usage: :b <name|number>".to_string();
+        return;
+    }
+    match find_tab(state, &query) {
+        Some(channel_id) => open_chat(state, tx_action, channel_id).await,
+        None => state.status_message = format!("No open channel matches {query}"),
+    }
+}
+
+/// The tab numbered `query`, or the first whose name contains it.
+pub fn find_tab(state: &App, query: &str) -> Option<String> {
+    let query = query.to_lowercase();
+    let tab = match query.parse::<usize>() {
+        Ok(number) => state.buffers.tabs.get(number.wrapping_sub(1)),
+        Err(_) => state
+            .buffers
+            .tabs
+            .iter()
+            .find(|(_, name)| name.to_lowercase().contains(&query)),
+    };
+    tab.map(|(id, _)| id.clone())
+}
+
+/// Closes the focused tab and opens the next one, or goes back to the channel list
+/// when it was the last.
+async fn close_current(state: &mut MutexGuard<'_, App>, tx_action: &Sender<AppAction>) {
+    let AppState::Chatting(channel_id, _) = state.state.clone() else {
+        state.status_message = "Open a channel to close it.".to_string();
+        return;
+    };
+    let Some(index) = current_tab(state) else {
+        return;
+    };
+
+    let len = state.buffers.tabs.len();
+    if len > 1 {
+        let next = state.buffers.tabs[(index + 1) % len].0.clone();
+        open_chat(state, tx_action, next).await;
+    } else {
+        focus_left(state, tx_action).await;
+        layout::channel_gone(state, &channel_id);
+    }
+    close(state, &channel_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{self, DM_CHANNEL, FRIEND, OTHER_DM, message, open, two_tabs};
+
+    #[tokio::test]
+    async fn switching_keeps_the_buffer_of_the_other_tab() {
+        let app = two_tabs().await;
+        let ids: Vec<&str> = app.buffers.tabs.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec![OTHER_DM, DM_CHANNEL]);
+        assert_eq!(app.buffers.buffers[OTHER_DM].messages.len(), 1);
+        assert_eq!(app.input, "");
+    }
+
+    #[tokio::test]
+    async fn drafts_come_back_with_their_tab() {
+        let app = open(two_tabs().await, OTHER_DM).await;
+        assert_eq!(app.input, "unsent");
+        assert_eq!(app.cursor_position, 6);
+        assert_eq!(app.messages[0].id, "100");
+        assert_eq!(app.buffers.buffers[DM_CHANNEL].messages[0].id, "200");
+    }
+
+    #[tokio::test]
+    async fn background_tabs_get_new_messages() {
+        let app = two_tabs().await;
+        let app = testing::receive(app, message("101", OTHER_DM, None, FRIEND, "new")).await;
+        let ids: Vec<&str> = app.buffers.buffers[OTHER_DM]
+            .messages
+            .iter()
+            .map(|m| m.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["101", "100"]);
+        assert_eq!(app.messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn empty_background_tabs_get_their_first_message() {
+        let mut app = two_tabs().await;
+        app.buffers.buffers.remove(OTHER_DM);
+        let app = testing::receive(app, message("101", OTHER_DM, None, FRIEND, "first")).await;
+        assert_eq!(app.buffers.buffers[OTHER_DM].messages[0].id, "101");
+
+        // Channels without a tab are not kept
+        let app = testing::receive(app, message("300", "22", None, FRIEND, "elsewhere")).await;
+        assert!(!app.buffers.buffers.contains_key("22"));
+    }
+
+    #[tokio::test]
+    async fn tabs_focused_long_ago_drop_their_messages() {
+        let mut app = two_tabs().await;
+        let others: Vec<String> = (1..LIVE_BUFFERS).map(|i| (40 + i).to_string()).collect();
+        app.dms.extend(others.iter().map(|id| testing::dm(id)));
+        for id in &others {
+            app = open(app, id).await;
+        }
+        assert!(app.buffers.buffers[OTHER_DM].messages.is_empty());
+        assert_eq!(app.buffers.buffers[DM_CHANNEL].messages[0].id, "200");
+        assert_eq!(app.buffers.tabs.len(), LIVE_BUFFERS + 1);
+
+        // Its tab stays, without new messages, and gives back its draft when reopened
+        let app = testing::receive(app, message("101", OTHER_DM, None, FRIEND, "new")).await;
+        assert!(app.buffers.buffers[OTHER_DM].messages.is_empty());
+        let app = open(app, OTHER_DM).await;
+        assert_eq!(app.input, "unsent");
+    }
+
+    #[tokio::test]
+    async fn background_tabs_keep_their_newest_messages() {
+        let mut app = two_tabs().await;
+        app.buffers.buffers.get_mut(OTHER_DM).unwrap().messages = (0..BUFFER_MESSAGES as u64)
+            .rev()
+            .map(|id| message(&(100 + id).to_string(), OTHER_DM, None, FRIEND, "old"))
+            .collect();
+        let app = testing::receive(app, message("999", OTHER_DM, None, FRIEND, "new")).await;
+        let messages = &app.buffers.buffers[OTHER_DM].messages;
+        assert_eq!(messages.len(), BUFFER_MESSAGES);
+        assert_eq!(messages[0].id, "999");
+        assert_eq!(messages[BUFFER_MESSAGES - 1].id, "101");
+    }
+
+    #[tokio::test]
+    async fn tabs_are_found_by_number_or_name() {
+        let mut app = two_tabs().await;
+        app.buffers.tabs[0].1 = "general".to_string();
+        let state = tokio::sync::Mutex::new(app);
+        let (tx_action, mut rx_action) = tokio::sync::mpsc::channel(8);
+        run_command(&mut state.lock().await, "b", &["GEN"], &tx_action).await;
+        assert!(
+            matches!(rx_action.try_recv(), Ok(AppAction::TransitionToChat(id)) if id == OTHER_DM)
+        );
+    }
+}
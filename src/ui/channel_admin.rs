@@ -0,0 +1,625 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    App, AppAction, AppState,
+    api::{Channel, channel::Overwrite, permissions::Permissions},
+    logs::{LogType, print_log},
+    ui::{
+        events::{channel_list, current_guild_id},
+        form::{FieldValue, Form, FormField, FormPurpose},
+        moderation::parse_duration,
+    },
+};
+
+/// Discord caps slowmode at six hours.
+const MAX_SLOWMODE_SECONDS: u64 = 6 * 60 * 60;
+
+const CHANNEL_TYPES: &[(u8, &str)] = &[
+    (0, "Text"),
+    (2, "Voice"),
+    (4, "Category"),
+    (5, "Announcement"),
+    (15, "Forum"),
+];
+
+/// The channel under the cursor in the channel list, or the open one while chatting.
+fn target_channel(state: &App) -> Option<Channel> {
+    match &state.state {
+        AppState::SelectingChannel(_, _) => channel_list(state)
+            .get(state.selection_index)
+            .map(|c| (*c).clone()),
+        AppState::Chatting(channel_id, _) => state
+            .channels
+            .iter()
+            .flat_map(|c| std::iter::once(c).chain(c.children.iter().flatten()))
+            .find(|c| &c.id == channel_id)
+            .cloned(),
+        _ => None,
+    }
+}
+
+/// Checks `permission` in `channel` (or guild-wide without one), explaining any refusal in the status line.
+pub fn ensure_permission(
+    state: &mut App,
+    channel: Option<&Channel>,
+    permission: Permissions,
+) -> bool {
+    if state.offline {
+        state.status_message = "Offline mode: the cache is read-only.".to_string();
+        return false;
+    }
+
+    let Some(context) = state.context.as_ref() else {
+        state.status_message = "Permissions are still loading.".to_string();
+        return false;
+    };
+
+    let permissions = match channel {
+        Some(channel) => channel.permissions(context),
+        None => context.guild_permissions(),
+    };
+
+    if !permissions.contains(permission) {
+        let name = permission
+            .iter_names()
+            .next()
+            .map(|(name, _)| name)
+            .unwrap_or_default();
+        state.status_message = format!("You need {name} to do that.");
+        return false;
+    }
+
+    true
+}
+
+fn category_options(state: &App) -> Vec<(String, String)> {
+    std::iter::once((String::new(), "None".to_string()))
+        .chain(
+            state
+                .channels
+                .iter()
+                .filter(|c| c.channel_type == 4)
+                .map(|c| (c.id.clone(), c.name.clone())),
+        )
+        .collect()
+}
+
+fn channel_fields(state: &App, channel: Option<&Channel>, parent_id: &str) -> Vec<FormField> {
+    vec![
+        FormField::text(
+            "name",
+            "Name",
+            channel.map(|c| c.name.clone()).unwrap_or_default(),
+        ),
+        FormField::text(
+            "topic",
+            "Topic",
+            channel.and_then(|c| c.topic.clone()).unwrap_or_default(),
+        ),
+        FormField::text(
+            "slowmode",
+            "Slowmode (e.g. 10s, 5m)",
+            channel
+                .and_then(|c| c.rate_limit_per_user)
+                .unwrap_or(0)
+                .to_string(),
+        ),
+        FormField::checkbox("nsfw", "NSFW", channel.is_some_and(|c| c.nsfw)),
+        FormField::select("parent", "Category", category_options(state), parent_id),
+    ]
+}
+
+/// `:newchannel [name]`, created next to the selected channel.
+pub fn open_create_form(state: &mut App, args: &[&str]) {
+    let Some(guild_id) = current_guild_id(state) else {
+        state.status_message = "Open a server to create channels in it.".to_string();
+        return;
+    };
+    if !ensure_permission(state, None, Permissions::MANAGE_CHANNELS) {
+        return;
+    }
+
+    let parent_id = target_channel(state)
+        .and_then(|c| {
+            if c.channel_type == 4 {
+                Some(c.id)
+            } else {
+                c.parent_id
+            }
+        })
+        .unwrap_or_default();
+
+    let mut fields = channel_fields(state, None, &parent_id);
+    if let Some(FormField {
+        value: FieldValue::Text(name),
+        ..
+    }) = fields.first_mut()
+    {
+        *name = args.join("-");
+    }
+    fields.insert(
+        1,
+        FormField::select(
+            "type",
+            "Type",
+            CHANNEL_TYPES
+                .iter()
+                .map(|(t, label)| (t.to_string(), label.to_string()))
+                .collect(),
+            "0",
+        ),
+    );
+
+    state.form = Some(Form::new(
+        "New channel".to_string(),
+        FormPurpose::CreateChannel { guild_id },
+        fields,
+    ));
+}
+
+/// `:editchannel`
+pub fn open_edit_form(state: &mut App) {
+    let Some(channel) = target_channel(state) else {
+        state.status_message = "Select a channel to edit.".to_string();
+        return;
+    };
+    if !ensure_permission(state, Some(&channel), Permissions::MANAGE_CHANNELS) {
+        return;
+    }
+
+    let fields = if channel.channel_type == 4 {
+        vec![FormField::text("name", "Name", channel.name.clone())]
+    } else {
+        channel_fields(
+            state,
+            Some(&channel),
+            channel.parent_id.as_deref().unwrap_or_default(),
+        )
+    };
+
+    state.form = Some(Form::new(
+        format!("Edit {}", channel.name),
+        FormPurpose::EditChannel {
+            channel_id: channel.id,
+            channel_type: channel.channel_type,
+        },
+        fields,
+    ));
+}
+
+/// `:deletechannel`, behind a confirmation checkbox.
+pub fn open_delete_form(state: &mut App) {
+    let Some(channel) = target_channel(state) else {
+        state.status_message = "Select a channel to delete.".to_string();
+        return;
+    };
+    if !ensure_permission(state, Some(&channel), Permissions::MANAGE_CHANNELS) {
+        return;
+    }
+
+    state.form = Some(Form::new(
+        format!("Delete {}?", channel.name),
+        FormPurpose::DeleteChannel {
+            channel_id: channel.id,
+        },
+        vec![FormField::checkbox(
+            "confirm",
+            "Yes, delete it for everyone",
+            false,
+        )],
+    ));
+}
+
+/// `:overwrite <@everyone|role name|user>` edits the selected channel's overwrite for a role or member.
+pub fn open_overwrite_form(state: &mut App, args: &[&str]) {
+    let Some(channel) = target_channel(state) else {
+        state.status_message = "Select a channel first.".to_string();
+        return;
+    };
+    if !ensure_permission(state, Some(&channel), Permissions::MANAGE_ROLES) {
+        return;
+    }
+    let Some(context) = state.context.as_ref() else {
+        return;
+    };
+
+    let query = args.join(" ");
+    let query = query.trim_start_matches('@');
+    if query.is_empty() {
+        state.status_message = "Usage: :overwrite <@everyone|role name|user>".to_string();
+        return;
+    }
+
+    let role = if query.eq_ignore_ascii_case("everyone") {
+        Some((context.everyone_role_id.clone(), "@everyone".to_string()))
+    } else {
+        context
+            .all_guild_roles
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(query) || r.id == query)
+            .map(|r| (r.id.clone(), format!("@{}", r.name)))
+    };
+
+    let (target_id, target_type, target_name) = match role {
+        Some((id, name)) => (id, 0, name),
+        None => match state
+            .user_names
+            .iter()
+            .find(|(id, name)| name.eq_ignore_ascii_case(query) || *id == query)
+        {
+            Some((id, name)) => (id.clone(), 1, name.clone()),
+            None if query.chars().all(|c| c.is_ascii_digit()) => {
+                (query.to_string(), 1, query.to_string())
+            }
+            None => {
+                state.status_message = format!("No role or member named {query}.");
+                return;
+            }
+        },
+    };
+
+    let existing = channel
+        .permission_overwrites
+        .iter()
+        .find(|o| o.id == target_id);
+    let allow = existing
+        .map(|o| Permissions::parse(&o.allow))
+        .unwrap_or_default();
+    let deny = existing
+        .map(|o| Permissions::parse(&o.deny))
+        .unwrap_or_default();
+
+    let options: Vec<(String, String)> = [
+        ("inherit", "/ inherit"),
+        ("allow", "✓ allow"),
+        ("deny", "✗ deny"),
+    ]
+    .iter()
+    .map(|(v, l)| (v.to_string(), l.to_string()))
+    .collect();
+
+    let fields = Permissions::all()
+        .iter_names()
+        .map(|(name, permission)| {
+            let current = if allow.contains(permission) {
+                "allow"
+            } else if deny.contains(permission) {
+                "deny"
+            } else {
+                "inherit"
+            };
+            FormField::select(name, name, options.clone(), current)
+        })
+        .collect();
+
+    state.form = Some(Form::new(
+        format!("{target_name} in {}", channel.name),
+        FormPurpose::EditOverwrite {
+            channel_id: channel.id,
+            target_id,
+            target_type,
+        },
+        fields,
+    ));
+}
+
+/// `:movechannel up|down` swaps the selected channel with its neighbour.
+pub fn move_channel(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    let step: isize = match args.first() {
+        Some(&"up") => -1,
+        Some(&"down") => 1,
+        _ => {
+            state.status_message = "Usage: :movechannel up|down".to_string();
+            return;
+        }
+    };
+    let (Some(guild_id), Some(channel)) = (current_guild_id(state), target_channel(state)) else {
+        state.status_message = "Select a channel to move.".to_string();
+        return;
+    };
+    if !ensure_permission(state, Some(&channel), Permissions::MANAGE_CHANNELS) {
+        return;
+    }
+
+    // Categories move among categories, channels among the channels of their category
+    let mut siblings: Vec<Channel> = if channel.channel_type == 4 {
+        state
+            .channels
+            .iter()
+            .filter(|c| c.channel_type == 4)
+            .cloned()
+            .collect()
+    } else {
+        Channel::flatten_tree(&state.channels)
+            .into_iter()
+            .filter(|c| c.channel_type != 4 && c.parent_id == channel.parent_id)
+            .collect()
+    };
+
+    let Some(index) = siblings.iter().position(|c| c.id == channel.id) else {
+        return;
+    };
+    let Some(other) = index
+        .checked_add_signed(step)
+        .filter(|&i| i < siblings.len())
+    else {
+        return;
+    };
+    siblings.swap(index, other);
+
+    let positions: Vec<(String, i64)> = siblings
+        .iter()
+        .enumerate()
+        .map(|(position, c)| (c.id.clone(), position as i64))
+        .collect();
+
+    // Reorder locally right away, the request only confirms it
+    let mut channels = Channel::flatten_tree(&state.channels);
+    for channel in channels.iter_mut() {
+        if let Some((_, position)) = positions.iter().find(|(id, _)| id == &channel.id) {
+            channel.position = *position;
+        }
+    }
+    set_channels(state, &guild_id, channels);
+    // The selection follows the channel, past the children of a category moved down
+    if matches!(state.state, AppState::SelectingChannel(_, _))
+        && let Some(index) = channel_list(state).iter().position(|c| c.id == channel.id)
+    {
+        state.selection_index = index;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        if let Err(e) = api_client
+            .modify_channel_positions(&guild_id, &positions)
+            .await
+        {
+            report_error(&tx_action, "move channel", e).await;
+        }
+    });
+}
+
+/// Replaces the channel tree with `channels`, keeping the cache in sync.
+fn set_channels(state: &mut App, guild_id: &str, channels: Vec<Channel>) {
+    if let Some(store) = state.store.as_mut() {
+        store.save_channels(guild_id, &channels);
+    }
+    state.channels = Channel::filter_channels_by_categories(channels).unwrap_or_default();
+}
+
+/// Inserts or replaces a channel Discord confirmed was created or edited.
+pub fn apply_channel_change(state: &mut App, mut channel: Channel) {
+    let Some(guild_id) = channel.guild_id.clone() else {
+        return;
+    };
+    channel.children = None;
+
+    let mut channels = Channel::flatten_tree(&state.channels);
+    if channels
+        .first()
+        .is_some_and(|c| c.guild_id.as_ref() != Some(&guild_id))
+    {
+        return;
+    }
+    match channels.iter_mut().find(|c| c.id == channel.id) {
+        Some(existing) => *existing = channel,
+        None => channels.push(channel),
+    }
+    set_channels(state, &guild_id, channels);
+}
+
+pub fn apply_channel_delete(state: &mut App, channel_id: &str) {
+    let mut channels = Channel::flatten_tree(&state.channels);
+    let Some(guild_id) = channels
+        .iter()
+        .find(|c| c.id == channel_id)
+        .and_then(|c| c.guild_id.clone())
+    else {
+        return;
+    };
+
+    channels.retain(|c| c.id != channel_id);
+    // Channels of a deleted category become uncategorized
+    for channel in channels.iter_mut() {
+        if channel.parent_id.as_deref() == Some(channel_id) {
+            channel.parent_id = None;
+        }
+    }
+    set_channels(state, &guild_id, channels);
+
+    let last = channel_list(state).len().saturating_sub(1);
+    state.selection_index = state.selection_index.min(last);
+}
+
+async fn report_error(tx_action: &Sender<AppAction>, what: &str, e: crate::Error) {
+    let _ = print_log(format!("Failed to {what}: {e}").into(), LogType::Error);
+    tx_action
+        .send(AppAction::UpdateStatus(format!("Failed to {what}: {e}")))
+        .await
+        .ok();
+}
+
+fn channel_body(form: &Form, channel_type: u8, state: &mut App) -> Option<serde_json::Value> {
+    let name = form.text("name");
+    if name.is_empty() {
+        state.status_message = "A channel needs a name.".to_string();
+        return None;
+    }
+
+    let mut body = serde_json::json!({ "name": name });
+
+    // Categories only have a name
+    if channel_type == 4 {
+        return Some(body);
+    }
+
+    let slowmode = form.text("slowmode");
+    let Some(slowmode) = parse_duration(if slowmode.is_empty() { "0" } else { &slowmode })
+        .filter(|&s| s <= MAX_SLOWMODE_SECONDS)
+    else {
+        state.status_message = format!("Invalid slowmode: {slowmode} (at most 6h)");
+        return None;
+    };
+
+    let parent = form.selected("parent");
+    body["topic"] = serde_json::json!(form.text("topic"));
+    body["rate_limit_per_user"] = serde_json::json!(slowmode);
+    body["nsfw"] = serde_json::json!(form.checked("nsfw"));
+    body["parent_id"] = if parent.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::json!(parent)
+    };
+
+    Some(body)
+}
+
+/// Sends a submitted channel form to the API. Results come back as actions so the
+/// channel tree updates as soon as Discord confirms.
+pub fn submit_form(state: &mut App, form: Form, tx_action: &Sender<AppAction>) {
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+
+    match form.purpose.clone() {
+        FormPurpose::CreateChannel { guild_id } => {
+            let channel_type: u8 = form.selected("type").parse().unwrap_or(0);
+            let Some(mut body) = channel_body(&form, channel_type, state) else {
+                state.form = Some(form);
+                return;
+            };
+            body["type"] = serde_json::json!(channel_type);
+
+            state.status_message = "Creating channel...".to_string();
+            tokio::spawn(async move {
+                match api_client.create_guild_channel(&guild_id, body).await {
+                    Ok(channel) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus(format!(
+                                "Created {}.",
+                                channel.name
+                            )))
+                            .await
+                            .ok();
+                        tx_action
+                            .send(AppAction::ApiChannelChanged(channel))
+                            .await
+                            .ok();
+                    }
+                    Err(e) => report_error(&tx_action, "create channel", e).await,
+                }
+            });
+        }
+        FormPurpose::EditChannel {
+            channel_id,
+            channel_type,
+        } => {
+            let Some(body) = channel_body(&form, channel_type, state) else {
+                state.form = Some(form);
+                return;
+            };
+
+            state.status_message = "Saving channel...".to_string();
+            tokio::spawn(async move {
+                match api_client.modify_channel(&channel_id, body).await {
+                    Ok(channel) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus(format!("Saved {}.", channel.name)))
+                            .await
+                            .ok();
+                        tx_action
+                            .send(AppAction::ApiChannelChanged(channel))
+                            .await
+                            .ok();
+                    }
+                    Err(e) => report_error(&tx_action, "edit channel", e).await,
+                }
+            });
+        }
+        FormPurpose::DeleteChannel { channel_id } => {
+            if !form.checked("confirm") {
+                state.status_message = "Not deleted: tick the confirmation first.".to_string();
+                return;
+            }
+
+            state.status_message = "Deleting channel...".to_string();
+            tokio::spawn(async move {
+                match api_client.delete_channel(&channel_id).await {
+                    Ok(()) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus("Channel deleted.".to_string()))
+                            .await
+                            .ok();
+                        tx_action
+                            .send(AppAction::ApiChannelDeleted(channel_id))
+                            .await
+                            .ok();
+                    }
+                    Err(e) => report_error(&tx_action, "delete channel", e).await,
+                }
+            });
+        }
+        FormPurpose::EditOverwrite {
+            channel_id,
+            target_id,
+            target_type,
+        } => {
+            let mut allow = Permissions::empty();
+            let mut deny = Permissions::empty();
+            for (name, permission) in Permissions::all().iter_names() {
+                match form.selected(name).as_str() {
+                    "allow" => allow |= permission,
+                    "deny" => deny |= permission,
+                    _ => {}
+                }
+            }
+
+            let Some(mut channel) = Channel::flatten_tree(&state.channels)
+                .into_iter()
+                .find(|c| c.id == channel_id)
+            else {
+                return;
+            };
+
+            let overwrite = Overwrite {
+                id: target_id.clone(),
+                r#type: target_type,
+                allow: allow.bits().to_string(),
+                deny: deny.bits().to_string(),
+            };
+            channel.permission_overwrites.retain(|o| o.id != target_id);
+            let remove = allow.is_empty() && deny.is_empty();
+            if !remove {
+                channel.permission_overwrites.push(overwrite.clone());
+            }
+
+            state.status_message = "Saving permissions...".to_string();
+            tokio::spawn(async move {
+                let result = if remove {
+                    api_client
+                        .delete_channel_permission(&channel_id, &target_id)
+                        .await
+                } else {
+                    api_client
+                        .edit_channel_permissions(&channel_id, &overwrite)
+                        .await
+                };
+
+                match result {
+                    Ok(()) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus("Permissions saved.".to_string()))
+                            .await
+                            .ok();
+                        tx_action
+                            .send(AppAction::ApiChannelChanged(channel))
+                            .await
+                            .ok();
+                    }
+                    Err(e) => report_error(&tx_action, "save permissions", e).await,
+                }
+            });
+        }
+        _ => {}
+    }
+}
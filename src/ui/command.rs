@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    export::{self, ExportFormat},
+    logs::{LogType, print_log},
+    store::Store,
+    ui::{
+        audit_log, buffers, channel_admin, friends, group_dm, guild_list, inbox, members,
+        moderation::{self, ModerationAction},
+        notifications, profile, roles, unread, windows,
+    },
+};
+
+/// Runs a line typed at the `:` prompt.
+pub async fn execute_command(
+    state: &mut MutexGuard<'_, App>,
+    line: &str,
+    tx_action: &Sender<AppAction>,
+) {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return;
+    };
+    let args: Vec<&str> = words.collect();
+
+    match name {
+        "export" => export_current_channel(state, &args, tx_action),
+        "kick" => moderation::start_moderation(state, ModerationAction::Kick, None),
+        "ban" => ban(state, &args),
+        "unban" => moderation::start_moderation(
+            state,
+            ModerationAction::Unban,
+            args.first().map(|id| id.to_string()),
+        ),
+        "timeout" => timeout(state, &args),
+        "auditlog" => audit_log::open_audit_log(state, &args, tx_action),
+        "newchannel" => channel_admin::open_create_form(state, &args),
+        "editchannel" => channel_admin::open_edit_form(state),
+        "deletechannel" => channel_admin::open_delete_form(state),
+        "movechannel" => channel_admin::move_channel(state, &args, tx_action),
+        "overwrite" => channel_admin::open_overwrite_form(state, &args),
+        "roles" => roles::open_roles(state),
+        "profile" => profile::open_profile(state, &args, tx_action),
+        "member" => roles::open_member(state, &args, tx_action),
+        "friends" => match args.split_first() {
+            Some((&"add", username)) => friends::send_request(state, username.join(" "), tx_action),
+            _ => friends::open_friends(state, tx_action),
+        },
+        "dm" => group_dm::open_dm_with(state, &args, tx_action),
+        "gdm" => group_dm::run_command(state, &args, tx_action),
+        "pin" => guild_list::set_pin(state, Some(true)),
+        "unpin" => guild_list::set_pin(state, Some(false)),
+        "moveguild" => match args.first() {
+            Some(&"up") => guild_list::move_entry(state, -1),
+            Some(&"down") => guild_list::move_entry(state, 1),
+            _ => state.status_message = "Usage: :moveguild up|down".to_string(),
+        },
+        "unread" => unread::jump_to_first_unread(state, tx_action).await,
+        "inbox" => inbox::open_inbox(state, tx_action),
+        "notify" => notifications::open_settings(state, &args),
+        "mute" => notifications::mute(state, &args),
+        "unmute" => notifications::unmute(state, &args),
+        "b" | "bd" => buffers::run_command(state, name, &args, tx_action).await,
+        "split" | "sp" | "vsplit" | "vs" | "close" | "only" => {
+            windows::run_command(state, name, &args, tx_action).await
+        }
+        "members" => members::toggle(state, (!args.is_empty()).then(|| args.join(" ")), tx_action),
+        _ => {
+            state.status_message = format!("Not a command: {name}");
+        }
+    }
+}
+
+/// `:ban [delete window]`, e.g. `:ban 1d` also deletes the last day of their messages.
+fn ban(state: &mut MutexGuard<'_, App>, args: &[&str]) {
+    let delete_message_seconds = match args.first() {
+        Some(window) => match moderation::parse_ban_window(window) {
+            Some(seconds) => seconds,
+            None => {
+                state.status_message =
+                    format!("Invalid delete window: {window} (e.g. 1h, 1d, at most 7d)");
+                return;
+            }
+        },
+        None => 0,
+    };
+
+    moderation::start_moderation(
+        state,
+        ModerationAction::Ban {
+            delete_message_seconds,
+        },
+        None,
+    );
+}
+
+/// `:timeout <duration>` or `:timeout off`.
+fn timeout(state: &mut MutexGuard<'_, App>, args: &[&str]) {
+    let seconds = match args.first() {
+        Some(&"off") => None,
+        Some(duration) => match moderation::parse_timeout(duration) {
+            Some(seconds) => Some(seconds),
+            None => {
+                state.status_message =
+                    format!("Invalid timeout: {duration} (e.g. 10m, 1h, 1d, at most 28d)");
+                return;
+            }
+        },
+        None => {
+            state.status_message = "Usage: :timeout <duration> or :timeout off".to_string();
+            return;
+        }
+    };
+
+    moderation::start_moderation(state, ModerationAction::Timeout { seconds }, None);
+}
+
+/// `:export [json|md|html|txt] [path]`
+fn export_current_channel(
+    state: &mut MutexGuard<'_, App>,
+    args: &[&str],
+    tx_action: &Sender<AppAction>,
+) {
+    let AppState::Chatting(channel_id, channel_name) = state.state.clone() else {
+        state.status_message = "Open a channel to export it.".to_string();
+        return;
+    };
+
+    let format = match args.first() {
+        Some(name) => match ExportFormat::parse(name) {
+            Some(format) => format,
+            None => {
+                state.status_message =
+                    format!("Unknown export format: {name} (json, md, html or txt)");
+                return;
+            }
+        },
+        None => ExportFormat::Markdown,
+    };
+    let path = args.get(1).map(PathBuf::from);
+
+    state.status_message = format!("Exporting {channel_name}...");
+    let tx_action = tx_action.clone();
+
+    // Offline we can only export what the cache has seen
+    if state.offline {
+        let Some(store) = state.store.as_ref().and_then(Store::reopen) else {
+            state.status_message = "Export failed: the cache is unavailable.".to_string();
+            return;
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut messages = store.load_messages(&channel_id, None, u32::MAX as usize);
+            messages.reverse();
+
+            let status =
+                match export::write_export(&messages, &channel_id, &channel_name, format, path) {
+                    Ok(path) => format!(
+                        "Exported {} cached messages to {}",
+                        messages.len(),
+                        path.display()
+                    ),
+                    Err(e) => {
+                        let _ = print_log(format!("Export failed: {e}").into(), LogType::Error);
+                        format!("Export failed: {e}")
+                    }
+                };
+            tx_action
+                .blocking_send(AppAction::UpdateStatus(status))
+                .ok();
+        });
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+
+    tokio::spawn(async move {
+        let tx_progress = tx_action.clone();
+        let result = export::export_channel(
+            &api_client,
+            &channel_id,
+            &channel_name,
+            format,
+            path,
+            |count| {
+                tx_progress
+                    .try_send(AppAction::UpdateStatus(format!(
+                        "Exporting {channel_name}: {count} messages..."
+                    )))
+                    .ok();
+            },
+        )
+        .await;
+
+        let status = match result {
+            Ok((path, count)) => format!("Exported {count} messages to {}", path.display()),
+            Err(e) => {
+                let _ = print_log(format!("Export failed: {e}").into(), LogType::Error);
+                format!("Export failed: {e}")
+            }
+        };
+
+        tx_action.send(AppAction::UpdateStatus(status)).await.ok();
+    });
+}
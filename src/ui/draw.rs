@@ -1,4 +1,5 @@
 use ratatui::{
+    layout::Rect,
     style::{Color, Style, Stylize},
     text::Span,
     widgets::{BorderType, Clear, List, ListItem, ListState},
@@ -6,545 +7,1595 @@ use ratatui::{
 use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    App, AppState,
-    api::{Channel, DM, Emoji, Guild},
+    App, AppState, Window,
+    api::{
+        Channel, DM, Emoji, Message,
+        audit_log::action_name,
+        permissions::Permissions,
+        snowflake_timestamp_ms,
+        user::{RELATIONSHIP_BLOCKED, RELATIONSHIP_INCOMING, RELATIONSHIP_OUTGOING},
+    },
+    ui::{
+        audit_log::{AuditLogView, resolve_name},
+        buffers::{ChatView, current_chat},
+        events::{can_delete_message, can_edit_message, lacks_permission},
+        form::{FieldValue, Form},
+        friends::{self, FriendsView, sorted_relationships},
+        guild_list::{GuildRow, guild_rows_matching},
+        highlights::Highlights,
+        inbox::{InboxKind, InboxView, is_unread, unread_count},
+        members::member_groups,
+        moderation::visual_range,
+        popup::Popup,
+        roles::{RoleView, sorted_roles},
+        search::find_matches,
+        unread::{Badge, channel_badge, guild_badge, guilds_badge, is_first_unread},
+        windows,
+    },
 };
 
-pub fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
-    use ratatui::layout::{Constraint, Direction, Layout};
-    use ratatui::text::{Line, Text};
-    use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+/// A list entry with its unread state: bold when unread, dimmed when muted,
+/// followed by the mention counter.
+fn badged_item(text: String, style: Style, badge: Badge) -> ListItem<'static> {
+    let style = if badge.unread || badge.mentions > 0 {
+        style.bold()
+    } else if badge.muted {
+        style.dim()
+    } else {
+        style
+    };
+    let mut spans = vec![Span::styled(text, style)];
+    if badge.mentions > 0 {
+        spans.push(Span::styled(
+            badge.counter(),
+            Style::default().fg(Color::LightRed).bold(),
+        ));
+    }
+    ListItem::new(ratatui::text::Line::from(spans))
+}
 
-    let area = f.area();
+/// Splits a line into spans, highlighting every match of the active search pattern.
+fn highlight_matches(line: &str, pattern: Option<&str>, style: Style) -> Vec<Span<'static>> {
+    let ranges = pattern
+        .map(|pattern| find_matches(line, pattern))
+        .unwrap_or_default();
+    let match_style = style.fg(Color::Black).bg(Color::LightYellow);
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
-        .split(area);
+    let mut spans = Vec::new();
+    let mut last = 0;
 
-    app.terminal_height = chunks[0].height as usize;
-    app.terminal_width = chunks[0].width as usize;
+    for (start, end) in ranges {
+        if start > last {
+            spans.push(Span::styled(line[last..start].to_string(), style));
+        }
+        spans.push(Span::styled(line[start..end].to_string(), match_style));
+        last = end;
+    }
 
-    let max_height = app.terminal_height.saturating_sub(2);
-    let max_width = app.terminal_width.saturating_sub(2) as u16;
+    if last < line.len() || spans.is_empty() {
+        spans.push(Span::styled(line[last..].to_string(), style));
+    }
 
-    match &app.state {
-        AppState::Loading(_) => {
-            let loading_area = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Percentage(40),
-                    Constraint::Length(3),
-                    Constraint::Min(0),
-                ])
-                .split(chunks[0])[1];
+    spans
+}
 
-            let spinner = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-            let symbol = spinner[app.tick_count % spinner.len()];
+/// Like `highlight_matches`, with the config's keywords reversed as mentions are.
+fn highlight_line(
+    line: &str,
+    highlights: &Highlights,
+    pattern: Option<&str>,
+    style: Style,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+
+    for (start, end) in highlights.find(line) {
+        if start > last {
+            spans.extend(highlight_matches(&line[last..start], pattern, style));
+        }
+        spans.extend(highlight_matches(
+            &line[start..end],
+            pattern,
+            style.reversed(),
+        ));
+        last = end;
+    }
 
-            let loading_text = Line::from(vec![
-                Span::styled("Loading", Style::default().fg(Color::LightCyan)),
-                Span::raw(" "),
-                Span::styled(symbol, Style::default().fg(Color::LightCyan)),
-            ]);
+    if last < line.len() || spans.is_empty() {
+        spans.extend(highlight_matches(&line[last..], pattern, style));
+    }
 
-            let loading_paragraph = Paragraph::new(Text::from(vec![loading_text]))
-                .alignment(ratatui::layout::Alignment::Center)
-                .block(Block::default().borders(Borders::NONE));
+    spans
+}
 
-            f.render_widget(Clear, chunks[0]);
-            f.render_widget(loading_paragraph, loading_area);
+fn format_audit_value(key: &str, value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => "∅".to_string(),
+        // Permission bitfields are unreadable as numbers
+        Some(serde_json::Value::String(bits))
+            if matches!(key, "permissions" | "allow" | "deny") =>
+        {
+            let names: Vec<&str> = Permissions::parse(bits)
+                .iter_names()
+                .map(|(name, _)| name)
+                .collect();
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
         }
-        AppState::Home => {
-            let options = [
-                ("Guilds", Color::LightMagenta),
-                ("DMs", Color::LightYellow),
-                ("Quit", Color::LightRed),
-            ];
-
-            let items: Vec<ListItem> = options.iter().map(|o| ListItem::new(o.0).fg(o.1)).collect();
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
 
-            let list = List::new(items)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "vimcord Client - Home",
-                            Style::default().fg(Color::Yellow),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double),
-                )
-                .highlight_style(Style::default().reversed())
-                .highlight_symbol(">> ");
+/// Draws the audit log table, with the changes of the selected entry below when expanded.
+fn draw_audit_log(f: &mut ratatui::Frame, app: &App, view: &AuditLogView, area: Rect) {
+    use ratatui::layout::{Constraint, Direction, Layout};
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap};
+
+    let chunks = if view.expanded {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area)
+    } else {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(100)])
+            .split(area)
+    };
 
-            app.selection_index = app.selection_index.min(options.len().saturating_sub(1));
+    let rows: Vec<Row> = view
+        .entries
+        .iter()
+        .map(|entry| {
+            let time = chrono::DateTime::from_timestamp_millis(snowflake_timestamp_ms(&entry.id))
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let user = entry
+                .user_id
+                .as_deref()
+                .map(|id| resolve_name(app, view, id))
+                .unwrap_or_default();
+            let target = entry
+                .target_id
+                .as_deref()
+                .map(|id| resolve_name(app, view, id))
+                .unwrap_or_default();
+
+            Row::new(vec![
+                Cell::from(time).style(Style::default().fg(Color::LightCyan)),
+                Cell::from(user).style(Style::default().fg(Color::Yellow)),
+                Cell::from(action_name(entry.action_type)),
+                Cell::from(target),
+                Cell::from(entry.reason.clone().unwrap_or_default())
+                    .style(Style::default().fg(Color::Gray)),
+            ])
+        })
+        .collect();
+
+    let mut title = "Audit log".to_string();
+    if let Some(user_id) = &view.filter.user_id {
+        title.push_str(&format!(" | user: {}", resolve_name(app, view, user_id)));
+    }
+    if let Some(action_type) = view.filter.action_type {
+        title.push_str(&format!(" | action: {}", action_name(action_type)));
+    }
+    if view.is_loading {
+        title.push_str(" | loading...");
+    } else if view.entries.is_empty() {
+        title.push_str(" | no entries");
+    }
 
-            let mut state = ListState::default().with_selected(Some(app.selection_index));
-            f.render_widget(Clear, chunks[0]);
-            f.render_stateful_widget(list, chunks[0], &mut state);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(16),
+            Constraint::Percentage(20),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["Time", "User", "Action", "Target", "Reason"])
+            .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(
+        Block::default()
+            .title(Span::styled(title, Style::default().fg(Color::Yellow)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double),
+    )
+    .row_highlight_style(Style::default().reversed())
+    .highlight_symbol(">> ");
+
+    let mut state = TableState::default().with_selected(Some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, chunks[0], &mut state);
+
+    if view.expanded
+        && let Some(entry) = view.entries.get(view.selected)
+    {
+        let mut lines: Vec<Line> = entry
+            .changes
+            .iter()
+            .map(|change| {
+                Line::from(vec![
+                    Span::styled(format!("{}: ", change.key), Style::default().bold()),
+                    Span::styled(
+                        format_audit_value(&change.key, change.old_value.as_ref()),
+                        Style::default().fg(Color::LightRed),
+                    ),
+                    Span::raw(" → "),
+                    Span::styled(
+                        format_audit_value(&change.key, change.new_value.as_ref()),
+                        Style::default().fg(Color::LightGreen),
+                    ),
+                ])
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "No recorded changes",
+                Style::default().fg(Color::DarkGray),
+            )));
         }
-        AppState::SelectingDM => {
-            let filter_text = app.input.to_lowercase();
 
-            let filtered_dms: Vec<&DM> = app
-                .dms
-                .iter()
-                .filter(|d| d.get_name().to_lowercase().contains(&filter_text))
-                .collect();
+        let diff = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(Span::styled("Changes", Style::default().fg(Color::Yellow)))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Double),
+            )
+            .wrap(Wrap { trim: false });
+        f.render_widget(diff, chunks[1]);
+    }
+}
 
-            let items: Vec<ListItem> = filtered_dms
-                .iter()
-                .map(|d| {
-                    let mut spans = Vec::new();
-
-                    if d.channel_type == 1 && d.recipients.len() == 1 {
-                        let (status_char, status_color) = match app
-                            .user_statuses
-                            .get(&d.recipients[0].id)
-                            .map(|s| s.as_str())
-                        {
-                            Some("online") => ("", Color::LightGreen),
-                            Some("idle") => ("", Color::LightYellow),
-                            Some("dnd") => ("", Color::LightRed),
-                            _ => ("", Color::DarkGray), // offline/invisible/unknown
-                        };
-                        spans.push(Span::styled(
-                            format!("{} ", status_char),
-                            Style::default().fg(status_color),
-                        ));
-                    }
+/// Draws the guild roles, highest first, each in its own color.
+fn draw_role_view(f: &mut ratatui::Frame, app: &App, view: &RoleView, area: Rect) {
+    use ratatui::layout::Constraint;
+    use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
 
-                    let char = match d.channel_type {
-                        1 => '',
-                        3 => '',
-                        _ => '',
-                    };
+    let Some(context) = app.context.as_ref() else {
+        return;
+    };
 
-                    let color = match d.channel_type {
-                        1 => Color::LightMagenta,
-                        3 => Color::LightBlue,
-                        _ => Color::LightRed,
-                    };
+    let rows: Vec<Row> = sorted_roles(context)
+        .into_iter()
+        .map(|role| {
+            let color = role
+                .rgb()
+                .map_or(Color::Gray, |(r, g, b)| Color::Rgb(r, g, b));
+            let permissions = Permissions::parse(&role.permissions);
+            let flags: Vec<&str> = [
+                (role.hoist, "hoisted"),
+                (role.mentionable, "mentionable"),
+                (role.managed, "managed"),
+                (
+                    permissions.contains(Permissions::ADMINISTRATOR),
+                    "administrator",
+                ),
+            ]
+            .into_iter()
+            .filter_map(|(set, flag)| set.then_some(flag))
+            .collect();
 
-                    spans.push(Span::styled(
-                        format!("{char} {}", d.get_name()),
-                        Style::default().fg(color),
-                    ));
+            Row::new(vec![
+                Cell::from("●").style(Style::default().fg(color)),
+                Cell::from(format!("@{}", role.name.trim_start_matches('@')))
+                    .style(Style::default().fg(color)),
+                Cell::from(permissions.iter().count().to_string()),
+                Cell::from(flags.join(", ")).style(Style::default().fg(Color::Gray)),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Percentage(40),
+            Constraint::Length(11),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["", "Role", "Permissions", "Flags"])
+            .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(
+        Block::default()
+            .title(Span::styled("Roles", Style::default().fg(Color::Yellow)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double),
+    )
+    .row_highlight_style(Style::default().reversed())
+    .highlight_symbol(">> ");
+
+    let mut state = TableState::default().with_selected(Some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
 
-                    ListItem::new(Line::from(spans))
+/// Draws the inbox, newest first, unread entries in bold.
+fn draw_inbox(f: &mut ratatui::Frame, app: &App, view: &InboxView, area: Rect) {
+    use ratatui::layout::Constraint;
+    use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
+
+    let rows: Vec<Row> = app
+        .inbox
+        .iter()
+        .map(|entry| {
+            let time = chrono::DateTime::parse_from_rfc3339(&entry.message.timestamp)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%m-%d %H:%M")
+                        .to_string()
                 })
-                .collect();
+                .unwrap_or_default();
+            let kind_color = match entry.kind {
+                InboxKind::Mention => Color::LightRed,
+                InboxKind::DirectMessage => Color::LightYellow,
+                InboxKind::Keyword => Color::LightMagenta,
+            };
+            let author = entry
+                .message
+                .author
+                .global_name
+                .clone()
+                .unwrap_or(entry.message.author.username.clone());
+            let content = entry.message.map_mentions().replace('\n', " ");
+
+            let row = Row::new(vec![
+                Cell::from(time).style(Style::default().fg(Color::LightBlue)),
+                Cell::from(entry.kind.label()).style(Style::default().fg(kind_color)),
+                Cell::from(entry.location.clone()).style(Style::default().fg(Color::LightCyan)),
+                Cell::from(author).style(Style::default().fg(Color::Yellow)),
+                Cell::from(content),
+            ]);
+            if is_unread(app, entry) {
+                row.bold()
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let empty = rows.is_empty();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(11),
+            Constraint::Length(7),
+            Constraint::Percentage(25),
+            Constraint::Length(16),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["Time", "Kind", "Where", "From", "Message"])
+            .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(
+        Block::default()
+            .title(Span::styled(
+                if empty {
+                    "Inbox (nothing yet)"
+                } else {
+                    "Inbox"
+                },
+                Style::default().fg(Color::Yellow),
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double),
+    )
+    .row_highlight_style(Style::default().reversed())
+    .highlight_symbol(">> ");
+
+    let mut state = TableState::default().with_selected(Some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
 
-            let num_filtered = items.len();
-            app.selection_index = app.selection_index.min(num_filtered.saturating_sub(1));
+/// Draws friends, pending requests and blocked users, in the order of `sorted_relationships`.
+fn draw_friends(f: &mut ratatui::Frame, app: &App, view: &FriendsView, area: Rect) {
+    use ratatui::layout::Constraint;
+    use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
+
+    let rows: Vec<Row> = sorted_relationships(app)
+        .into_iter()
+        .map(|relationship| {
+            let status = app
+                .user_statuses
+                .get(&relationship.id)
+                .map_or("offline", |s| s.as_str());
+            let kind_color = match relationship.kind {
+                RELATIONSHIP_INCOMING => Color::LightGreen,
+                RELATIONSHIP_OUTGOING => Color::LightYellow,
+                RELATIONSHIP_BLOCKED => Color::LightRed,
+                _ => Color::Gray,
+            };
+            let activity = app
+                .user_activities
+                .get(&relationship.id)
+                .cloned()
+                .unwrap_or_default();
 
-            let list = List::new(items)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "vimcord Client - Direct Messages",
-                            Style::default().fg(Color::Yellow),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double),
-                )
-                .highlight_style(Style::default().reversed())
-                .highlight_symbol(">> ");
+            Row::new(vec![
+                Cell::from("●").style(Style::default().fg(status_color(status))),
+                if view.marked.contains(&relationship.id) {
+                    Cell::from(format!("✓ {}", friends::display_name(relationship)))
+                        .style(Style::default().fg(Color::LightCyan).bold())
+                } else {
+                    Cell::from(friends::display_name(relationship))
+                },
+                Cell::from(relationship.user.tag()).style(Style::default().fg(Color::Gray)),
+                Cell::from(friends::kind_label(relationship.kind))
+                    .style(Style::default().fg(kind_color)),
+                Cell::from(activity).style(Style::default().fg(Color::Gray)),
+            ])
+        })
+        .collect();
+
+    let empty = rows.is_empty();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Length(16),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["", "Name", "Username", "Relationship", "Activity"])
+            .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(
+        Block::default()
+            .title(Span::styled(
+                if empty {
+                    "Friends (none yet, n to add one)"
+                } else {
+                    "Friends"
+                },
+                Style::default().fg(Color::Yellow),
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double),
+    )
+    .row_highlight_style(Style::default().reversed())
+    .highlight_symbol(">> ");
+
+    let mut state = TableState::default().with_selected(Some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(table, area, &mut state);
+}
 
-            let mut state = ListState::default().with_selected(Some(app.selection_index));
-            f.render_widget(Clear, chunks[0]);
-            f.render_stateful_widget(list, chunks[0], &mut state);
+const MEMBER_LIST_WIDTH: u16 = 28;
+const MIN_WIDTH_WITH_MEMBERS: u16 = 80;
+
+fn status_color(status: &str) -> Color {
+    match status {
+        "online" => Color::Green,
+        "idle" => Color::Yellow,
+        "dnd" => Color::Red,
+        _ => Color::DarkGray,
+    }
+}
+
+/// Draws the member list pane, grouped as `member_groups` sorts it.
+fn draw_member_list(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Borders, Paragraph};
+
+    let groups = member_groups(app);
+    let count: usize = groups.iter().map(|g| g.members.len()).sum();
+
+    let mut lines: Vec<Line> = Vec::new();
+    for group in &groups {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
         }
-        AppState::SelectingGuild => {
-            let filter_text = app.input.to_lowercase();
+        lines.push(Line::from(Span::styled(
+            format!("{} — {}", group.name.to_uppercase(), group.members.len()),
+            Style::default().fg(Color::Gray).bold(),
+        )));
+
+        for member in &group.members {
+            let offline = member.status == "offline";
+            let name_color = match member.color {
+                _ if offline => Color::DarkGray,
+                Some((r, g, b)) => Color::Rgb(r, g, b),
+                None => Color::White,
+            };
+            lines.push(Line::from(vec![
+                Span::styled("● ", Style::default().fg(status_color(&member.status))),
+                Span::styled(member.name.clone(), Style::default().fg(name_color)),
+            ]));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No members known yet",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
 
-            let filtered_guilds: Vec<&Guild> = app
-                .guilds
-                .iter()
-                .filter(|g| g.name.to_lowercase().contains(&filter_text))
-                .collect();
+    let mut title = format!("Members ({count})");
+    if !app.member_list.filter.is_empty() {
+        title.push_str(&format!(" /{}", app.member_list.filter));
+    }
 
-            let mut count = 0;
-            let items: Vec<ListItem> = filtered_guilds
-                .iter()
-                .map(|g| {
-                    let color = if count % 2 == 0 {
-                        Color::LightCyan
-                    } else {
-                        Color::LightYellow
-                    };
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(Span::styled(title, Style::default().fg(Color::Yellow)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double),
+    );
 
-                    count += 1;
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
 
-                    ListItem::new(g.name.as_str()).style(Style::default().fg(color))
-                })
-                .collect();
+/// Color of a guild member's highest colored role, `None` outside of guilds.
+fn role_color(app: &App, user_id: &str) -> Option<Color> {
+    app.channel_permissions?;
+    let context = app.context.as_ref()?;
+    let member = app.members.get(&context.everyone_role_id)?.get(user_id)?;
+    context
+        .color_of(&member.roles)
+        .map(|(r, g, b)| Color::Rgb(r, g, b))
+}
 
-            let num_filtered = items.len();
-            app.selection_index = app.selection_index.min(num_filtered.saturating_sub(1));
+/// Draws a scrollable popup centered over `area`.
+fn draw_popup(f: &mut ratatui::Frame, popup: &Popup, area: Rect) {
+    use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
 
-            let list = List::new(items)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "vimcord Client - Guilds",
-                            Style::default().fg(Color::Yellow),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double),
-                )
-                .highlight_style(Style::default().reversed())
-                .highlight_symbol(">> ");
+    let width = area.width.saturating_mul(4) / 5;
+    let height = area.height.saturating_mul(4) / 5;
+    let popup_rect = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
 
-            let mut state = ListState::default().with_selected(Some(app.selection_index));
-            f.render_widget(Clear, chunks[0]);
-            f.render_stateful_widget(list, chunks[0], &mut state);
-        }
-        AppState::SelectingChannel(_, guild_name) => {
-            let filter_text = app.input.to_lowercase();
+    let paragraph = Paragraph::new(popup.lines.clone())
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    popup.title.clone(),
+                    Style::default().fg(Color::Yellow),
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Double),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((popup.scroll as u16, 0));
 
-            let permission_context = &app.context;
+    f.render_widget(Clear, popup_rect);
+    f.render_widget(paragraph, popup_rect);
+}
 
-            let mut list_items: Vec<ListItem> = Vec::new();
+/// Draws a form as a dialog centered over `area`, one field per line.
+fn draw_form(f: &mut ratatui::Frame, form: &Form, area: Rect) {
+    use ratatui::layout::{Constraint, Direction, Layout};
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Borders, Paragraph};
+
+    let label_width = form
+        .fields
+        .iter()
+        .map(|field| field.label.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let lines: Vec<Line> = form
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let value = match &field.value {
+                FieldValue::Text(text) if i == form.focused => format!("{text}_"),
+                FieldValue::Text(text) => text.clone(),
+                FieldValue::Select { options, selected } => format!(
+                    "◀ {} ▶",
+                    options
+                        .get(*selected)
+                        .map(|(_, label)| label.as_str())
+                        .unwrap_or_default()
+                ),
+                FieldValue::Checkbox(checked) => {
+                    format!("[{}]", if *checked { "x" } else { " " })
+                }
+            };
+
+            let value_style = if i == form.focused {
+                Style::default().reversed()
+            } else {
+                Style::default()
+            };
+
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>label_width$}  ", field.label),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(value, value_style),
+            ])
+        })
+        .collect();
+
+    let width = area.width.saturating_mul(4) / 5;
+    let height = (lines.len() as u16 + 4).min(area.height);
+    let form_rect = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let block = Block::default()
+        .title(Span::styled(
+            form.title.clone(),
+            Style::default().fg(Color::Yellow),
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double);
+    let inner = block.inner(form_rect);
 
-            let should_display_channel_content = |c: &Channel| {
-                let is_readable = permission_context
-                    .as_ref()
-                    .is_some_and(|context| c.is_readable(context));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    // Keep the focused field on screen in long forms
+    let visible = chunks[0].height as usize;
+    let scroll = (form.focused + 1).saturating_sub(visible);
+
+    f.render_widget(Clear, form_rect);
+    f.render_widget(block, form_rect);
+    f.render_widget(Paragraph::new(lines).scroll((scroll as u16, 0)), chunks[0]);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "↑/↓ move · ←/→ change · Space toggle · Enter save · Esc cancel",
+            Style::default().fg(Color::DarkGray),
+        )),
+        chunks[1],
+    );
+}
+
+/// The double-bordered block of every screen, dimmed when its pane doesn't have the focus.
+fn pane_block(title: String, focused: bool) -> ratatui::widgets::Block<'static> {
+    use ratatui::widgets::{Block, Borders};
 
-                is_readable
-                    && (filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text))
+    let color = if focused {
+        Color::Yellow
+    } else {
+        Color::DarkGray
+    };
+    let block = Block::default()
+        .title(Span::styled(title, Style::default().fg(color)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double);
+    if focused {
+        block
+    } else {
+        block.border_style(Style::default().fg(Color::DarkGray))
+    }
+}
+
+/// A list in its pane, an unfocused one marking what the other panes show.
+fn pane_list(items: Vec<ListItem<'_>>, title: String, focused: bool) -> List<'_> {
+    List::new(items)
+        .block(pane_block(title, focused))
+        .highlight_style(if focused {
+            Style::default().reversed()
+        } else {
+            Style::default().bg(Color::DarkGray)
+        })
+        .highlight_symbol(">> ")
+}
+
+fn draw_loading(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    use ratatui::layout::{Constraint, Direction, Layout};
+    use ratatui::text::{Line, Text};
+    use ratatui::widgets::{Block, Borders, Paragraph};
+
+    let loading_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(40),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(area)[1];
+
+    let spinner = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    let symbol = spinner[app.tick_count % spinner.len()];
+
+    let loading_text = Line::from(vec![
+        Span::styled("Loading", Style::default().fg(Color::LightCyan)),
+        Span::raw(" "),
+        Span::styled(symbol, Style::default().fg(Color::LightCyan)),
+    ]);
+
+    let loading_paragraph = Paragraph::new(Text::from(vec![loading_text]))
+        .alignment(ratatui::layout::Alignment::Center)
+        .block(Block::default().borders(Borders::NONE));
+
+    f.render_widget(Clear, area);
+    f.render_widget(loading_paragraph, loading_area);
+}
+
+fn draw_home(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
+    let inbox = match unread_count(app) {
+        0 => "Inbox".to_string(),
+        n => format!("Inbox ({n})"),
+    };
+    let options = [
+        ("Guilds", Color::LightMagenta),
+        ("DMs", Color::LightYellow),
+        ("Friends", Color::LightGreen),
+        (inbox.as_str(), Color::LightCyan),
+        ("Quit", Color::LightRed),
+    ];
+
+    let items: Vec<ListItem> = options.iter().map(|o| ListItem::new(o.0).fg(o.1)).collect();
+    let list = pane_list(items, "vimcord Client - Home".to_string(), true);
+
+    app.selection_index = app.selection_index.min(options.len().saturating_sub(1));
+
+    let mut state = ListState::default().with_selected(Some(app.selection_index));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// The DM list, filtered by the input line while it has the focus.
+fn draw_dm_list(f: &mut ratatui::Frame, app: &mut App, area: Rect, focused: bool) {
+    use ratatui::text::Line;
+
+    let filter_text = if focused {
+        app.input.to_lowercase()
+    } else {
+        String::new()
+    };
+    let open_chat = app.panes.chat.as_ref().map(|(id, _)| id.as_str());
+
+    let filtered_dms: Vec<&DM> = app
+        .dms
+        .iter()
+        .filter(|d| d.get_name().to_lowercase().contains(&filter_text))
+        .collect();
+    let open_index = filtered_dms
+        .iter()
+        .position(|d| Some(d.id.as_str()) == open_chat);
+
+    let items: Vec<ListItem> = filtered_dms
+        .iter()
+        .map(|d| {
+            let mut spans = Vec::new();
+
+            if d.channel_type == 1 && d.recipients.len() == 1 {
+                let (status_char, status_color) = match app
+                    .user_statuses
+                    .get(&d.recipients[0].id)
+                    .map(|s| s.as_str())
+                {
+                    Some("online") => ("", Color::LightGreen),
+                    Some("idle") => ("", Color::LightYellow),
+                    Some("dnd") => ("", Color::LightRed),
+                    _ => ("", Color::DarkGray), // offline/invisible/unknown
+                };
+                spans.push(Span::styled(
+                    format!("{} ", status_char),
+                    Style::default().fg(status_color),
+                ));
+            }
+
+            let char = match d.channel_type {
+                1 => '',
+                3 => '',
+                _ => '',
             };
 
-            app.channels
-                .iter()
-                .filter(|c| {
-                    if c.children.is_none() && c.channel_type != 4 {
-                        return should_display_channel_content(c);
-                    }
+            let color = match d.channel_type {
+                1 => Color::LightMagenta,
+                3 => Color::LightBlue,
+                _ => Color::LightRed,
+            };
 
-                    if c.channel_type == 4 {
-                        if filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text) {
-                            return true;
-                        }
+            let badge = channel_badge(app, &d.id);
+            let style = Style::default().fg(color);
+            spans.push(Span::styled(
+                format!("{char} {}", d.get_name()),
+                if badge.unread { style.bold() } else { style },
+            ));
+            if badge.mentions > 0 {
+                spans.push(Span::styled(
+                    badge.counter(),
+                    Style::default().fg(Color::LightRed).bold(),
+                ));
+            }
 
-                        if let Some(children) = &c.children {
-                            return children.iter().any(should_display_channel_content);
-                        }
-                    }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
 
-                    false
-                })
-                .for_each(|c| {
-                    let get_channel_style = |channel_type: u8| -> (char, Color) {
-                        match channel_type {
-                            15 => ('', Color::LightYellow),
-                            13 => ('󱝉', Color::LightRed),
-                            5 => ('', Color::LightGreen),
-                            4 => ('', Color::Gray),
-                            2 => ('', Color::LightCyan),
-                            0 => ('', Color::LightBlue),
-                            _ => ('', Color::LightMagenta),
-                        }
-                    };
-
-                    if c.channel_type == 4 {
-                        let (char, color) = get_channel_style(c.channel_type);
-                        list_items.push(
-                            ListItem::new(format!("{char} {}", c.name))
-                                .style(Style::default().fg(color)),
-                        );
-
-                        if let Some(children) = &c.children {
-                            children
-                                .iter()
-                                .filter(|c| should_display_channel_content(c))
-                                .for_each(|child| {
-                                    let (char, color) = get_channel_style(child.channel_type);
-
-                                    list_items.push(
-                                        ListItem::new(format!("  {char} {}", child.name))
-                                            .style(Style::default().fg(color)),
-                                    );
-                                });
-                        }
-                    } else {
-                        let (char, color) = get_channel_style(c.channel_type);
-                        list_items.push(
-                            ListItem::new(format!("{char} {}", c.name))
-                                .style(Style::default().fg(color)),
-                        );
-                    }
-                });
-
-            let num_filtered = list_items.len();
-            app.selection_index = app.selection_index.min(num_filtered.saturating_sub(1));
-
-            let hidden_items: Vec<ListItem> = app
-                .channels
-                .iter()
-                .flat_map(|c| {
-                    if c.channel_type == 4 {
-                        let mut items: Vec<&Channel> = Vec::new();
-
-                        if let Some(children) = &c.children {
-                            items.extend(children.iter().filter(|child| {
-                                permission_context
-                                    .as_ref()
-                                    .is_some_and(|context| !child.is_readable(context))
-                            }));
-                        }
-                        items
-                    } else if permission_context
-                        .as_ref()
-                        .is_some_and(|context| !c.is_readable(context))
-                    {
-                        vec![c]
-                    } else {
-                        vec![]
-                    }
-                })
-                .map(|c| {
-                    let char = match c.channel_type {
-                        15 => '',
-                        13 => '󱝉',
-                        5 => '',
-                        4 => '',
-                        2 => '',
-                        0 => '',
-                        _ => '',
-                    };
-
-                    let color = Color::DarkGray;
-
-                    ListItem::new(format!(" {char} {}", c.name)).style(Style::default().fg(color))
-                })
-                .collect();
+    let selected = if focused {
+        app.selection_index = app.selection_index.min(items.len().saturating_sub(1));
+        Some(app.selection_index)
+    } else {
+        open_index
+    };
 
-            list_items.extend(hidden_items);
+    let title = if app.panes.active {
+        "Direct Messages"
+    } else {
+        "vimcord Client - Direct Messages"
+    };
+    let list = pane_list(items, title.to_string(), focused);
 
-            let title = format!(
-                "Channels for Guild: {} | Channels found: {} | Actual index: {}",
-                guild_name,
-                num_filtered.saturating_sub(1),
-                app.selection_index
-            );
+    let mut state = ListState::default().with_selected(selected);
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
 
-            let list = List::new(list_items)
-                .block(
-                    Block::default()
-                        .title(Span::styled(title, Style::default().fg(Color::Yellow)))
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double),
+/// The server list and its folders, filtered by the input line while it has the focus.
+fn draw_guild_list(f: &mut ratatui::Frame, app: &mut App, area: Rect, focused: bool) {
+    let rows = guild_rows_matching(app, if focused { &app.input } else { "" });
+    let open_index = rows.iter().position(
+        |row| matches!(row, GuildRow::Guild { id, .. } if Some(id) == app.panes.guild_id.as_ref()),
+    );
+
+    let mut count = 0;
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .map(|row| match row {
+            GuildRow::Folder {
+                name,
+                color,
+                guild_ids,
+                collapsed,
+                ..
+            } => {
+                let color = color.map_or(Color::LightBlue, |(r, g, b)| Color::Rgb(r, g, b));
+                let arrow = if collapsed { "▸" } else { "▾" };
+                badged_item(
+                    format!("{arrow} {name} [{}]", guild_ids.len()),
+                    Style::default().fg(color),
+                    guilds_badge(app, &guild_ids),
                 )
-                .highlight_style(Style::default().reversed())
-                .highlight_symbol(">> ");
+            }
+            GuildRow::Guild {
+                id,
+                name,
+                in_folder,
+                pinned,
+            } => {
+                let color = if count % 2 == 0 {
+                    Color::LightCyan
+                } else {
+                    Color::LightYellow
+                };
+                count += 1;
 
-            let mut state = ListState::default().with_selected(Some(app.selection_index));
-            f.render_widget(Clear, chunks[0]);
-            f.render_stateful_widget(list, chunks[0], &mut state);
-        }
-        AppState::Chatting(_, channel_name)
-        | AppState::EmojiSelection(_, channel_name)
-        | AppState::Editing(_, channel_name, _, _) => {
-            if max_width == 0 {
-                return;
+                let prefix = match (pinned, in_folder) {
+                    (true, _) => "★ ",
+                    (false, true) => "  │ ",
+                    (false, false) => "",
+                };
+                badged_item(
+                    format!("{prefix}{name}"),
+                    Style::default().fg(color),
+                    guild_badge(app, &id),
+                )
             }
+        })
+        .collect();
 
-            let mut messages_reversed_with_index =
-                app.messages.iter().enumerate().collect::<Vec<_>>();
-            messages_reversed_with_index.reverse(); // Oldest first
+    let selected = if focused {
+        app.selection_index = app.selection_index.min(items.len().saturating_sub(1));
+        Some(app.selection_index)
+    } else {
+        open_index
+    };
 
-            let mut final_content: Vec<Line> = Vec::new();
-            let mut total_visual_height = 0;
+    let title = if app.panes.active {
+        "Servers"
+    } else {
+        "vimcord Client - Guilds"
+    };
+    let list = pane_list(items, title.to_string(), focused);
 
-            for (original_idx, message) in messages_reversed_with_index.into_iter() {
-                let is_selected =
-                    app.selection_index > 0 && app.selection_index - 1 == original_idx;
+    let mut state = ListState::default().with_selected(selected);
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
 
-                let formatted_text = format!(
-                    "[{}] {}: {}",
-                    message
-                        .timestamp
-                        .split('T')
-                        .next()
-                        .unwrap_or("")
-                        .to_string()
-                        + " "
-                        + message
-                            .timestamp
-                            .split('T')
-                            .nth(1)
-                            .unwrap_or("")
-                            .split('.')
-                            .next()
-                            .unwrap_or(""),
-                    message
-                        .author
-                        .global_name
-                        .clone()
-                        .unwrap_or(message.author.username.clone()),
-                    message.content.as_deref().unwrap_or("(*non-text*)")
-                );
+/// The channel tree of a guild, channels we can't read greyed out at the end.
+fn draw_channel_list(
+    f: &mut ratatui::Frame,
+    app: &mut App,
+    area: Rect,
+    guild_name: &str,
+    focused: bool,
+) {
+    let filter_text = if focused {
+        app.input.to_lowercase()
+    } else {
+        String::new()
+    };
+    let open_chat = app.panes.chat.as_ref().map(|(id, _)| id.as_str());
 
-                let text_lines: Vec<&str> = formatted_text.split('\n').collect();
-                let mut estimated_height = 0;
+    let permission_context = &app.context;
 
-                let safe_max_width = max_width.saturating_sub(4);
-                for line in text_lines {
-                    let width = UnicodeWidthStr::width(line);
+    let mut list_items: Vec<ListItem> = Vec::new();
+    // Channel id of every item, to find the open one
+    let mut item_ids: Vec<&str> = Vec::new();
 
-                    if width == 0 || safe_max_width == 0 {
-                        estimated_height += 1;
-                        continue;
-                    }
+    let should_display_channel_content = |c: &Channel| {
+        let is_readable = permission_context
+            .as_ref()
+            .is_some_and(|context| c.is_readable(context));
 
-                    let mut current_line_width = 0;
-                    let mut first_word = true;
-
-                    for word in line.split(' ') {
-                        let word_width = UnicodeWidthStr::width(word);
-                        let space_width = if first_word { 0 } else { 1 };
-
-                        if current_line_width + space_width + word_width <= safe_max_width as usize
-                        {
-                            current_line_width += space_width + word_width;
-                        } else {
-                            if current_line_width > 0 {
-                                estimated_height += 1;
-                            }
-
-                            if word_width > safe_max_width as usize {
-                                let chunks = word_width.div_ceil(safe_max_width as usize);
-                                estimated_height += chunks.saturating_sub(1);
-                                current_line_width = word_width % safe_max_width as usize;
-                                if current_line_width == 0 {
-                                    current_line_width = safe_max_width as usize;
-                                }
-                            } else {
-                                current_line_width = word_width;
-                            }
-                        }
-                        first_word = false;
-                    }
-                    if current_line_width > 0 {
-                        estimated_height += 1;
-                    }
+        is_readable && (filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text))
+    };
+
+    app.channels
+        .iter()
+        .filter(|c| {
+            if c.children.is_none() && c.channel_type != 4 {
+                return should_display_channel_content(c);
+            }
+
+            if c.channel_type == 4 {
+                if filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text) {
+                    return true;
                 }
 
-                let start_y = total_visual_height;
-                total_visual_height += estimated_height;
-                let end_y = total_visual_height;
+                if let Some(children) = &c.children {
+                    return children.iter().any(should_display_channel_content);
+                }
+            }
 
-                if is_selected {
-                    if start_y < app.chat_scroll_offset {
-                        app.chat_scroll_offset = start_y;
-                    } else if end_y > app.chat_scroll_offset + max_height {
-                        app.chat_scroll_offset = end_y.saturating_sub(max_height);
-                    }
+            false
+        })
+        .for_each(|c| {
+            let get_channel_style = |channel_type: u8| -> (char, Color) {
+                match channel_type {
+                    15 => ('', Color::LightYellow),
+                    13 => ('󱝉', Color::LightRed),
+                    5 => ('', Color::LightGreen),
+                    4 => ('', Color::Gray),
+                    2 => ('', Color::LightCyan),
+                    0 => ('', Color::LightBlue),
+                    _ => ('', Color::LightMagenta),
                 }
+            };
 
-                let formatted_time = format!(
-                    " {}]",
-                    message
-                        .timestamp
-                        .split('T')
-                        .nth(1)
-                        .unwrap_or("")
-                        .split('.')
-                        .next()
-                        .unwrap_or(""),
+            if c.channel_type == 4 {
+                let (char, color) = get_channel_style(c.channel_type);
+                list_items.push(
+                    ListItem::new(format!("{char} {}", c.name)).style(Style::default().fg(color)),
                 );
+                item_ids.push(&c.id);
+
+                if let Some(children) = &c.children {
+                    children
+                        .iter()
+                        .filter(|c| should_display_channel_content(c))
+                        .for_each(|child| {
+                            let (char, color) = get_channel_style(child.channel_type);
+
+                            list_items.push(badged_item(
+                                format!("  {char} {}", child.name),
+                                Style::default().fg(color),
+                                channel_badge(app, &child.id),
+                            ));
+                            item_ids.push(&child.id);
+                        });
+                }
+            } else {
+                let (char, color) = get_channel_style(c.channel_type);
+                list_items.push(badged_item(
+                    format!("{char} {}", c.name),
+                    Style::default().fg(color),
+                    channel_badge(app, &c.id),
+                ));
+                item_ids.push(&c.id);
+            }
+        });
+
+    let num_filtered = list_items.len();
+
+    let hidden_channels: Vec<&Channel> = app
+        .channels
+        .iter()
+        .flat_map(|c| {
+            if c.channel_type == 4 {
+                let mut items: Vec<&Channel> = Vec::new();
+
+                if let Some(children) = &c.children {
+                    items.extend(children.iter().filter(|child| {
+                        permission_context
+                            .as_ref()
+                            .is_some_and(|context| !child.is_readable(context))
+                    }));
+                }
+                items
+            } else if permission_context
+                .as_ref()
+                .is_some_and(|context| !c.is_readable(context))
+            {
+                vec![c]
+            } else {
+                vec![]
+            }
+        })
+        .collect();
+    item_ids.extend(hidden_channels.iter().map(|c| c.id.as_str()));
+
+    let hidden_items = hidden_channels.into_iter().map(|c| {
+        let char = match c.channel_type {
+            15 => '',
+            13 => '󱝉',
+            5 => '',
+            4 => '',
+            2 => '',
+            0 => '',
+            _ => '',
+        };
+
+        let color = Color::DarkGray;
+
+        ListItem::new(format!(" {char} {}", c.name)).style(Style::default().fg(color))
+    });
+
+    list_items.extend(hidden_items);
+    let open_index = item_ids.iter().position(|id| Some(*id) == open_chat);
+
+    let selected = if focused {
+        app.selection_index = app.selection_index.min(list_items.len().saturating_sub(1));
+        Some(app.selection_index)
+    } else {
+        open_index
+    };
+
+    let title = if app.panes.active {
+        guild_name.to_string()
+    } else {
+        format!(
+            "Channels for Guild: {} | Channels found: {} | Actual index: {}",
+            guild_name,
+            num_filtered.saturating_sub(1),
+            app.selection_index
+        )
+    };
+    let list = pane_list(list_items, title, focused);
+
+    let mut state = ListState::default().with_selected(selected);
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
 
-                let formatted_date = message
+/// The messages of the open channel, oldest at the top. An unfocused chat pane has no
+/// selection and stays scrolled to the newest message.
+/// The open chat in `area`. Without the focus it shows where we left it.
+fn draw_chat(f: &mut ratatui::Frame, app: &mut App, area: Rect, channel_name: &str, focused: bool) {
+    let view = if focused {
+        ChatView {
+            selection_index: app.selection_index,
+            scroll: app.chat_scroll_offset,
+            ..ChatView::default()
+        }
+    } else {
+        current_chat(app)
+            .and_then(|id| app.buffers.buffers.get(id))
+            .and_then(|buffer| buffer.view.clone())
+            .unwrap_or_default()
+    };
+
+    let scroll = draw_messages(f, app, &app.messages, area, channel_name, &view, focused);
+    if focused {
+        app.chat_scroll_offset = scroll;
+        app.terminal_height = area.height as usize;
+        app.terminal_width = area.width as usize;
+    }
+}
+
+/// The open chat and, after `:split` or `:vsplit`, the other windows next to it.
+fn draw_chat_windows(
+    f: &mut ratatui::Frame,
+    app: &mut App,
+    area: Rect,
+    channel_name: &str,
+    focused: bool,
+) {
+    use ratatui::layout::{Constraint, Direction, Layout};
+
+    let channels = windows::visible(app);
+    if channels.len() < 2 {
+        draw_chat(f, app, area, channel_name, focused);
+        return;
+    }
+
+    let direction = if app.windows.vertical {
+        Direction::Horizontal
+    } else {
+        Direction::Vertical
+    };
+    let rects = Layout::default()
+        .direction(direction)
+        .constraints(vec![
+            Constraint::Ratio(1, channels.len() as u32);
+            channels.len()
+        ])
+        .split(area);
+    let current = current_chat(app).map(str::to_string);
+
+    for (channel_id, rect) in channels.iter().zip(rects.iter()) {
+        if current.as_ref() == Some(channel_id) {
+            draw_chat(f, app, *rect, channel_name, focused);
+            continue;
+        }
+        let app: &App = app;
+        let name = app
+            .buffers
+            .tabs
+            .iter()
+            .find(|(id, _)| id == channel_id)
+            .map_or("", |(_, name)| name.as_str());
+        let buffer = app.buffers.buffers.get(channel_id);
+        let messages = buffer.map_or(&[][..], |buffer| buffer.messages.as_slice());
+        let view = buffer
+            .and_then(|buffer| buffer.view.clone())
+            .unwrap_or_default();
+        draw_messages(f, app, messages, *rect, name, &view, false);
+    }
+}
+
+/// Draws the messages of any chat into any `area`, scrolled so the selected one shows.
+/// Returns the scroll it settled on.
+fn draw_messages(
+    f: &mut ratatui::Frame,
+    app: &App,
+    messages: &[Message],
+    area: Rect,
+    channel_name: &str,
+    view: &ChatView,
+    focused: bool,
+) -> usize {
+    use ratatui::text::Line;
+    use ratatui::widgets::{Paragraph, Wrap};
+
+    let max_height = (area.height as usize).saturating_sub(2);
+    let max_width = area.width.saturating_sub(2);
+    // 0 for the input line, as `selection_index`
+    let selection = view.selection_index;
+    let mut scroll = view.scroll;
+
+    if max_width == 0 {
+        return scroll;
+    }
+
+    let mut messages_reversed_with_index = messages.iter().enumerate().collect::<Vec<_>>();
+    messages_reversed_with_index.reverse(); // Oldest first
+
+    let mut final_content: Vec<Line> = Vec::new();
+    let mut total_visual_height = 0;
+    let search_pattern = app.search.as_ref().map(|s| s.pattern.clone());
+    let visual_range = if focused { visual_range(app) } else { None };
+
+    let mut previous = None;
+    for (original_idx, message) in messages_reversed_with_index.into_iter() {
+        if is_first_unread(app, message, previous) {
+            let label = " new messages ";
+            let rule = "─".repeat((max_width as usize).saturating_sub(label.len() + 4).max(4) / 2);
+            final_content.push(
+                Line::from(format!("{rule}{label}{rule}"))
+                    .style(Style::default().fg(Color::LightRed))
+                    .centered(),
+            );
+            total_visual_height += 1;
+        }
+        previous = Some(message);
+
+        let is_selected = selection > 0 && selection - 1 == original_idx;
+
+        let badge = message
+            .author_badge()
+            .map(|badge| format!(" {badge} "))
+            .unwrap_or_default();
+
+        let formatted_text = format!(
+            "[{}] {}{}: {}",
+            message
+                .timestamp
+                .split('T')
+                .next()
+                .unwrap_or("")
+                .to_string()
+                + " "
+                + message
                     .timestamp
                     .split('T')
-                    .next()
+                    .nth(1)
                     .unwrap_or("")
-                    .to_string();
-
-                let author = format!(
-                    " {}: ",
-                    message
-                        .author
-                        .global_name
-                        .clone()
-                        .unwrap_or(message.author.username.clone())
-                );
+                    .split('.')
+                    .next()
+                    .unwrap_or(""),
+            message
+                .author
+                .global_name
+                .clone()
+                .unwrap_or(message.author.username.clone()),
+            // The badge is drawn one space after the name
+            if badge.is_empty() {
+                String::new()
+            } else {
+                format!(" {badge}")
+            },
+            message.content.as_deref().unwrap_or("(*non-text*)")
+        );
 
-                let content = message.map_mentions();
+        let text_lines: Vec<&str> = formatted_text.split('\n').collect();
+        let mut estimated_height = 0;
 
-                let content_lines: Vec<&str> = content.split('\n').collect();
+        let safe_max_width = max_width.saturating_sub(4);
+        for line in text_lines {
+            let width = UnicodeWidthStr::width(line);
 
-                let mentionned = if let Some(author) = &app.current_user {
-                    message.mentions.contains(author)
-                } else {
-                    false
-                };
+            if width == 0 || safe_max_width == 0 {
+                estimated_height += 1;
+                continue;
+            }
 
-                let bg_color = if is_selected {
-                    Color::DarkGray
-                } else {
-                    Color::Reset
-                };
+            let mut current_line_width = 0;
+            let mut first_word = true;
 
-                let mut style = Style::default().fg(Color::White).bg(bg_color);
+            for word in line.split(' ') {
+                let word_width = UnicodeWidthStr::width(word);
+                let space_width = if first_word { 0 } else { 1 };
 
-                if mentionned {
-                    style = style.reversed();
-                }
+                if current_line_width + space_width + word_width <= safe_max_width as usize {
+                    current_line_width += space_width + word_width;
+                } else {
+                    if current_line_width > 0 {
+                        estimated_height += 1;
+                    }
 
-                for (i, line_content) in content_lines.iter().enumerate() {
-                    let mut spans = vec![];
-
-                    if i == 0 {
-                        spans.push(Span::styled(
-                            "[".to_string(),
-                            Style::default().fg(Color::LightBlue).bg(bg_color),
-                        ));
-                        spans.push(Span::styled(
-                            formatted_date.clone(),
-                            Style::default().fg(Color::LightCyan).bg(bg_color),
-                        ));
-                        spans.push(Span::styled(
-                            formatted_time.clone(),
-                            Style::default().fg(Color::LightBlue).bg(bg_color),
-                        ));
-                        spans.push(Span::styled(
-                            author.clone(),
-                            Style::default().fg(Color::Yellow).bg(bg_color),
-                        ));
+                    if word_width > safe_max_width as usize {
+                        let chunks = word_width.div_ceil(safe_max_width as usize);
+                        estimated_height += chunks.saturating_sub(1);
+                        current_line_width = word_width % safe_max_width as usize;
+                        if current_line_width == 0 {
+                            current_line_width = safe_max_width as usize;
+                        }
                     } else {
-                        // Keep multi-line messages highlighted properly across all lines
-                        spans.push(Span::styled("".to_string(), Style::default().bg(bg_color)));
+                        current_line_width = word_width;
                     }
+                }
+                first_word = false;
+            }
+            if current_line_width > 0 {
+                estimated_height += 1;
+            }
+        }
+
+        let start_y = total_visual_height;
+        total_visual_height += estimated_height;
+        let end_y = total_visual_height;
+
+        if is_selected {
+            if start_y < scroll {
+                scroll = start_y;
+            } else if end_y > scroll + max_height {
+                scroll = end_y.saturating_sub(max_height);
+            }
+        }
+
+        let formatted_time = format!(
+            " {}]",
+            message
+                .timestamp
+                .split('T')
+                .nth(1)
+                .unwrap_or("")
+                .split('.')
+                .next()
+                .unwrap_or(""),
+        );
+
+        let formatted_date = message
+            .timestamp
+            .split('T')
+            .next()
+            .unwrap_or("")
+            .to_string();
+
+        let author = format!(
+            " {}",
+            message
+                .author
+                .global_name
+                .clone()
+                .unwrap_or(message.author.username.clone())
+        );
+
+        let author_color = role_color(app, &message.author.id);
+
+        let content = message.map_mentions();
+
+        let content_lines: Vec<&str> = content.split('\n').collect();
+
+        let mentionned = if let Some(author) = &app.current_user {
+            message.mentions.contains(author)
+        } else {
+            false
+        };
+
+        let is_in_visual_range = visual_range
+            .as_ref()
+            .is_some_and(|r| r.contains(&original_idx));
+
+        let bg_color = if is_selected {
+            Color::DarkGray
+        } else if is_in_visual_range {
+            Color::Blue
+        } else {
+            Color::Reset
+        };
+
+        let mut style = Style::default().fg(Color::White).bg(bg_color);
 
-                    spans.push(Span::styled(line_content.to_string(), style));
-                    final_content.push(Line::from(spans));
+        if mentionned {
+            style = style.reversed();
+        }
+
+        for (i, line_content) in content_lines.iter().enumerate() {
+            let mut spans = vec![];
+
+            if i == 0 {
+                spans.push(Span::styled(
+                    "[".to_string(),
+                    Style::default().fg(Color::LightBlue).bg(bg_color),
+                ));
+                spans.push(Span::styled(
+                    formatted_date.clone(),
+                    Style::default().fg(Color::LightCyan).bg(bg_color),
+                ));
+                spans.push(Span::styled(
+                    formatted_time.clone(),
+                    Style::default().fg(Color::LightBlue).bg(bg_color),
+                ));
+                spans.push(Span::styled(
+                    author.clone(),
+                    Style::default()
+                        .fg(author_color.unwrap_or(Color::Yellow))
+                        .bg(bg_color),
+                ));
+                if !badge.is_empty() {
+                    spans.push(Span::styled(" ", Style::default().bg(bg_color)));
+                    spans.push(Span::styled(
+                        badge.clone(),
+                        Style::default().fg(Color::White).bg(Color::Blue),
+                    ));
                 }
+                spans.push(Span::styled(
+                    ": ",
+                    Style::default()
+                        .fg(author_color.unwrap_or(Color::Yellow))
+                        .bg(bg_color),
+                ));
+            } else {
+                // Keep multi-line messages highlighted properly across all lines
+                spans.push(Span::styled("".to_string(), Style::default().bg(bg_color)));
             }
 
-            if app.selection_index == 0 {
-                app.chat_scroll_offset = total_visual_height.saturating_sub(max_height);
+            spans.extend(highlight_line(
+                line_content,
+                &app.highlights,
+                search_pattern.as_deref(),
+                style,
+            ));
+            final_content.push(Line::from(spans));
+        }
+    }
+
+    if selection == 0 {
+        scroll = total_visual_height.saturating_sub(max_height);
+    }
+
+    let title = format!("vimcord Client - Chatting in channel - {}", channel_name);
+
+    let paragraph = Paragraph::new(final_content)
+        .block(pane_block(title, focused))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll as u16, 0));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+    scroll
+}
+
+/// The open channels, numbered for `:b`, with a dot on those with unread messages.
+fn draw_tab_line(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    use ratatui::text::Line;
+    use ratatui::widgets::Tabs;
+
+    let current = current_chat(app);
+    let titles: Vec<Line> = app
+        .buffers
+        .tabs
+        .iter()
+        .enumerate()
+        .map(|(i, (id, name))| {
+            let badge = channel_badge(app, id);
+            let mut spans = vec![Span::raw(format!("{} {name}", i + 1))];
+            if badge.mentions > 0 {
+                spans.push(Span::styled(" ●", Style::default().fg(Color::LightRed)));
+            } else if badge.unread {
+                spans.push(Span::raw(" ●"));
             }
+            Line::from(spans)
+        })
+        .collect();
+    let selected = app
+        .buffers
+        .tabs
+        .iter()
+        .position(|(id, _)| Some(id.as_str()) == current);
 
-            let title = format!("vimcord Client - Chatting in channel - {}", channel_name);
+    f.render_widget(
+        Tabs::new(titles)
+            .select(selected)
+            .style(Style::default().fg(Color::DarkGray))
+            .highlight_style(Style::default().fg(Color::White).bold()),
+        area,
+    );
+}
 
-            let paragraph = Paragraph::new(final_content)
-                .block(
-                    Block::default()
-                        .title(Span::styled(title, Style::default().fg(Color::Yellow)))
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Double),
-                )
-                .wrap(Wrap { trim: false })
-                .scroll((app.chat_scroll_offset as u16, 0));
+pub fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
+    use ratatui::layout::{Constraint, Direction, Layout};
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Borders, Paragraph};
 
-            f.render_widget(Clear, chunks[0]);
-            f.render_widget(paragraph, chunks[0]);
+    let area = f.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
+        .split(area);
+
+    // A line of tabs on top once a channel was opened
+    let body = if app.buffers.tabs.is_empty() {
+        chunks[0]
+    } else {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(chunks[0]);
+        draw_tab_line(f, app, rows[0]);
+        rows[1]
+    };
+
+    // Side by side panes when asked for and there is room, one screen at a time otherwise
+    let layout = &app.config.layout;
+    app.panes.active = layout.panes
+        && body.width >= layout.min_width
+        && !matches!(app.state, AppState::Home | AppState::Loading(Window::Home));
+    let (side_areas, content_area) = if app.panes.active {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(layout.guild_width),
+                Constraint::Length(layout.channel_width),
+                Constraint::Min(0),
+            ])
+            .split(body);
+        (Some((columns[0], columns[1])), columns[2])
+    } else {
+        (None, body)
+    };
+
+    let is_chatting = matches!(
+        app.state,
+        AppState::Chatting(_, _) | AppState::EmojiSelection(_, _) | AppState::Editing(_, _, _, _)
+    );
+    // The member list takes the right of the chat when there is room for both
+    let (main_area, member_area) =
+        if is_chatting && app.member_list.visible && content_area.width >= MIN_WIDTH_WITH_MEMBERS {
+            let split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(MEMBER_LIST_WIDTH)])
+                .split(content_area);
+            (split[0], Some(split[1]))
+        } else {
+            (content_area, None)
+        };
+
+    app.terminal_height = main_area.height as usize;
+    app.terminal_width = main_area.width as usize;
+
+    let guild_area = side_areas.map_or(content_area, |(guild_area, _)| guild_area);
+    let tree_area = side_areas.map_or(content_area, |(_, tree_area)| tree_area);
+
+    match &app.state {
+        AppState::Loading(_) => draw_loading(f, app, content_area),
+        AppState::Home => draw_home(f, app, content_area),
+        AppState::SelectingDM => draw_dm_list(f, app, tree_area, true),
+        AppState::SelectingGuild => draw_guild_list(f, app, guild_area, true),
+        AppState::SelectingChannel(_, guild_name) => {
+            let guild_name = guild_name.clone();
+            draw_channel_list(f, app, tree_area, &guild_name, true);
+        }
+        AppState::Chatting(_, channel_name)
+        | AppState::EmojiSelection(_, channel_name)
+        | AppState::Editing(_, channel_name, _, _) => {
+            let channel_name = channel_name.clone();
+            draw_chat_windows(f, app, main_area, &channel_name, true);
+
+            if let Some(member_area) = member_area {
+                draw_member_list(f, app, member_area);
+            }
         }
     };
 
+    // The panes without the focus keep showing where we are
+    if let Some((guild_area, tree_area)) = side_areas {
+        if !matches!(app.state, AppState::SelectingGuild) {
+            draw_guild_list(f, app, guild_area, false);
+        }
+        if !matches!(
+            app.state,
+            AppState::SelectingChannel(_, _) | AppState::SelectingDM
+        ) {
+            match app.panes.guild_id.clone() {
+                Some(guild_id) => {
+                    let guild_name = app
+                        .guilds
+                        .iter()
+                        .find(|g| g.id == guild_id)
+                        .map(|g| g.name.clone())
+                        .unwrap_or_default();
+                    draw_channel_list(f, app, tree_area, &guild_name, false);
+                }
+                None => draw_dm_list(f, app, tree_area, false),
+            }
+        }
+        if !is_chatting && !matches!(app.state, AppState::Loading(_)) {
+            match app.panes.chat.clone() {
+                Some((_, channel_name)) => {
+                    draw_chat_windows(f, app, content_area, &channel_name, false)
+                }
+                None => {
+                    f.render_widget(Clear, content_area);
+                    f.render_widget(
+                        Paragraph::new(Line::from(Span::styled(
+                            "No channel open yet",
+                            Style::default().fg(Color::DarkGray),
+                        )))
+                        .block(pane_block("Chat".to_string(), false)),
+                        content_area,
+                    );
+                }
+            }
+        }
+    }
+
     if let AppState::EmojiSelection(_, _) = &app.state {
         let input_area = chunks[1];
         let emoji_popup_height = 8;
@@ -616,19 +1667,45 @@ pub fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
     }
 
     let is_editing = matches!(&app.state, AppState::Editing(_, _, _, _));
+    let is_read_only = matches!(&app.state, AppState::Chatting(_, _))
+        && lacks_permission(app, Permissions::SEND_MESSAGES);
     let border_color = if is_editing {
         Color::LightMagenta
+    } else if is_read_only {
+        Color::DarkGray
     } else {
         Color::Reset
     };
     let title_color = if is_editing {
         Color::LightMagenta
+    } else if is_read_only {
+        Color::DarkGray
     } else {
         Color::Yellow
     };
 
     let mut display_status_message = app.status_message.clone();
 
+    // Only advertise the message actions we are actually allowed to take
+    if app.vim_mode
+        && matches!(&app.state, AppState::Chatting(_, _))
+        && let Some(msg) = app
+            .selection_index
+            .checked_sub(1)
+            .and_then(|i| app.messages.get(i))
+    {
+        let mut actions = Vec::new();
+        if can_edit_message(app, msg) {
+            actions.push("i: edit");
+        }
+        if can_delete_message(app, msg) {
+            actions.push("dd: delete");
+        }
+        if !actions.is_empty() && !app.offline {
+            display_status_message = format!("{display_status_message} | {}", actions.join(", "));
+        }
+    }
+
     let active_channel_id = match &app.state {
         AppState::Chatting(id, _) => Some(id),
         AppState::EmojiSelection(id, _) => Some(id),
@@ -661,11 +1738,46 @@ pub fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
             }
         };
 
-        display_status_message = format!("{} | {}", app.status_message, text);
+        display_status_message = format!("{display_status_message} | {text}");
+    }
+
+    if let Some(view) = &app.audit_log {
+        draw_audit_log(f, app, view, chunks[0]);
+    }
+
+    if let Some(view) = &app.role_view {
+        draw_role_view(f, app, view, chunks[0]);
+    }
+
+    if let Some(view) = &app.friends_view {
+        draw_friends(f, app, view, chunks[0]);
+    }
+
+    if let Some(view) = &app.inbox_view {
+        draw_inbox(f, app, view, chunks[0]);
+    }
+
+    if let Some(popup) = &app.popup {
+        draw_popup(f, popup, chunks[0]);
+    }
+
+    if let Some(form) = &app.form {
+        draw_form(f, form, chunks[0]);
     }
 
+    let input_text = match &app.prompt {
+        Some(prompt) => prompt.display(),
+        None => app.input.clone(),
+    };
+
+    let input_style = if is_read_only {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default()
+    };
+
     f.render_widget(
-        Paragraph::new(app.input.as_str()).block(
+        Paragraph::new(input_text).style(input_style).block(
             Block::default()
                 .title(Span::styled(
                     format!("Input: {}", display_status_message),
@@ -678,7 +1790,10 @@ pub fn draw_ui(f: &mut ratatui::Frame, app: &mut App) {
         chunks[1],
     );
 
-    if app.selection_index == 0 {
+    if let Some(prompt) = &app.prompt {
+        let cursor_x = chunks[1].x + 1 + UnicodeWidthStr::width(prompt.display().as_str()) as u16;
+        f.set_cursor_position((cursor_x, chunks[1].y + 1));
+    } else if app.selection_index == 0 {
         let cursor = if app.cursor_position <= app.input.len() && app.cursor_position > 0 {
             app.cursor_position
         } else {
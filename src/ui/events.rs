@@ -7,12 +7,266 @@ use tokio::{
 };
 
 use crate::{
-    App, AppAction, AppState, InputMode, KeywordAction, Window,
-    api::{Channel, DM, Emoji, Guild, Message},
+    App, AppAction, AppState, Error, InputMode, KeywordAction, Window,
+    api::{Channel, DM, Emoji, Message, guild::GuildMember, permissions::Permissions},
     logs::{LogType, print_log},
-    ui::vim,
+    ui::{
+        audit_log, buffers, channel_admin, form, friends, group_dm,
+        guild_list::{self, GuildRow},
+        inbox, layout, members, moderation, notifications, popup, profile, prompt, roles, search,
+        unread, vim, windows,
+    },
 };
 
+pub const OFFLINE_READ_ONLY: &str = "Offline mode: the cache is read-only.";
+pub const READ_ONLY_CHANNEL: &str =
+    "Read-only channel: you don't have permission to send messages here.";
+
+/// Shows the cached channels and permissions of a guild. Returns whether any were cached.
+pub fn load_cached_guild(state: &mut App, guild_id: &str) -> bool {
+    let cached_channels = state
+        .store
+        .as_ref()
+        .map(|s| s.load_channels(guild_id))
+        .unwrap_or_default();
+    let has_cache = !cached_channels.is_empty();
+
+    if has_cache || state.offline {
+        state.channels =
+            Channel::filter_channels_by_categories(cached_channels).unwrap_or_default();
+        state.context = state
+            .store
+            .as_ref()
+            .and_then(|s| s.load_value(&format!("context:{guild_id}")));
+    }
+    has_cache
+}
+
+/// Fetches the channels, custom emojis and permission context of a guild in the background,
+/// they come back as `ApiUpdateChannel`, `ApiUpdateEmojis` and `ApiUpdateContext`.
+/// `show_loading` puts up the loading screen until they are in, when nothing was cached.
+pub fn fetch_guild(
+    state: &App,
+    tx_action: &Sender<AppAction>,
+    guild_id: String,
+    show_loading: bool,
+) {
+    let api_client_clone = state.api_client.clone();
+    let tx_clone = tx_action.clone();
+
+    tokio::spawn(async move {
+        if show_loading {
+            tx_clone
+                .send(AppAction::TransitionToLoading(Window::Channel(
+                    guild_id.clone(),
+                )))
+                .await
+                .ok();
+        }
+        match api_client_clone.get_guild_channels(&guild_id).await {
+            Ok(channels) => {
+                tx_clone
+                    .send(AppAction::ApiUpdateChannel(channels))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load channels: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+        match api_client_clone.get_guild_emojis(&guild_id).await {
+            Ok(emojis) => {
+                tx_clone.send(AppAction::ApiUpdateEmojis(emojis)).await.ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load custom emojis: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+        match api_client_clone.get_permission_context(&guild_id).await {
+            Ok(context) => {
+                tx_clone
+                    .send(AppAction::ApiUpdateContext(Some(context)))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load permission context: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+
+        if show_loading {
+            tx_clone.send(AppAction::EndLoading).await.ok();
+        }
+    });
+}
+
+/// Merges a freshly fetched page into the loaded messages, both newest first.
+/// The page replaces what it covers, so messages deleted meanwhile go away, while older
+/// history from the cache or scrolling and newer messages from the gateway are kept.
+fn merge_messages(existing: Vec<Message>, page: Vec<Message>) -> Vec<Message> {
+    let (Some(newest), Some(oldest)) = (
+        page.iter().map(|m| unread::id_value(&m.id)).max(),
+        page.iter().map(|m| unread::id_value(&m.id)).min(),
+    ) else {
+        return existing;
+    };
+
+    let mut merged: Vec<Message> = existing
+        .into_iter()
+        .filter(|m| {
+            let id = unread::id_value(&m.id);
+            id < oldest || id > newest
+        })
+        .chain(page)
+        .collect();
+    merged.sort_by_key(|m| std::cmp::Reverse(unread::id_value(&m.id)));
+    merged
+}
+
+/// Fetches the page of history preceding the oldest loaded message and appends it.
+/// Returns how many messages were added.
+pub async fn load_older_messages(
+    state: &mut MutexGuard<'_, App>,
+    channel_id: &str,
+) -> Result<usize, Error> {
+    let Some(oldest) = state.messages.last() else {
+        return Ok(0);
+    };
+    let oldest_id = oldest.id.clone();
+
+    let older_msgs = if state.offline {
+        state
+            .store
+            .as_ref()
+            .map(|s| s.load_messages(channel_id, Some(&oldest_id), 100))
+            .unwrap_or_default()
+    } else {
+        let older_msgs = state
+            .api_client
+            .get_channel_messages(channel_id, None, Some(oldest_id), None, Some(100))
+            .await?;
+
+        if let Some(store) = state.store.as_ref() {
+            store.save_messages(&older_msgs);
+        }
+        older_msgs
+    };
+
+    let count = older_msgs.len();
+    state.messages.extend(older_msgs);
+    Ok(count)
+}
+
+/// How many pages of history a walk back to a message goes through before giving up.
+const MAX_HISTORY_PAGES: usize = 20;
+
+/// Why history is walked back, and what to select once it got there.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryGoal {
+    /// The older message matching the search, by id
+    SearchMatch(String),
+    /// The first message after the read marker
+    FirstUnread,
+    /// The message opened from the inbox, by id
+    InboxMessage(String),
+}
+
+/// Loads older history of the open chat until a message at or before `target` is loaded,
+/// then completes `goal`. Pages are fetched in a task and come back as `ApiHistoryPage`,
+/// the UI keeps running meanwhile.
+pub async fn load_history_until(
+    state: &mut MutexGuard<'_, App>,
+    tx_action: &Sender<AppAction>,
+    channel_id: &str,
+    target: &str,
+    goal: HistoryGoal,
+) {
+    let target = unread::id_value(target);
+    let reached = move |messages: &[Message]| {
+        messages
+            .last()
+            .is_none_or(|m| unread::id_value(&m.id) <= target)
+    };
+
+    if state.offline {
+        // The cache is local, no need for a task
+        for _ in 0..MAX_HISTORY_PAGES {
+            if reached(&state.messages)
+                || !matches!(load_older_messages(state, channel_id).await, Ok(1..))
+            {
+                break;
+            }
+        }
+        history_loaded(state, channel_id, goal).await;
+        return;
+    }
+    let before = state.messages.last().map(|m| m.id.clone());
+    let Some(mut before) = before.filter(|_| !reached(&state.messages)) else {
+        history_loaded(state, channel_id, goal).await;
+        return;
+    };
+
+    state.is_loading = true;
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    let channel_id = channel_id.to_string();
+    tokio::spawn(async move {
+        for _ in 0..MAX_HISTORY_PAGES {
+            let page = match api_client
+                .get_channel_messages(&channel_id, None, Some(before.clone()), None, Some(100))
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => {
+                    let _ = print_log(
+                        format!("Failed to load older history: {e}").into(),
+                        LogType::Error,
+                    );
+                    break;
+                }
+            };
+            let Some(oldest) = page.last().map(|m| m.id.clone()) else {
+                break;
+            };
+            let done = reached(&page);
+            tx_action
+                .send(AppAction::ApiHistoryPage(channel_id.clone(), page))
+                .await
+                .ok();
+            if done {
+                break;
+            }
+            before = oldest;
+        }
+        tx_action
+            .send(AppAction::HistoryLoaded(channel_id, goal))
+            .await
+            .ok();
+    });
+}
+
+/// The walk back through history ended, reaching its target or not.
+async fn history_loaded(state: &mut MutexGuard<'_, App>, channel_id: &str, goal: HistoryGoal) {
+    state.is_loading = false;
+    if !matches!(&state.state, AppState::Chatting(id, _) if id == channel_id) {
+        return;
+    }
+    match goal {
+        HistoryGoal::SearchMatch(message_id) => search::history_loaded(state, &message_id),
+        HistoryGoal::FirstUnread => unread::history_loaded(state),
+        HistoryGoal::InboxMessage(message_id) => inbox::history_loaded(state, &message_id),
+    }
+}
+
 /// Helper function to insert a character at the cursor position.
 /// Handles both emoji selection state and normal input state.
 fn insert_char_at_cursor(state: &mut MutexGuard<'_, App>, tx_action: Sender<AppAction>, c: char) {
@@ -77,41 +331,41 @@ pub async fn handle_input_events(
             _ = time::sleep(Duration::from_millis(10)) => {
                 if event::poll(Duration::from_millis(0))? {
                     match event::read()? {
-                        event::Event::Key(key) => {
-                            if key.kind == KeyEventKind::Press {
-                                if key.code == KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
-                                    tx.send(AppAction::SigInt).await.ok();
-                                } else {
-                                    match key.code {
-                                        KeyCode::Esc => {
-                                            tx.send(AppAction::InputEscape).await.ok();
-                                        }
-                                        KeyCode::Enter => {
-                                            tx.send(AppAction::InputSubmit).await.ok();
-                                        }
-                                        KeyCode::Backspace => {
-                                            tx.send(AppAction::InputBackspace).await.ok();
-                                        }
-                                        KeyCode::Delete => {
-                                            tx.send(AppAction::InputDelete).await.ok();
-                                        }
-                                        KeyCode::Up => {
-                                            tx.send(AppAction::SelectPrevious).await.ok();
-                                        }
-                                        KeyCode::Down => {
-                                            tx.send(AppAction::SelectNext).await.ok();
-                                        }
-                                        KeyCode::Left => {
-                                            tx.send(AppAction::SelectLeft).await.ok();
-                                        }
-                                        KeyCode::Right => {
-                                            tx.send(AppAction::SelectRight).await.ok();
-                                        }
-                                        KeyCode::Char(c) => {
-                                            tx.send(AppAction::InputChar(c)).await.ok();
-                                        }
-                                        _ => {}
+                        event::Event::Key(key) if key.kind == KeyEventKind::Press => {
+                            if key.code == KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
+                                tx.send(AppAction::SigInt).await.ok();
+                            } else if key.code == KeyCode::Char('w') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
+                                tx.send(AppAction::WindowCommand).await.ok();
+                            } else {
+                                match key.code {
+                                    KeyCode::Esc => {
+                                        tx.send(AppAction::InputEscape).await.ok();
+                                    }
+                                    KeyCode::Enter => {
+                                        tx.send(AppAction::InputSubmit).await.ok();
+                                    }
+                                    KeyCode::Backspace => {
+                                        tx.send(AppAction::InputBackspace).await.ok();
                                     }
+                                    KeyCode::Delete => {
+                                        tx.send(AppAction::InputDelete).await.ok();
+                                    }
+                                    KeyCode::Up => {
+                                        tx.send(AppAction::SelectPrevious).await.ok();
+                                    }
+                                    KeyCode::Down => {
+                                        tx.send(AppAction::SelectNext).await.ok();
+                                    }
+                                    KeyCode::Left => {
+                                        tx.send(AppAction::SelectLeft).await.ok();
+                                    }
+                                    KeyCode::Right => {
+                                        tx.send(AppAction::SelectRight).await.ok();
+                                    }
+                                    KeyCode::Char(c) => {
+                                        tx.send(AppAction::InputChar(c)).await.ok();
+                                    }
+                                    _ => {}
                                 }
                             }
                         }
@@ -126,6 +380,242 @@ pub async fn handle_input_events(
     }
 }
 
+/// Shows the cached history of a channel right away, then refreshes it from the API.
+pub async fn open_chat(
+    state: &mut MutexGuard<'_, App>,
+    tx_action: &Sender<AppAction>,
+    channel_id: String,
+) {
+    let live_messages = buffers::switch_to(state, &channel_id);
+    state.input = String::new();
+    state.cursor_position = 0;
+    // Loading the messages acks them, remember where we stopped reading first
+    state.unread_marker = unread::unread_marker(state, &channel_id);
+
+    // An open tab is kept up to date by the gateway, no need to fetch it again
+    if let Some(messages) = live_messages {
+        state.messages = messages;
+        buffers::reopened(state, &channel_id);
+        inbox::messages_loaded(state, &channel_id, tx_action).await;
+        tx_action
+            .send(AppAction::TransitionToChat(channel_id))
+            .await
+            .ok();
+        return;
+    }
+
+    let cached_messages = state
+        .store
+        .as_ref()
+        .map(|s| s.load_messages(&channel_id, None, 100))
+        .unwrap_or_default();
+    let has_cache = !cached_messages.is_empty();
+
+    if has_cache || state.offline {
+        state.messages = cached_messages
+            .into_iter()
+            .filter(|m| !state.deleted_message_ids.contains(&m.id))
+            .collect();
+        tx_action
+            .send(AppAction::TransitionToChat(channel_id.clone()))
+            .await
+            .ok();
+
+        if state.offline {
+            return;
+        }
+    } else {
+        tx_action
+            .send(AppAction::TransitionToLoading(Window::Chat(
+                channel_id.clone(),
+            )))
+            .await
+            .ok();
+    }
+
+    let tx_action_clone = tx_action.clone();
+    let api_client_clone = state.api_client.clone();
+
+    tokio::spawn(async move {
+        match api_client_clone
+            .get_channel_messages(&channel_id, None, None, None, Some(100))
+            .await
+        {
+            Ok(messages) => {
+                if let Err(e) = tx_action_clone
+                    .send(AppAction::ApiUpdateMessages(channel_id.clone(), messages))
+                    .await
+                {
+                    let _ = print_log(
+                        format!("Failed to send message update action: {e}").into(),
+                        LogType::Error,
+                    );
+                }
+            }
+            Err(e) => {
+                let _ = print_log(format!("Error loading chat: {e}").into(), LogType::Error);
+            }
+        }
+
+        if !has_cache {
+            tx_action_clone.send(AppAction::EndLoading).await.ok();
+        }
+    });
+}
+
+/// Looks a guild channel up in the loaded channel tree, then in the on-disk cache.
+pub fn cached_channel(state: &App, channel_id: &str) -> Option<Channel> {
+    state
+        .channels
+        .iter()
+        .flat_map(|c| std::iter::once(c).chain(c.children.iter().flatten()))
+        .find(|c| c.id == channel_id)
+        .cloned()
+        .or_else(|| {
+            state
+                .store
+                .as_ref()
+                .and_then(|s| s.load_channel(channel_id))
+        })
+}
+
+/// Our permissions in a guild channel, `None` for DMs or while the guild context is unknown.
+fn compute_channel_permissions(state: &App, channel_id: &str) -> Option<Permissions> {
+    let context = state.context.as_ref()?;
+    let channel = cached_channel(state, channel_id)?;
+
+    (channel.guild_id.as_ref() == Some(&context.everyone_role_id))
+        .then(|| channel.permissions(context))
+}
+
+fn refresh_channel_permissions(state: &mut App) {
+    state.channel_permissions = match &state.state {
+        AppState::Chatting(channel_id, _)
+        | AppState::EmojiSelection(channel_id, _)
+        | AppState::Editing(channel_id, _, _, _) => compute_channel_permissions(state, channel_id),
+        _ => None,
+    };
+}
+
+/// The guild being browsed or chatted in, `None` in DMs.
+pub fn current_guild_id(state: &App) -> Option<String> {
+    match &state.state {
+        AppState::SelectingChannel(guild_id, _) => Some(guild_id.clone()),
+        AppState::Chatting(_, _) if state.channel_permissions.is_some() => state
+            .context
+            .as_ref()
+            .map(|context| context.everyone_role_id.clone()),
+        _ => None,
+    }
+}
+
+/// Whether the open channel denies `permission`. Unknown permissions are not enforced
+/// locally, the API has the final word.
+pub fn lacks_permission(state: &App, permission: Permissions) -> bool {
+    state
+        .channel_permissions
+        .is_some_and(|permissions| !permissions.contains(permission))
+}
+
+pub fn is_own_message(state: &App, message: &Message) -> bool {
+    state
+        .current_user
+        .as_ref()
+        .is_some_and(|user| user.id == message.author.id)
+}
+
+/// Discord only lets authors edit their messages, whatever their permissions.
+pub fn can_edit_message(state: &App, message: &Message) -> bool {
+    is_own_message(state, message)
+}
+
+pub fn can_delete_message(state: &App, message: &Message) -> bool {
+    is_own_message(state, message)
+        || state
+            .channel_permissions
+            .is_some_and(|permissions| permissions.contains(Permissions::MANAGE_MESSAGES))
+}
+
+/// The channel list as drawn: readable channels matching the filter under their categories,
+/// followed by the channels we cannot see.
+pub fn channel_list(state: &App) -> Vec<&Channel> {
+    let filter_text = state.input.to_lowercase();
+    let is_readable = |c: &Channel| {
+        state
+            .context
+            .as_ref()
+            .is_some_and(|context| c.is_readable(context))
+    };
+    let should_display_content = |c: &Channel| {
+        is_readable(c) && (filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text))
+    };
+
+    let mut list: Vec<&Channel> = Vec::new();
+
+    for c in &state.channels {
+        if c.channel_type == 4 {
+            let name_matches =
+                filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text);
+            let child_matches = c
+                .children
+                .as_ref()
+                .is_some_and(|children| children.iter().any(should_display_content));
+
+            if name_matches || child_matches {
+                list.push(c);
+                list.extend(
+                    c.children
+                        .iter()
+                        .flatten()
+                        .filter(|child| should_display_content(child)),
+                );
+            }
+        } else if should_display_content(c) {
+            list.push(c);
+        }
+    }
+
+    if state.context.is_some() {
+        for c in &state.channels {
+            if c.channel_type == 4 {
+                list.extend(
+                    c.children
+                        .iter()
+                        .flatten()
+                        .filter(|child| !is_readable(child)),
+                );
+            } else if !is_readable(c) {
+                list.push(c);
+            }
+        }
+    }
+
+    list
+}
+
+/// Resolves a channel or DM name from what is already loaded or cached, without hitting the API.
+fn cached_channel_name(state: &App, channel_id: &str) -> Option<String> {
+    if let Some(dm) = state.dms.iter().find(|dm| dm.id == channel_id) {
+        return Some(dm.get_name());
+    }
+
+    cached_channel(state, channel_id).map(|c| c.name)
+}
+
+async fn channel_name(state: &App, channel_id: &str) -> String {
+    if let Some(name) = cached_channel_name(state, channel_id) {
+        return name;
+    }
+
+    match state.api_client.get_channel(channel_id).await {
+        Ok(c) => c.name,
+        Err(e) => {
+            print_log(e, LogType::Error).ok();
+            "<Empty Name>".to_string()
+        }
+    }
+}
+
 async fn input_submit(
     state: &mut MutexGuard<'_, App>,
     tx_action: &Sender<AppAction>,
@@ -143,6 +633,12 @@ async fn input_submit(
                 tx_action.send(AppAction::TransitionToDM).await.ok();
             }
             2 => {
+                friends::open_friends(state, tx_action);
+            }
+            3 => {
+                inbox::open_inbox(state, tx_action);
+            }
+            4 => {
                 return Some(KeywordAction::Break);
             }
             _ => {}
@@ -167,222 +663,61 @@ async fn input_submit(
                 selected_dm.recipients[0].username.clone()
             };
 
-            state.input = String::new();
-            state.cursor_position = 0;
             state.status_message = format!("Loading messages for {selected_dm_name}...");
 
-            let tx_action_clone = tx_action.clone();
-            let api_client_clone = state.api_client.clone();
-            let channel_id_load = dm_id_clone.clone();
-
-            tokio::spawn(async move {
-                tx_action_clone
-                    .send(AppAction::TransitionToLoading(Window::Chat(
-                        channel_id_load.clone(),
-                    )))
-                    .await
-                    .ok();
-
-                match api_client_clone
-                    .get_channel_messages(&channel_id_load, None, None, None, Some(100))
-                    .await
-                {
-                    Ok(messages) => {
-                        if let Err(e) = tx_action_clone
-                            .send(AppAction::ApiUpdateMessages(
-                                channel_id_load.clone(),
-                                messages,
-                            ))
-                            .await
-                        {
-                            let _ = print_log(
-                                format!("Failed to send message update action: {e}").into(),
-                                LogType::Error,
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        let _ =
-                            print_log(format!("Error loading DM chat: {e}").into(), LogType::Error);
-                    }
-                }
-
-                tx_action_clone.send(AppAction::EndLoading).await.ok();
-            });
+            open_chat(state, tx_action, dm_id_clone).await;
         }
         AppState::SelectingGuild => {
-            let filter_text = state.input.to_lowercase();
-            let guilds: Vec<&Guild> = state
-                .guilds
-                .iter()
-                .filter(|g| g.name.to_lowercase().contains(&filter_text))
-                .collect();
-
-            if guilds.is_empty() {
-                return Some(KeywordAction::Continue);
-            }
-
-            let selected_guild = &guilds[state.selection_index];
-            let guild_id_clone = selected_guild.id.clone();
-            let selected_guild_name = selected_guild.name.clone();
-
-            let tx_clone = tx_action.clone();
+            let (guild_id_clone, selected_guild_name) =
+                match guild_list::guild_rows(state).get(state.selection_index) {
+                    Some(GuildRow::Guild { id, name, .. }) => (id.clone(), name.clone()),
+                    Some(GuildRow::Folder { key, .. }) => {
+                        guild_list::toggle_folder(state, key);
+                        return None;
+                    }
+                    None => return Some(KeywordAction::Continue),
+                };
 
             state.status_message = format!("Loading channels for {selected_guild_name}...");
 
-            let api_client_clone = state.api_client.clone();
-
-            tokio::spawn(async move {
-                tx_clone
-                    .send(AppAction::TransitionToLoading(Window::Channel(
-                        guild_id_clone.clone(),
-                    )))
+            let has_cache = load_cached_guild(state, &guild_id_clone);
+            if has_cache || state.offline {
+                tx_action
+                    .send(AppAction::TransitionToChannels(guild_id_clone.clone()))
                     .await
                     .ok();
-                match api_client_clone.get_guild_channels(&guild_id_clone).await {
-                    Ok(channels) => {
-                        tx_clone
-                            .send(AppAction::ApiUpdateChannel(channels))
-                            .await
-                            .ok();
-                    }
-                    Err(e) => {
-                        let _ = print_log(
-                            format!("Failed to load channels: {e}").into(),
-                            LogType::Error,
-                        );
-                    }
-                }
-                match api_client_clone.get_guild_emojis(&guild_id_clone).await {
-                    Ok(emojis) => {
-                        tx_clone.send(AppAction::ApiUpdateEmojis(emojis)).await.ok();
-                    }
-                    Err(e) => {
-                        let _ = print_log(
-                            format!("Failed to load custom emojis: {e}").into(),
-                            LogType::Error,
-                        );
-                    }
-                }
-                match api_client_clone
-                    .get_permission_context(&guild_id_clone)
-                    .await
-                {
-                    Ok(context) => {
-                        tx_clone
-                            .send(AppAction::ApiUpdateContext(Some(context)))
-                            .await
-                            .ok();
-                    }
-                    Err(e) => {
-                        let _ = print_log(
-                            format!("Failed to load permission context: {e}").into(),
-                            LogType::Error,
-                        );
-                    }
+
+                if state.offline {
+                    return None;
                 }
+            }
 
-                tx_clone.send(AppAction::EndLoading).await.ok();
-            });
+            fetch_guild(state, tx_action, guild_id_clone, !has_cache);
         }
         AppState::SelectingChannel(_, _) => {
-            let permission_context = &state.context;
-            let mut text_channels: Vec<&Channel> = Vec::new();
-
-            let filter_text = state.input.to_lowercase();
-            let should_display_channel_content = |c: &Channel| {
-                let is_readable = permission_context
-                    .as_ref()
-                    .is_some_and(|context| c.is_readable(context));
-
-                is_readable
-                    && (filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text))
+            // The same rows `move_selection` walks and the channel list draws
+            let Some(selected) = channel_list(state).get(state.selection_index).copied() else {
+                return Some(KeywordAction::Continue);
             };
-
-            state
-                .channels
-                .iter()
-                .filter(|c| {
-                    if c.children.is_none() && c.channel_type != 4 {
-                        return should_display_channel_content(c);
-                    }
-
-                    if c.channel_type == 4 {
-                        if filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text) {
-                            return true;
-                        }
-
-                        if let Some(children) = &c.children {
-                            return children.iter().any(should_display_channel_content);
-                        }
-                    }
-
-                    false
-                })
-                .for_each(|c| {
-                    if let Some(children) = &c.children {
-                        text_channels.push(c);
-
-                        children
-                            .iter()
-                            .filter(|c| should_display_channel_content(c))
-                            .for_each(|c| text_channels.push(c));
-                    } else {
-                        text_channels.push(c);
-                    }
-                });
-
-            if text_channels.is_empty()
-                || text_channels.len() <= state.selection_index
-                || text_channels[state.selection_index].channel_type == 4
-            {
+            if selected.channel_type == 4 {
                 return Some(KeywordAction::Continue);
             }
+            let readable = state
+                .context
+                .as_ref()
+                .is_some_and(|context| selected.is_readable(context));
+            if !readable {
+                state.status_message =
+                    format!("You can't see #{}, press K to see why.", selected.name);
+                return None;
+            }
 
-            let channel_info = {
-                let selected_channel = &text_channels[state.selection_index];
-                (selected_channel.id.clone(), selected_channel.name.clone())
-            };
-            let (channel_id_clone, selected_channel_name) = channel_info;
-
-            tx_action
-                .send(AppAction::TransitionToLoading(Window::Chat(
-                    channel_id_clone.clone(),
-                )))
-                .await
-                .ok();
+            let (channel_id_clone, selected_channel_name) =
+                (selected.id.clone(), selected.name.clone());
 
-            state.input = String::new();
-            state.cursor_position = 0;
             state.status_message = format!("Loading messages for {selected_channel_name}...");
 
-            match state
-                .api_client
-                .get_channel_messages(&channel_id_clone, None, None, None, Some(100))
-                .await
-            {
-                Ok(messages) => {
-                    if let Err(e) = tx_action
-                        .send(AppAction::ApiUpdateMessages(
-                            channel_id_clone.clone(),
-                            messages,
-                        ))
-                        .await
-                    {
-                        print_log(
-                            format!("Failed to send message update action: {e}").into(),
-                            LogType::Error,
-                        )
-                        .ok();
-                        return None;
-                    }
-                }
-                Err(e) => {
-                    state.status_message = format!("Error loading chat: {e}");
-                }
-            }
-
-            tx_action.send(AppAction::EndLoading).await.ok();
+            open_chat(state, tx_action, channel_id_clone).await;
         }
         AppState::EmojiSelection(channel_id, _) => {
             let start_pos = state.emoji_filter_start?;
@@ -429,13 +764,7 @@ async fn input_submit(
                     state.cursor_position = pos;
                 }
             }
-            let channel_name = match state.api_client.get_channel(channel_id.as_str()).await {
-                Ok(c) => c.name,
-                Err(e) => {
-                    print_log(e, LogType::Error).ok();
-                    "<Empty Name>".to_string()
-                }
-            };
+            let channel_name = channel_name(state, &channel_id).await;
 
             state.state = AppState::Chatting(channel_id.clone(), channel_name);
             state.emoji_filter.clear();
@@ -445,6 +774,9 @@ async fn input_submit(
                 "Chatting in channel. Press Enter to send message, Esc to return to channels."
                     .to_string();
         }
+        AppState::Editing(_, _, _, _) | AppState::Chatting(_, _) if state.offline => {
+            state.status_message = OFFLINE_READ_ONLY.to_string();
+        }
         AppState::Editing(channel_id, _, message, _) => {
             let (channel_id_clone, message_id_clone) = (channel_id.clone(), message.id.clone());
             let content = state.input.drain(..).collect::<String>();
@@ -493,6 +825,9 @@ async fn input_submit(
                 });
             }
         }
+        AppState::Chatting(_, _) if lacks_permission(state, Permissions::SEND_MESSAGES) => {
+            state.status_message = READ_ONLY_CHANNEL.to_string();
+        }
         AppState::Chatting(channel_id, _) => {
             let channel_id_clone = channel_id.clone();
 
@@ -530,113 +865,68 @@ async fn move_selection(state: &mut MutexGuard<'_, App>, n: i32, total_filtered_
         AppState::Home => {
             if n < 0 {
                 state.selection_index = if state.selection_index == 0 {
-                    3 - n.unsigned_abs() as usize
+                    5 - n.unsigned_abs() as usize
                 } else {
                     state.selection_index - n.unsigned_abs() as usize
                 };
             } else {
-                state.selection_index = (state.selection_index + n.unsigned_abs() as usize) % 3;
+                state.selection_index = (state.selection_index + n.unsigned_abs() as usize) % 5;
             }
         }
-        AppState::SelectingDM => {
-            if !state.dms.is_empty() {
-                if n < 0 {
-                    state.selection_index = if state.selection_index == 0 {
-                        state.dms.len() - n.unsigned_abs() as usize
-                    } else {
-                        state.selection_index - n.unsigned_abs() as usize
-                    };
+        AppState::SelectingDM if !state.dms.is_empty() => {
+            if n < 0 {
+                state.selection_index = if state.selection_index == 0 {
+                    state.dms.len() - n.unsigned_abs() as usize
                 } else {
-                    state.selection_index =
-                        (state.selection_index + n.unsigned_abs() as usize) % state.dms.len();
-                }
+                    state.selection_index - n.unsigned_abs() as usize
+                };
+            } else {
+                state.selection_index =
+                    (state.selection_index + n.unsigned_abs() as usize) % state.dms.len();
             }
         }
-        AppState::SelectingGuild => {
-            if !state.guilds.is_empty() {
-                if n < 0 {
-                    state.selection_index = if state.selection_index == 0 {
-                        state.guilds.len() - n.unsigned_abs() as usize
-                    } else {
-                        state.selection_index - n.unsigned_abs() as usize
-                    };
-                } else {
-                    state.selection_index =
-                        (state.selection_index + n.unsigned_abs() as usize) % state.guilds.len();
-                }
+        AppState::SelectingGuild if !state.guilds.is_empty() => {
+            let len = guild_list::guild_rows(state).len();
+            if len == 0 {
+                return;
             }
-        }
-        AppState::SelectingChannel(_, _) => {
-            if !state.channels.is_empty() {
-                let filter_text = state.input.to_lowercase();
-                let permission_context = &state.context;
-
-                let should_display_content = |c: &Channel| {
-                    let is_readable = permission_context
-                        .as_ref()
-                        .is_some_and(|context| c.is_readable(context));
 
-                    is_readable
-                        && (filter_text.is_empty() || c.name.to_lowercase().contains(&filter_text))
+            if n < 0 {
+                state.selection_index = if state.selection_index == 0 {
+                    len - n.unsigned_abs() as usize
+                } else {
+                    state.selection_index - n.unsigned_abs() as usize
                 };
+            } else {
+                state.selection_index = (state.selection_index + n.unsigned_abs() as usize) % len;
+            }
+        }
+        AppState::SelectingChannel(_, _) if !state.channels.is_empty() => {
+            // Hidden channels stay selectable so they can be inspected
+            let len = channel_list(state).len();
+            if len == 0 {
+                return;
+            }
 
-                let len: usize = state
-                    .channels
-                    .iter()
-                    .flat_map(|c| {
-                        if c.channel_type == 4 {
-                            let mut list_items_to_render: Vec<&Channel> = Vec::new();
-
-                            let name_matches = filter_text.is_empty()
-                                || c.name.to_lowercase().contains(&filter_text);
-
-                            let child_matches = c.children.as_ref().is_some_and(|children| {
-                                children.iter().any(should_display_content)
-                            });
-
-                            if name_matches || child_matches {
-                                list_items_to_render.push(c);
-
-                                if let Some(children) = &c.children {
-                                    list_items_to_render.extend(
-                                        children
-                                            .iter()
-                                            .filter(|child| should_display_content(child)),
-                                    );
-                                }
-                            }
-                            list_items_to_render
-                        } else if should_display_content(c) {
-                            vec![c]
-                        } else {
-                            vec![]
-                        }
-                    })
-                    .count();
-
-                if n < 0 {
-                    state.selection_index = if state.selection_index == 0 {
-                        len - n.unsigned_abs() as usize
-                    } else {
-                        state.selection_index - n.unsigned_abs() as usize
-                    };
+            if n < 0 {
+                state.selection_index = if state.selection_index == 0 {
+                    len - n.unsigned_abs() as usize
                 } else {
-                    state.selection_index =
-                        (state.selection_index + n.unsigned_abs() as usize) % len;
-                }
+                    state.selection_index - n.unsigned_abs() as usize
+                };
+            } else {
+                state.selection_index = (state.selection_index + n.unsigned_abs() as usize) % len;
             }
         }
-        AppState::EmojiSelection(_, _) => {
-            if total_filtered_emojis > 0 {
-                if n < 0 {
-                    state.emoji_index = if state.emoji_index == 0 {
-                        total_filtered_emojis - 1
-                    } else {
-                        state.emoji_index - 1
-                    };
+        AppState::EmojiSelection(_, _) if total_filtered_emojis > 0 => {
+            if n < 0 {
+                state.emoji_index = if state.emoji_index == 0 {
+                    total_filtered_emojis - 1
                 } else {
-                    state.emoji_index = (state.emoji_index + 1) % total_filtered_emojis;
-                }
+                    state.emoji_index - 1
+                };
+            } else {
+                state.emoji_index = (state.emoji_index + 1) % total_filtered_emojis;
             }
         }
         _ => {}
@@ -644,7 +934,7 @@ async fn move_selection(state: &mut MutexGuard<'_, App>, n: i32, total_filtered_
 }
 
 fn handle_user_typing(state: &mut App) {
-    if state.silent_typing {
+    if state.silent_typing || state.offline {
         return;
     }
     if let AppState::Chatting(channel_id, _) = &state.state {
@@ -684,6 +974,39 @@ pub async fn handle_keys_events(
 
     let total_filtered_emojis = filtered_unicode.len() + filtered_custom.len();
 
+    if popup::handle_popup_action(&mut state, &action) {
+        return None;
+    }
+
+    if audit_log::handle_audit_log_action(&mut state, &action, &tx_action) {
+        return None;
+    }
+
+    if form::handle_form_action(&mut state, &action, &tx_action) {
+        return None;
+    }
+
+    if roles::handle_role_view_action(&mut state, &action, &tx_action) {
+        return None;
+    }
+
+    if prompt::handle_prompt_action(&mut state, &action, &tx_action).await {
+        return None;
+    }
+
+    // After the prompt, which `n` opens on top of the view
+    if friends::handle_friends_action(&mut state, &action, &tx_action) {
+        return None;
+    }
+
+    if inbox::handle_inbox_action(&mut state, &action, &tx_action).await {
+        return None;
+    }
+
+    if layout::handle_layout_action(&mut state, &action, &tx_action).await {
+        return None;
+    }
+
     match action {
         AppAction::SigInt => return Some(KeywordAction::Break),
         AppAction::InputEscape => {
@@ -707,6 +1030,11 @@ pub async fn handle_keys_events(
                 }
                 return None;
             }
+            if state.visual_anchor.take().is_some() {
+                state.status_message = "Visual selection cancelled.".to_string();
+                return None;
+            }
+
             // Navigation logic: go back to previous screen or quit
             match &state.state {
                 AppState::Home | AppState::Loading(_) => return Some(KeywordAction::Break),
@@ -720,13 +1048,21 @@ pub async fn handle_keys_events(
                     tx_action.send(AppAction::TransitionToGuilds).await.ok();
                 }
                 AppState::Chatting(channel_id, _) => {
-                    let channel = match state.api_client.get_channel(&channel_id.clone()).await {
-                        Ok(c) => c,
-                        Err(e) => {
-                            tx_action.send(AppAction::TransitionToHome).await.ok();
-                            state.status_message = format!("{e}");
-                            return None;
-                        }
+                    if state.dms.iter().any(|dm| &dm.id == channel_id) {
+                        tx_action.send(AppAction::TransitionToDM).await.ok();
+                        return None;
+                    }
+
+                    let channel = match cached_channel(&state, channel_id) {
+                        Some(c) => c,
+                        None => match state.api_client.get_channel(&channel_id.clone()).await {
+                            Ok(c) => c,
+                            Err(e) => {
+                                tx_action.send(AppAction::TransitionToHome).await.ok();
+                                state.status_message = format!("{e}");
+                                return None;
+                            }
+                        },
                     };
 
                     if channel.channel_type == 1 || channel.channel_type == 3 {
@@ -755,6 +1091,13 @@ pub async fn handle_keys_events(
                 }
             }
         }
+        AppAction::InputChar(_) | AppAction::Paste(_)
+            if matches!(state.state, AppState::Chatting(_, _))
+                && (!state.vim_mode || state.mode == InputMode::Insert)
+                && lacks_permission(&state, Permissions::SEND_MESSAGES) =>
+        {
+            state.status_message = READ_ONLY_CHANNEL.to_string();
+        }
         AppAction::Paste(text) => {
             // Always insert text at cursor position, effectively treating it as insert mode operation
             // but without necessarily switching mode if we want to be strict.
@@ -786,6 +1129,9 @@ pub async fn handle_keys_events(
                 }
             }
         }
+        AppAction::WindowCommand => {
+            state.panes.pending = true;
+        }
         AppAction::SelectEmoji => {
             if let AppState::Chatting(channel_id, channel_name)
             | AppState::Editing(channel_id, channel_name, _, _) = state.state.clone()
@@ -993,32 +1339,13 @@ pub async fn handle_keys_events(
                 };
 
                 if should_ack {
-                    state
-                        .last_message_ids
-                        .insert(channel_id.clone(), newest_msg.id.clone());
-
-                    let api_client_clone = state.api_client.clone();
-                    let channel_id_clone = channel_id.clone();
-                    let msg_id_clone = newest_msg.id.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = api_client_clone
-                            .ack_message(&channel_id_clone, &msg_id_clone)
-                            .await
-                        {
-                            let _ = crate::logs::print_log(
-                                format!("Failed to ack message: {e}").into(),
-                                crate::logs::LogType::Error,
-                            );
-                        }
-                    });
+                    unread::ack(&mut state, &channel_id, &newest_msg.id);
                 }
             }
 
-            // Clear any active desktop notifications for this channel
-            if let Some(handles) = state.active_notifications.remove(&channel_id) {
-                for handle in handles {
-                    handle.close();
-                }
+            notifications::channel_read(&mut state, &channel_id);
+            if let Some(store) = state.store.as_ref() {
+                store.save_messages(&new_messages);
             }
             // Seed the username cache from all loaded message authors
             for msg in &new_messages {
@@ -1026,20 +1353,75 @@ pub async fn handle_keys_events(
                     .user_names
                     .insert(msg.author.id.clone(), msg.author.username.clone());
             }
-            state.messages = new_messages
+            let selected_id = state
+                .selection_index
+                .checked_sub(1)
+                .and_then(|i| state.messages.get(i))
+                .map(|m| m.id.clone());
+            let mut existing = std::mem::take(&mut state.messages);
+            // Without a cache the previous channel's messages may still be shown
+            existing.retain(|m| m.channel_id == channel_id);
+            state.messages = merge_messages(existing, new_messages)
                 .into_iter()
                 .filter(|m| !state.deleted_message_ids.contains(&m.id))
                 .collect();
+            // Stay on the message that was selected, wherever the merge put it
+            if let Some(index) =
+                selected_id.and_then(|id| state.messages.iter().position(|m| m.id == id))
+            {
+                state.selection_index = index + 1;
+            }
+            state.selection_index = state.selection_index.min(state.messages.len());
+            roles::request_missing_members(&mut state, &tx_action);
+            members::request_member_list(&mut state, &tx_action);
+            inbox::messages_loaded(&mut state, &channel_id, &tx_action).await;
         }
-        AppAction::ApiUpdateGuilds(new_guilds) => {
+        AppAction::ApiUpdateMentions(messages) => {
+            inbox::apply_mentions(&mut state, messages);
+        }
+        AppAction::ApiInboxGuild(message, guild_id) => {
+            inbox::open_message(&mut state, &tx_action, message, guild_id).await;
+        }
+        AppAction::ApiUpdateGuilds(mut new_guilds) => {
+            // Our list of guilds leaves out what READY's full guilds told us
+            for guild in &mut new_guilds {
+                if let Some(known) = state.guilds.iter().find(|g| g.id == guild.id) {
+                    guild.default_message_notifications = guild
+                        .default_message_notifications
+                        .or(known.default_message_notifications);
+                }
+            }
+            if let Some(store) = state.store.as_mut() {
+                store.save_guilds(&new_guilds);
+            }
             state.guilds = new_guilds.clone();
             state.status_message =
                 "Select a server. Use arrows to navigate, Enter to select & Esc to quit."
                     .to_string();
         }
+        AppAction::ApiUpdateGuildFolders(folders) | AppAction::GatewayGuildFolders(folders) => {
+            if let Some(store) = state.store.as_ref() {
+                store.save_value("guild_folders", &folders);
+            }
+            state.guild_folders = folders;
+        }
         AppAction::ApiUpdateChannel(new_channels) => {
+            unread::apply_channels(&mut state, &new_channels);
+            if let Some(store) = state.store.as_mut()
+                && let Some(guild_id) = new_channels.iter().find_map(|c| c.guild_id.clone())
+            {
+                store.save_channels(&guild_id, &new_channels);
+            }
             state.channels =
                 Channel::filter_channels_by_categories(new_channels).unwrap_or_default();
+            refresh_channel_permissions(&mut state);
+            // Also loaded behind a chat opened from the inbox, leave that alone
+            if !matches!(
+                state.state,
+                AppState::SelectingChannel(_, _) | AppState::Loading(_)
+            ) {
+                return None;
+            }
             let text_channels_count = state.channels.len();
             if text_channels_count > 0 {
                 state.status_message =
@@ -1048,7 +1430,10 @@ pub async fn handle_keys_events(
                 state.status_message =
                     "No text channels found. (Esc to return to Servers)".to_string();
             }
-            state.selection_index = 0;
+            // Cached channels may already be on screen, keep the selection when reconciling
+            if !matches!(state.state, AppState::SelectingChannel(_, _)) {
+                state.selection_index = 0;
+            }
         }
         AppAction::ApiUpdateEmojis(new_emojis) => {
             state.custom_emojis = new_emojis;
@@ -1064,6 +1449,10 @@ pub async fn handle_keys_events(
                         .unwrap_or(0),
                 )
             });
+            if let Some(store) = state.store.as_mut() {
+                store.save_dms(&new_dms);
+                store.save_users(new_dms.iter().flat_map(|dm| &dm.recipients));
+            }
             state.dms = new_dms.clone();
 
             // Initialize last_message_ids for all DMs on load and seed username cache
@@ -1088,12 +1477,96 @@ pub async fn handle_keys_events(
             } else {
                 state.status_message = "No DMs found. (Esc to return to Home)".to_string();
             }
-            state.selection_index = 0;
+            // Cached DMs may already be on screen, keep the selection when reconciling
+            if !matches!(state.state, AppState::SelectingDM) {
+                state.selection_index = 0;
+            }
         }
         AppAction::ApiUpdateContext(new_context) => {
+            if let Some(store) = state.store.as_ref()
+                && let Some(context) = &new_context
+            {
+                store.save_value(&format!("context:{}", context.everyone_role_id), context);
+            }
             state.context = new_context;
+            refresh_channel_permissions(&mut state);
+        }
+        AppAction::ApiUpdateAuditLog(guild_id, filter, log) => {
+            audit_log::apply_page(&mut state, &guild_id, &filter, log);
+        }
+        AppAction::ApiAuditLogFailed(guild_id, filter, error) => {
+            audit_log::page_failed(&mut state, &guild_id, &filter, &error);
+        }
+        AppAction::ApiChannelChanged(channel) => {
+            channel_admin::apply_channel_change(&mut state, channel);
+            refresh_channel_permissions(&mut state);
+        }
+        AppAction::ApiChannelDeleted(channel_id) => {
+            channel_admin::apply_channel_delete(&mut state, &channel_id);
+            layout::channel_gone(&mut state, &channel_id);
+            buffers::close(&mut state, &channel_id);
+        }
+        AppAction::ApiUpdateRoles(guild_id, new_roles) => {
+            roles::apply_roles(&mut state, &guild_id, new_roles);
+            refresh_channel_permissions(&mut state);
+        }
+        AppAction::ApiUpdateMembers(guild_id, new_members) => {
+            let members = state.members.entry(guild_id).or_default();
+            for member in new_members {
+                members.insert(member.user.id.clone(), member);
+            }
+        }
+        AppAction::ApiOpenMember(guild_id, member) => {
+            roles::open_member_form(&mut state, guild_id, member);
+        }
+        AppAction::ApiOpenProfile(user, user_profile, note) => {
+            profile::show_profile(&mut state, user, user_profile, note);
+        }
+        AppAction::GatewayRelationships(relationships)
+        | AppAction::ApiUpdateRelationships(relationships) => {
+            state.relationships = relationships;
+            friends::clamp_selection(&mut state);
+        }
+        AppAction::GatewayRelationshipAdd(relationship) => {
+            friends::apply_relationship(&mut state, relationship);
+        }
+        AppAction::GatewayRelationshipRemove(user_id) => {
+            friends::remove_relationship(&mut state, &user_id);
+        }
+        AppAction::ApiOpenDM(dm) => {
+            if !state.dms.iter().any(|d| d.id == dm.id) {
+                group_dm::apply_dm(&mut state, dm.clone());
+            }
+            state.friends_view = None;
+            state.status_message = format!("Loading messages for {}...", dm.get_name());
+            open_chat(&mut state, &tx_action, dm.id).await;
+        }
+        AppAction::ApiDMChanged(dm) | AppAction::GatewayDMUpdate(dm) => {
+            group_dm::apply_dm(&mut state, dm);
+        }
+        AppAction::ApiDMLeft(channel_id) | AppAction::GatewayDMDelete(channel_id) => {
+            group_dm::remove_dm(&mut state, &channel_id);
+            layout::channel_gone(&mut state, &channel_id);
+            buffers::close(&mut state, &channel_id);
+            if matches!(&state.state, AppState::Chatting(id, _) if *id == channel_id) {
+                tx_action.send(AppAction::TransitionToDM).await.ok();
+            }
+        }
+        AppAction::GatewayRecipientAdd(channel_id, user) => {
+            group_dm::add_recipient(&mut state, &channel_id, user);
+        }
+        AppAction::GatewayRecipientRemove(channel_id, user_id) => {
+            group_dm::remove_recipient(&mut state, &channel_id, &user_id);
+            if state.dms.iter().all(|dm| dm.id != channel_id)
+                && matches!(&state.state, AppState::Chatting(id, _) if *id == channel_id)
+            {
+                tx_action.send(AppAction::TransitionToDM).await.ok();
+            }
         }
         AppAction::ApiUpdateCurrentUser(user) => {
+            if let Some(store) = state.store.as_ref() {
+                store.save_value("current_user", &user);
+            }
             state.current_user = Some(user);
         }
         AppAction::GatewayTypingStart(channel_id, user_id, display_name) => {
@@ -1108,26 +1581,34 @@ pub async fn handle_keys_events(
         }
 
         AppAction::GatewayMessageCreate(msg) => {
-            let active_channel_id = if let AppState::Chatting(id, _) = &state.state {
-                Some(id.clone())
-            } else {
-                None
-            };
+            if let Some(store) = state.store.as_ref() {
+                store.save_messages(std::slice::from_ref(&msg));
+            }
+            unread::record_message(&mut state, &msg);
+            if let (Some(guild_id), Some(member)) = (&msg.guild_id, &msg.member) {
+                state.members.entry(guild_id.clone()).or_default().insert(
+                    msg.author.id.clone(),
+                    GuildMember::from_partial(msg.author.clone(), member.clone()),
+                );
+            }
 
-            if Some(msg.channel_id.clone()) == active_channel_id {
-                let mut msgs = state.messages.clone();
+            // Open tabs in the background stay live too
+            let visual_cursor = moderation::visual_cursor(&state, &msg.channel_id);
+            if let Some(msgs) = buffers::messages_mut(&mut state, &msg.channel_id) {
+                msgs.push(msg.clone());
+                // Sort by descending ID: newest messages first (to match REST API response)
+                msgs.sort_by_key(|m| std::cmp::Reverse(m.id.parse::<u64>().unwrap_or_default()));
+                buffers::trim(&mut state, &msg.channel_id);
                 // Cache author username from incoming message
                 state
                     .user_names
                     .insert(msg.author.id.clone(), msg.author.username.clone());
-                msgs.push(msg.clone());
-                // Sort by descending ID: newest messages first (to match REST API response)
-                msgs.sort_by_key(|m| std::cmp::Reverse(m.id.parse::<u64>().unwrap_or_default()));
-                state.messages = msgs;
+            }
+            moderation::restore_visual_cursor(&mut state, visual_cursor);
 
-                state
-                    .last_message_ids
-                    .insert(msg.channel_id.clone(), msg.id.clone());
+            // Split windows show their channel too, not only the focused one
+            if windows::is_shown(&state, &msg.channel_id) {
+                unread::mark_read(&mut state, &msg.channel_id, &msg.id);
 
                 let api_client_clone = state.api_client.clone();
                 let channel_id_clone = msg.channel_id.clone();
@@ -1137,35 +1618,9 @@ pub async fn handle_keys_events(
                         .ack_message(&channel_id_clone, &msg_id_clone)
                         .await;
                 });
-            } else if state.dms.iter().any(|dm| dm.id == msg.channel_id) {
-                // If it's a DM and we're not actively viewing it, maybe notify
-                let is_self = state
-                    .current_user
-                    .as_ref()
-                    .is_some_and(|u| u.id == msg.author.id);
-
-                if !is_self {
-                    let sender = msg.author.username.clone();
-                    let content = if state.discreet_notifs {
-                        "Sent you a DM".to_string()
-                    } else {
-                        msg.content
-                            .clone()
-                            .unwrap_or_else(|| "Sent an attachment".to_string())
-                    };
-                    if let Ok(handle) = notify_rust::Notification::new()
-                        .summary(&sender)
-                        .body(&content)
-                        .appname("vimcord")
-                        .show()
-                    {
-                        state
-                            .active_notifications
-                            .entry(msg.channel_id.clone())
-                            .or_default()
-                            .push(handle);
-                    }
-                }
+            } else {
+                inbox::record_message(&mut state, &msg);
+                notifications::notify_message(&mut state, &msg);
 
                 // Jump this DM to the top of the list
                 if let Some(pos) = state.dms.iter().position(|dm| dm.id == msg.channel_id) {
@@ -1173,10 +1628,6 @@ pub async fn handle_keys_events(
                     dm.last_message_id = Some(msg.id.clone());
                     state.dms.insert(0, dm);
                 }
-
-                state
-                    .last_message_ids
-                    .insert(msg.channel_id.clone(), msg.id.clone());
             }
 
             // Remove the typing indicator if the author sent a message in the channel
@@ -1184,16 +1635,42 @@ pub async fn handle_keys_events(
                 typers.remove(&msg.author.id);
             }
         }
-        AppAction::GatewayReadySupplemental(statuses) => {
+        AppAction::ApiUpdateOwnStatus(status) | AppAction::GatewayOwnStatus(status) => {
+            state.own_status = Some(status);
+        }
+        AppAction::GatewayGuilds(guilds) => {
+            notifications::apply_guild_defaults(&mut state, guilds);
+        }
+        AppAction::GatewayGuildSettings(settings) => {
+            notifications::apply_guild_settings(&mut state, settings);
+        }
+        AppAction::GatewayGuildSettingsUpdate(settings) => {
+            notifications::apply_guild_settings(&mut state, vec![settings]);
+        }
+        AppAction::GatewayReadState(read_states, heads) => {
+            unread::apply_ready(&mut state, read_states, heads);
+        }
+        AppAction::GatewayMessageAck(channel_id, message_id) => {
+            unread::mark_read(&mut state, &channel_id, &message_id);
+        }
+        AppAction::GatewayReadySupplemental(statuses, activities) => {
             state.user_statuses.extend(statuses);
+            state.user_activities.extend(activities);
         }
-        AppAction::GatewayPresenceUpdate(user_id, status) => {
+        AppAction::GatewayPresenceUpdate(user_id, status, activity) => {
+            match activity {
+                Some(activity) => state.user_activities.insert(user_id.clone(), activity),
+                None => state.user_activities.remove(&user_id),
+            };
             state.user_statuses.insert(user_id, status);
         }
         AppAction::GatewayMessageUpdate(msg) => {
-            let mut msgs = state.messages.clone();
-            if let Some(pos) = msgs.iter().position(|m| m.id == msg.id) {
-                let mut existing = msgs[pos].clone();
+            if let Some(store) = state.store.as_ref() {
+                store.update_message(&msg);
+            }
+            if let Some(existing) = buffers::messages_mut(&mut state, &msg.channel_id)
+                .and_then(|msgs| msgs.iter_mut().find(|m| m.id == msg.id))
+            {
                 if let Some(content) = msg.content {
                     existing.content = Some(content);
                 }
@@ -1203,25 +1680,40 @@ pub async fn handle_keys_events(
                 if let Some(timestamp) = msg.timestamp {
                     existing.timestamp = timestamp;
                 }
-                msgs[pos] = existing;
-                state.messages = msgs;
+                if let Some(edited_timestamp) = msg.edited_timestamp {
+                    existing.edited_timestamp = Some(edited_timestamp);
+                }
             }
         }
-        AppAction::GatewayMessageDelete(id, _channel_id) => {
-            let mut msgs = state.messages.clone();
-            msgs.retain(|m| m.id != id);
-            state.messages = msgs;
+        AppAction::GatewayMessageDelete(id, channel_id) => {
+            if let Some(store) = state.store.as_ref() {
+                store.delete_message(&id);
+            }
+            let visual_cursor = moderation::visual_cursor(&state, &channel_id);
+            if let Some(msgs) = buffers::messages_mut(&mut state, &channel_id) {
+                msgs.retain(|m| m.id != id);
+            }
+            moderation::restore_visual_cursor(&mut state, visual_cursor);
             state.deleted_message_ids.insert(id);
         }
         AppAction::TransitionToChannels(guild_id) => {
-            let guild_name = match state.api_client.get_guild(guild_id.as_str()).await {
-                Ok(g) => g.name,
-                Err(e) => {
-                    print_log(e, LogType::Error).ok();
-                    "<Empty Name>".to_string()
-                }
+            let cached_guild_name = state
+                .guilds
+                .iter()
+                .find(|g| g.id == guild_id)
+                .map(|g| g.name.clone());
+            let guild_name = match cached_guild_name {
+                Some(name) => name,
+                None => match state.api_client.get_guild(guild_id.as_str()).await {
+                    Ok(g) => g.name,
+                    Err(e) => {
+                        print_log(e, LogType::Error).ok();
+                        "<Empty Name>".to_string()
+                    }
+                },
             };
 
+            buffers::save_view(&mut state);
             state.input = String::new();
             state.cursor_position = 0;
             state.state = AppState::SelectingChannel(guild_id.clone(), guild_name);
@@ -1229,6 +1721,8 @@ pub async fn handle_keys_events(
                 "Select a server. Use arrows to navigate, Enter to select & Esc to quit"
                     .to_string();
             state.selection_index = 0;
+            state.panes.guild_id = Some(guild_id);
+            layout::select_current(&mut state);
         }
         AppAction::TransitionToChat(channel_id) => {
             // Check if we're coming from emoji selection before changing state
@@ -1249,24 +1743,29 @@ pub async fn handle_keys_events(
                 state.input = state.saved_input.clone().unwrap_or_default();
                 state.saved_input = None;
             }
-            let channel_name = match state.api_client.get_channel(channel_id.as_str()).await {
-                Ok(c) => c.name,
-                Err(e) => {
-                    print_log(e, LogType::Error).ok();
-                    "<Empty Name>".to_string()
-                }
-            };
+            let channel_name = channel_name(&state, &channel_id).await;
 
+            layout::chat_opened(&mut state, &channel_id, &channel_name);
             state.state = AppState::Chatting(channel_id.clone(), channel_name);
+            refresh_channel_permissions(&mut state);
+            state.visual_anchor = None;
             state.chat_scroll_offset = 0;
             state.cursor_position = 0;
             state.selection_index = 0;
             state.last_typing_sent = None;
-            state.status_message =
+            if let AppState::Chatting(channel_id, channel_name) = state.state.clone() {
+                buffers::enter(&mut state, &channel_id, &channel_name);
+            }
+            state.status_message = if lacks_permission(&state, Permissions::SEND_MESSAGES) {
+                READ_ONLY_CHANNEL.to_string()
+            } else {
                 "Chatting in channel. Press Enter to send message, Esc to return to channels."
-                    .to_string();
+                    .to_string()
+            };
+            inbox::finish_jump(&mut state, &tx_action).await;
         }
         AppAction::TransitionToGuilds => {
+            buffers::save_view(&mut state);
             state.input = String::new();
             state.cursor_position = 0;
             state.state = AppState::SelectingGuild;
@@ -1274,23 +1773,34 @@ pub async fn handle_keys_events(
                 "Select a server. Use arrows to navigate, Enter to select & Esc to quit"
                     .to_string();
             state.selection_index = 0;
+            layout::select_current(&mut state);
         }
         AppAction::TransitionToDM => {
+            buffers::save_view(&mut state);
             state.input = String::new();
             state.cursor_position = 0;
             state.state = AppState::SelectingDM;
             state.status_message =
                 "Select a DM. Use arrows to navigate, Enter to select & Esc to quit".to_string();
             state.selection_index = 0;
+            state.panes.guild_id = None;
+            layout::select_current(&mut state);
+        }
+        AppAction::ApiDeleteMessage(_, _, _)
+        | AppAction::ApiBulkDeleteMessages(_, _, _)
+        | AppAction::TransitionToEditing(_, _, _, _)
+            if state.offline =>
+        {
+            state.status_message = OFFLINE_READ_ONLY.to_string();
         }
-        AppAction::ApiDeleteMessage(channel_id, message_id) => {
+        AppAction::ApiDeleteMessage(channel_id, message_id, reason) => {
             let api_client_clone = state.api_client.clone();
             let channel_id_clone = channel_id.clone();
             let message_id_clone = message_id.clone();
 
             tokio::spawn(async move {
                 if let Err(e) = api_client_clone
-                    .delete_message(&channel_id_clone, &message_id_clone)
+                    .delete_message(&channel_id_clone, &message_id_clone, reason.as_deref())
                     .await
                 {
                     let _ = print_log(
@@ -1309,6 +1819,47 @@ pub async fn handle_keys_events(
                 state.selection_index = state.messages.len();
             }
         }
+        AppAction::ApiBulkDeleteMessages(channel_id, message_ids, reason) => {
+            let api_client_clone = state.api_client.clone();
+            let tx_action_clone = tx_action.clone();
+            let bulk = !lacks_permission(&state, Permissions::MANAGE_MESSAGES)
+                && state.channel_permissions.is_some();
+            let ids = message_ids.clone();
+
+            tokio::spawn(async move {
+                let count = ids.len();
+                let status = match moderation::delete_messages(
+                    &api_client_clone,
+                    &channel_id,
+                    ids,
+                    bulk,
+                    reason.as_deref(),
+                )
+                .await
+                {
+                    Ok(()) => format!("Deleted {count} messages."),
+                    Err(e) => {
+                        let _ = print_log(
+                            format!("API Error deleting messages: {e}").into(),
+                            LogType::Error,
+                        );
+                        format!("Failed to delete messages: {e}")
+                    }
+                };
+                tx_action_clone
+                    .send(AppAction::UpdateStatus(status))
+                    .await
+                    .ok();
+            });
+
+            for message_id in message_ids {
+                state.messages.retain(|m| m.id != message_id);
+                state.deleted_message_ids.insert(message_id);
+            }
+            if state.selection_index > state.messages.len() {
+                state.selection_index = state.messages.len();
+            }
+        }
         AppAction::ApiEditMessage(channel_id, message_id, content) => {
             let (api_client_clone, channel_id_clone, message_id_clone, content_clone) = (
                 state.api_client.clone(),
@@ -1350,13 +1901,7 @@ pub async fn handle_keys_events(
                 _ => content.len(),
             };
 
-            let channel_name = match state.api_client.get_channel(channel_id.as_str()).await {
-                Ok(c) => c.name,
-                Err(e) => {
-                    print_log(e, LogType::Error).ok();
-                    "<Empty Name>".to_string()
-                }
-            };
+            let channel_name = channel_name(&state, &channel_id).await;
 
             state.selection_index = 0;
             state.state = AppState::Editing(
@@ -1370,6 +1915,7 @@ pub async fn handle_keys_events(
                     .to_string();
         }
         AppAction::TransitionToHome => {
+            buffers::save_view(&mut state);
             state.input = String::new();
             state.cursor_position = 0;
             state.state = AppState::Home;
@@ -1397,10 +1943,33 @@ pub async fn handle_keys_events(
                 };
             }
         }
+        AppAction::ApiHistoryPage(channel_id, page) => {
+            if !matches!(&state.state, AppState::Chatting(id, _) if *id == channel_id) {
+                return None;
+            }
+            if let Some(store) = state.store.as_ref() {
+                store.save_messages(&page);
+            }
+            // Only what is older than the oldest loaded message, the gateway added the rest
+            let oldest = state
+                .messages
+                .last()
+                .map_or(u64::MAX, |m| unread::id_value(&m.id));
+            state.messages.extend(
+                page.into_iter()
+                    .filter(|m| unread::id_value(&m.id) < oldest),
+            );
+        }
+        AppAction::HistoryLoaded(channel_id, goal) => {
+            history_loaded(&mut state, &channel_id, goal).await;
+        }
         AppAction::TransitionToLoadingMessages => {
             state.is_loading = true;
             state.status_message = "Loading Messages...".to_string();
         }
+        AppAction::UpdateStatus(message) => {
+            state.status_message = message;
+        }
         AppAction::EndLoadingMessages => {
             state.is_loading = false;
             state.status_message =
@@ -1430,3 +1999,39 @@ pub async fn handle_keys_events(
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{DM_CHANNEL, FRIEND, app, dispatch, dm, message, open};
+
+    #[tokio::test]
+    async fn refetched_pages_merge_into_older_history() {
+        let mut app = app();
+        app.dms = vec![dm(DM_CHANNEL)];
+        let mut app = open(app, DM_CHANNEL).await;
+        app.messages = vec![
+            message("103", DM_CHANNEL, None, FRIEND, "deleted while away"),
+            message("102", DM_CHANNEL, None, FRIEND, "before the edit"),
+            message("50", DM_CHANNEL, None, FRIEND, "scrolled in"),
+        ];
+        app.selection_index = 3;
+
+        let page = vec![
+            message("104", DM_CHANNEL, None, FRIEND, "new"),
+            message("102", DM_CHANNEL, None, FRIEND, "after the edit"),
+            message("101", DM_CHANNEL, None, FRIEND, "missed"),
+        ];
+        let app = dispatch(
+            app,
+            vec![AppAction::ApiUpdateMessages(DM_CHANNEL.to_string(), page)],
+        )
+        .await;
+
+        let ids: Vec<&str> = app.messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["104", "102", "101", "50"]);
+        assert_eq!(app.messages[1].content.as_deref(), Some("after the edit"));
+        // Still on "scrolled in"
+        assert_eq!(app.selection_index, 4);
+    }
+}
@@ -0,0 +1,130 @@
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+
+use crate::{
+    App,
+    api::permissions::{PermissionStep, Permissions, explain_permissions, is_synced},
+    ui::{events::channel_list, popup::Popup},
+};
+
+fn permission_names(permissions: Permissions) -> String {
+    permissions
+        .iter_names()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn step_lines(step: &PermissionStep) -> Vec<Line<'static>> {
+    // Steps without a result are only applied together, by the step that follows them
+    let label = match step.result {
+        Some(_) => step.label.clone(),
+        None => format!("· {}", step.label),
+    };
+    let mut lines = vec![Line::from(Span::styled(label, Style::default().bold()))];
+
+    if !step.allow.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("  + {}", permission_names(step.allow)),
+            Style::default().fg(Color::LightGreen),
+        )));
+    }
+    if !step.deny.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("  - {}", permission_names(step.deny)),
+            Style::default().fg(Color::LightRed),
+        )));
+    }
+    if step.allow.is_empty() && step.deny.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  (no change)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    lines
+}
+
+/// Opens a popup walking through how our permissions in the selected channel are computed.
+pub fn explain_selected_channel(state: &mut App) {
+    let Some(context) = state.context.clone() else {
+        state.status_message = "Permissions are still loading.".to_string();
+        return;
+    };
+    let Some(channel) = channel_list(state)
+        .get(state.selection_index)
+        .cloned()
+        .cloned()
+    else {
+        return;
+    };
+
+    let heading = |text: &str| {
+        Line::from(Span::styled(
+            text.to_string(),
+            Style::default().fg(Color::Yellow).bold(),
+        ))
+    };
+
+    let mut lines = Vec::new();
+
+    let category = channel.parent_id.as_ref().and_then(|parent_id| {
+        state
+            .channels
+            .iter()
+            .find(|c| &c.id == parent_id && c.channel_type == 4)
+    });
+    lines.push(Line::from(match category {
+        Some(category)
+            if is_synced(
+                &channel.permission_overwrites,
+                &category.permission_overwrites,
+            ) =>
+        {
+            format!("Category {}: permissions synced", category.name)
+        }
+        Some(category) => format!("Category {}: permissions NOT synced", category.name),
+        None => "No category".to_string(),
+    }));
+    lines.push(Line::from(""));
+
+    let (steps, result) = explain_permissions(&context, &channel.permission_overwrites);
+
+    lines.push(heading("Steps"));
+    for step in &steps {
+        lines.extend(step_lines(step));
+    }
+    lines.push(Line::from(""));
+
+    lines.push(heading("Result"));
+    for (name, permission) in Permissions::all().iter_names() {
+        // The last step that mentioned a permission is the one that decided it
+        let decided_by = steps
+            .iter()
+            .rev()
+            .find(|s| s.allow.contains(permission) || s.deny.contains(permission))
+            .map(|s| s.label.clone())
+            .unwrap_or("not granted by any role".to_string());
+
+        let (mark, color) = if result.contains(permission) {
+            ("✓", Color::LightGreen)
+        } else {
+            ("✗", Color::LightRed)
+        };
+
+        lines.push(Line::from(vec![
+            Span::styled(format!("{mark} {name}"), Style::default().fg(color)),
+            Span::styled(
+                format!("  {decided_by}"),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+
+    state.popup = Some(Popup::new(
+        format!("Why {} looks like this (Esc to close)", channel.name),
+        lines,
+    ));
+}
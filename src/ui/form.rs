@@ -0,0 +1,251 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction,
+    ui::{channel_admin, friends, group_dm, notifications, roles},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    /// Options are `(value, label)` pairs
+    Select {
+        options: Vec<(String, String)>,
+        selected: usize,
+    },
+    Checkbox(bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub key: &'static str,
+    pub label: String,
+    pub value: FieldValue,
+}
+
+impl FormField {
+    pub fn text(key: &'static str, label: &str, value: impl Into<String>) -> Self {
+        Self {
+            key,
+            label: label.to_string(),
+            value: FieldValue::Text(value.into()),
+        }
+    }
+
+    /// Starts on the option whose value is `selected`, or the first one.
+    pub fn select(
+        key: &'static str,
+        label: &str,
+        options: Vec<(String, String)>,
+        selected: &str,
+    ) -> Self {
+        let selected = options
+            .iter()
+            .position(|(value, _)| value == selected)
+            .unwrap_or(0);
+
+        Self {
+            key,
+            label: label.to_string(),
+            value: FieldValue::Select { options, selected },
+        }
+    }
+
+    pub fn checkbox(key: &'static str, label: &str, checked: bool) -> Self {
+        Self {
+            key,
+            label: label.to_string(),
+            value: FieldValue::Checkbox(checked),
+        }
+    }
+
+    /// Steps a select through its options, or flips a checkbox.
+    fn cycle(&mut self, step: isize) {
+        match &mut self.value {
+            FieldValue::Select { options, selected } if !options.is_empty() => {
+                *selected = (*selected as isize + step).rem_euclid(options.len() as isize) as usize;
+            }
+            FieldValue::Checkbox(checked) => *checked = !*checked,
+            _ => {}
+        }
+    }
+}
+
+/// What a form does once submitted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormPurpose {
+    CreateChannel {
+        guild_id: String,
+    },
+    EditChannel {
+        channel_id: String,
+        /// Categories are edited with their name only
+        channel_type: u8,
+    },
+    DeleteChannel {
+        channel_id: String,
+    },
+    EditOverwrite {
+        channel_id: String,
+        target_id: String,
+        target_type: u8,
+    },
+    CreateRole {
+        guild_id: String,
+    },
+    EditRole {
+        guild_id: String,
+        role_id: String,
+    },
+    DeleteRole {
+        guild_id: String,
+        role_id: String,
+    },
+    /// One checkbox per role of the guild, highest first
+    MemberRoles {
+        guild_id: String,
+        user_id: String,
+        /// The role of each checkbox, in field order
+        role_ids: Vec<String>,
+    },
+    RemoveFriend {
+        user_id: String,
+    },
+    BlockUser {
+        user_id: String,
+    },
+    LeaveGroup {
+        channel_id: String,
+    },
+    /// Our overrides for a channel, a DM or a guild
+    NotificationSettings {
+        target_id: String,
+    },
+}
+
+/// A popup of labelled fields, like a small dialog.
+#[derive(Debug, Clone)]
+pub struct Form {
+    pub title: String,
+    pub fields: Vec<FormField>,
+    pub focused: usize,
+    pub purpose: FormPurpose,
+}
+
+impl Form {
+    pub fn new(title: String, purpose: FormPurpose, fields: Vec<FormField>) -> Self {
+        Self {
+            title,
+            fields,
+            focused: 0,
+            purpose,
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<&FieldValue> {
+        self.fields.iter().find(|f| f.key == key).map(|f| &f.value)
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        match self.value(key) {
+            Some(FieldValue::Text(text)) => text.trim().to_string(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn selected(&self, key: &str) -> String {
+        match self.value(key) {
+            Some(FieldValue::Select { options, selected }) => options
+                .get(*selected)
+                .map(|(value, _)| value.clone())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn checked(&self, key: &str) -> bool {
+        matches!(self.value(key), Some(FieldValue::Checkbox(true)))
+    }
+}
+
+/// Routes input actions to the open form.
+/// Returns `true` when the action was consumed by the form.
+pub fn handle_form_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let Some(form) = state.form.as_mut() else {
+        return false;
+    };
+    let field_count = form.fields.len().max(1);
+    let field = form.fields.get_mut(form.focused);
+
+    match (action, field) {
+        (AppAction::InputEscape, _) => {
+            state.form = None;
+            state.status_message = "Cancelled.".to_string();
+        }
+        (AppAction::SelectNext, _) => {
+            form.focused = (form.focused + 1) % field_count;
+        }
+        (AppAction::SelectPrevious, _) => {
+            form.focused = (form.focused + field_count - 1) % field_count;
+        }
+        (AppAction::SelectLeft, Some(field)) => field.cycle(-1),
+        (AppAction::SelectRight, Some(field)) => field.cycle(1),
+        (AppAction::InputChar(c), Some(field)) => match &mut field.value {
+            FieldValue::Text(text) => text.push(*c),
+            _ if *c == ' ' => field.cycle(1),
+            _ => {}
+        },
+        (AppAction::Paste(pasted), Some(field)) => {
+            if let FieldValue::Text(text) = &mut field.value {
+                text.push_str(&pasted.replace('\n', " "));
+            }
+        }
+        (AppAction::InputBackspace, Some(field)) => {
+            if let FieldValue::Text(text) = &mut field.value {
+                text.pop();
+            }
+        }
+        (AppAction::InputSubmit, _) => {
+            let form = state.form.take().unwrap();
+            match form.purpose {
+                FormPurpose::CreateChannel { .. }
+                | FormPurpose::EditChannel { .. }
+                | FormPurpose::DeleteChannel { .. }
+                | FormPurpose::EditOverwrite { .. } => {
+                    channel_admin::submit_form(state, form, tx_action);
+                }
+                FormPurpose::CreateRole { .. }
+                | FormPurpose::EditRole { .. }
+                | FormPurpose::DeleteRole { .. }
+                | FormPurpose::MemberRoles { .. } => {
+                    roles::submit_form(state, form, tx_action);
+                }
+                FormPurpose::RemoveFriend { .. } | FormPurpose::BlockUser { .. } => {
+                    friends::submit_form(state, form, tx_action);
+                }
+                FormPurpose::LeaveGroup { .. } => {
+                    group_dm::submit_form(state, form, tx_action);
+                }
+                FormPurpose::NotificationSettings { .. } => {
+                    notifications::submit_form(state, form);
+                }
+            }
+        }
+        (
+            AppAction::InputChar(_)
+            | AppAction::Paste(_)
+            | AppAction::InputBackspace
+            | AppAction::InputDelete
+            | AppAction::SelectLeft
+            | AppAction::SelectRight,
+            _,
+        ) => {}
+        _ => return false,
+    }
+
+    true
+}
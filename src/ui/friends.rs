@@ -0,0 +1,349 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction,
+    api::{
+        ApiClient,
+        user::{
+            RELATIONSHIP_BLOCKED, RELATIONSHIP_FRIEND, RELATIONSHIP_INCOMING,
+            RELATIONSHIP_OUTGOING, Relationship,
+        },
+    },
+    logs::{LogType, print_log},
+    ui::{
+        form::{Form, FormField, FormPurpose},
+        group_dm,
+        prompt::{Prompt, PromptKind},
+    },
+};
+
+/// Friends, pending requests and blocked users, opened from the Home menu.
+#[derive(Debug, Clone, Default)]
+pub struct FriendsView {
+    pub selected: usize,
+    /// Friends marked with Space, Enter then opens a group DM with them
+    pub marked: Vec<String>,
+}
+
+fn kind_rank(kind: u8) -> u8 {
+    match kind {
+        RELATIONSHIP_INCOMING => 0,
+        RELATIONSHIP_OUTGOING => 1,
+        RELATIONSHIP_FRIEND => 2,
+        _ => 3,
+    }
+}
+
+pub fn kind_label(kind: u8) -> &'static str {
+    match kind {
+        RELATIONSHIP_INCOMING => "Incoming request",
+        RELATIONSHIP_OUTGOING => "Outgoing request",
+        RELATIONSHIP_FRIEND => "Friend",
+        RELATIONSHIP_BLOCKED => "Blocked",
+        _ => "Unknown",
+    }
+}
+
+fn is_online(state: &App, user_id: &str) -> bool {
+    state
+        .user_statuses
+        .get(user_id)
+        .is_some_and(|status| status != "offline" && status != "invisible")
+}
+
+/// Pending requests first, then friends with the online ones on top, then blocked users.
+pub fn sorted_relationships(state: &App) -> Vec<&Relationship> {
+    let mut relationships: Vec<&Relationship> = state.relationships.iter().collect();
+    relationships.sort_by_key(|r| {
+        (
+            kind_rank(r.kind),
+            !is_online(state, &r.id),
+            display_name(r).to_lowercase(),
+        )
+    });
+    relationships
+}
+
+pub fn display_name(relationship: &Relationship) -> String {
+    relationship
+        .nickname
+        .clone()
+        .or(relationship.user.global_name.clone())
+        .unwrap_or(relationship.user.username.clone())
+}
+
+fn selected(state: &App) -> Option<Relationship> {
+    let view = state.friends_view.as_ref()?;
+    sorted_relationships(state)
+        .get(view.selected)
+        .map(|r| (*r).clone())
+}
+
+async fn report_error(tx_action: &Sender<AppAction>, what: &str, e: crate::Error) {
+    let _ = print_log(format!("Failed to {what}: {e}").into(), LogType::Error);
+    tx_action
+        .send(AppAction::UpdateStatus(format!("Failed to {what}: {e}")))
+        .await
+        .ok();
+}
+
+async fn reload_relationships(api_client: &ApiClient, tx_action: &Sender<AppAction>) {
+    match api_client.get_relationships().await {
+        Ok(relationships) => {
+            tx_action
+                .send(AppAction::ApiUpdateRelationships(relationships))
+                .await
+                .ok();
+        }
+        Err(e) => report_error(tx_action, "load friends", e).await,
+    }
+}
+
+/// The "Friends" entry of the Home menu.
+pub fn open_friends(state: &mut App, tx_action: &Sender<AppAction>) {
+    state.friends_view = Some(FriendsView::default());
+    state.status_message =
+        "Friends: Enter opens a DM or accepts, Space marks for a group DM, d removes/declines, b blocks, n adds"
+            .to_string();
+
+    // READY already sent them, unless the gateway is not up yet
+    if state.relationships.is_empty() && !state.offline {
+        let api_client = state.api_client.clone();
+        let tx_action = tx_action.clone();
+        tokio::spawn(async move {
+            reload_relationships(&api_client, &tx_action).await;
+        });
+    }
+}
+
+/// A change to send to Discord, the user id or username it applies to.
+enum Change {
+    Request(String),
+    Accept(String),
+    Block(String),
+    Remove(String),
+}
+
+/// Runs a relationship request, then refreshes the list so it reflects Discord's answer.
+fn update_relationship(
+    state: &mut App,
+    tx_action: &Sender<AppAction>,
+    change: Change,
+    done: String,
+) {
+    if state.offline {
+        state.status_message = "Friends can't be changed offline.".to_string();
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        let result = match &change {
+            Change::Request(username) => api_client.send_friend_request(username).await,
+            Change::Accept(user_id) => api_client.put_relationship(user_id, None).await,
+            Change::Block(user_id) => {
+                api_client
+                    .put_relationship(user_id, Some(RELATIONSHIP_BLOCKED))
+                    .await
+            }
+            Change::Remove(user_id) => api_client.delete_relationship(user_id).await,
+        };
+        match result {
+            Ok(()) => {
+                tx_action.send(AppAction::UpdateStatus(done)).await.ok();
+                reload_relationships(&api_client, &tx_action).await;
+            }
+            Err(e) => report_error(&tx_action, "update friends", e).await,
+        }
+    });
+}
+
+/// Sends a friend request to `username`, from the `n` prompt.
+pub fn send_request(state: &mut App, username: String, tx_action: &Sender<AppAction>) {
+    let username = username.trim().trim_start_matches('@').to_lowercase();
+    if username.is_empty() {
+        return;
+    }
+
+    state.status_message = format!("Sending a friend request to {username}...");
+    let done = format!("Friend request sent to {username}.");
+    update_relationship(state, tx_action, Change::Request(username), done);
+}
+
+fn confirm(state: &mut App, title: String, purpose: FormPurpose, label: &str) {
+    state.form = Some(Form::new(
+        title,
+        purpose,
+        vec![FormField::checkbox("confirm", label, false)],
+    ));
+}
+
+/// Routes input actions to the open friends view.
+/// Returns `true` when the action was consumed by the view.
+pub fn handle_friends_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let last = state.relationships.len().saturating_sub(1);
+    let Some(view) = state.friends_view.as_mut() else {
+        return false;
+    };
+
+    match action {
+        AppAction::InputEscape | AppAction::InputChar('q') => {
+            state.friends_view = None;
+        }
+        AppAction::SelectNext | AppAction::InputChar('j') => {
+            view.selected = (view.selected + 1).min(last);
+        }
+        AppAction::SelectPrevious | AppAction::InputChar('k') => {
+            view.selected = view.selected.saturating_sub(1);
+        }
+        AppAction::InputChar(' ') => {
+            if let Some(relationship) = selected(state)
+                && relationship.kind == RELATIONSHIP_FRIEND
+                && let Some(view) = state.friends_view.as_mut()
+            {
+                match view.marked.iter().position(|id| *id == relationship.id) {
+                    Some(i) => {
+                        view.marked.remove(i);
+                    }
+                    None => view.marked.push(relationship.id),
+                }
+                state.status_message = match state.friends_view.as_ref().map(|v| v.marked.len()) {
+                    Some(0) | None => "No friends marked.".to_string(),
+                    Some(n) => format!("{n} marked, Enter creates a group DM with them."),
+                };
+            }
+        }
+        AppAction::InputChar('n') => {
+            state.prompt = Some(Prompt::new(PromptKind::FriendRequest));
+        }
+        AppAction::InputSubmit | AppAction::InputChar('a') => {
+            let Some(relationship) = selected(state) else {
+                return true;
+            };
+            let name = display_name(&relationship);
+            match relationship.kind {
+                RELATIONSHIP_INCOMING => {
+                    update_relationship(
+                        state,
+                        tx_action,
+                        Change::Accept(relationship.id),
+                        format!("You are now friends with {name}."),
+                    );
+                }
+                RELATIONSHIP_FRIEND if matches!(action, AppAction::InputSubmit) => {
+                    let marked = state
+                        .friends_view
+                        .as_mut()
+                        .map(|view| std::mem::take(&mut view.marked))
+                        .unwrap_or_default();
+                    let user_ids = if marked.is_empty() {
+                        vec![relationship.id]
+                    } else {
+                        marked
+                    };
+                    group_dm::create_dm(state, user_ids, tx_action);
+                }
+                _ => {}
+            }
+        }
+        AppAction::InputChar('d') => {
+            let Some(relationship) = selected(state) else {
+                return true;
+            };
+            let name = display_name(&relationship);
+            let done = match relationship.kind {
+                RELATIONSHIP_FRIEND => {
+                    confirm(
+                        state,
+                        format!("Remove {name} from your friends?"),
+                        FormPurpose::RemoveFriend {
+                            user_id: relationship.id,
+                        },
+                        "Yes, remove them",
+                    );
+                    return true;
+                }
+                RELATIONSHIP_INCOMING => format!("Declined the request of {name}."),
+                RELATIONSHIP_OUTGOING => format!("Cancelled the request to {name}."),
+                _ => format!("Unblocked {name}."),
+            };
+            update_relationship(state, tx_action, Change::Remove(relationship.id), done);
+        }
+        AppAction::InputChar('b') => {
+            if let Some(relationship) = selected(state)
+                && relationship.kind != RELATIONSHIP_BLOCKED
+            {
+                confirm(
+                    state,
+                    format!("Block {}?", display_name(&relationship)),
+                    FormPurpose::BlockUser {
+                        user_id: relationship.id,
+                    },
+                    "Yes, block them",
+                );
+            }
+        }
+        AppAction::InputChar(_)
+        | AppAction::InputBackspace
+        | AppAction::InputDelete
+        | AppAction::SelectLeft
+        | AppAction::SelectRight
+        | AppAction::Paste(_) => {}
+        _ => return false,
+    }
+
+    true
+}
+
+/// Sends a confirmed removal or block.
+pub fn submit_form(state: &mut App, form: Form, tx_action: &Sender<AppAction>) {
+    if !form.checked("confirm") {
+        state.status_message = "Nothing changed: tick the confirmation first.".to_string();
+        return;
+    }
+
+    match form.purpose {
+        FormPurpose::RemoveFriend { user_id } => {
+            update_relationship(
+                state,
+                tx_action,
+                Change::Remove(user_id),
+                "Friend removed.".to_string(),
+            );
+        }
+        FormPurpose::BlockUser { user_id } => {
+            update_relationship(
+                state,
+                tx_action,
+                Change::Block(user_id),
+                "User blocked.".to_string(),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Keeps the relationship list current with RELATIONSHIP_ADD/REMOVE.
+pub fn apply_relationship(state: &mut App, relationship: Relationship) {
+    state.relationships.retain(|r| r.id != relationship.id);
+    state.relationships.push(relationship);
+}
+
+pub fn remove_relationship(state: &mut App, user_id: &str) {
+    state.relationships.retain(|r| r.id != user_id);
+    clamp_selection(state);
+}
+
+/// Keeps the selection on a row after the list shrank.
+pub fn clamp_selection(state: &mut App) {
+    if let Some(view) = state.friends_view.as_mut() {
+        view.selected = view
+            .selected
+            .min(state.relationships.len().saturating_sub(1));
+    }
+}
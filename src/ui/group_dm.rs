@@ -0,0 +1,293 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    App, AppAction, AppState,
+    api::{DM, User},
+    logs::{LogType, print_log},
+    ui::{
+        form::{Form, FormField, FormPurpose},
+        profile::find_user,
+    },
+};
+
+async fn report_error(tx_action: &Sender<AppAction>, what: &str, e: crate::Error) {
+    let _ = print_log(format!("Failed to {what}: {e}").into(), LogType::Error);
+    tx_action
+        .send(AppAction::UpdateStatus(format!("Failed to {what}: {e}")))
+        .await
+        .ok();
+}
+
+/// Opens the DM with `user_ids`, reusing a 1:1 DM we already have.
+/// Several users always get a new group DM, like the official client does.
+pub fn create_dm(state: &mut App, user_ids: Vec<String>, tx_action: &Sender<AppAction>) {
+    if let [user_id] = user_ids.as_slice()
+        && let Some(dm) = state
+            .dms
+            .iter()
+            .find(|dm| !dm.is_group() && dm.recipients.iter().any(|u| &u.id == user_id))
+    {
+        tx_action.try_send(AppAction::ApiOpenDM(dm.clone())).ok();
+        return;
+    }
+    if state.offline {
+        state.status_message = "DMs can't be created offline.".to_string();
+        return;
+    }
+
+    state.status_message = if user_ids.len() > 1 {
+        format!("Creating a group DM with {} users...", user_ids.len())
+    } else {
+        "Opening the DM...".to_string()
+    };
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client.create_dm(&user_ids).await {
+            Ok(dm) => {
+                tx_action.send(AppAction::ApiOpenDM(dm)).await.ok();
+            }
+            Err(e) => report_error(&tx_action, "create the DM", e).await,
+        }
+    });
+}
+
+/// `:dm <user> [user...]`
+pub fn open_dm_with(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    if args.is_empty() {
+        state.status_message = "Usage: :dm <user> [user...]".to_string();
+        return;
+    }
+
+    let mut user_ids = Vec::new();
+    for query in args {
+        match find_user(state, query.trim_start_matches('@')) {
+            Some(user) if user_ids.contains(&user.id) => {}
+            Some(user) => user_ids.push(user.id),
+            None => {
+                state.status_message = format!("No user named {query} seen yet.");
+                return;
+            }
+        }
+    }
+    create_dm(state, user_ids, tx_action);
+}
+
+/// The group DM open in the chat view.
+fn open_group(state: &mut App) -> Option<DM> {
+    let group = match &state.state {
+        AppState::Chatting(channel_id, _) => state
+            .dms
+            .iter()
+            .find(|dm| &dm.id == channel_id && dm.is_group())
+            .cloned(),
+        _ => None,
+    };
+    if group.is_none() {
+        state.status_message = "Open a group DM first.".to_string();
+    }
+    group
+}
+
+fn find_recipient(dm: &DM, query: &str) -> Option<User> {
+    let query = query.trim_start_matches('@');
+    dm.recipients
+        .iter()
+        .find(|u| {
+            u.id == query
+                || u.username.eq_ignore_ascii_case(query)
+                || u.global_name
+                    .as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(query))
+        })
+        .cloned()
+}
+
+/// `:gdm add <user>`, `:gdm remove <user>`, `:gdm rename [name]` and `:gdm leave`
+pub fn run_command(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    let Some((subcommand, rest)) = args.split_first() else {
+        state.status_message =
+            "Usage: :gdm add|remove <user>, :gdm rename [name], :gdm leave".to_string();
+        return;
+    };
+    let Some(group) = open_group(state) else {
+        return;
+    };
+    if state.offline {
+        state.status_message = "Group DMs can't be changed offline.".to_string();
+        return;
+    }
+    let query = rest.join(" ");
+
+    match *subcommand {
+        "add" => {
+            let Some(user) = find_user(state, query.trim_start_matches('@')) else {
+                state.status_message = format!("No user named {query} seen yet.");
+                return;
+            };
+            if group.recipients.iter().any(|u| u.id == user.id) {
+                state.status_message = format!("{} is already in this group.", user.username);
+                return;
+            }
+
+            let api_client = state.api_client.clone();
+            let tx_action = tx_action.clone();
+            tokio::spawn(async move {
+                match api_client.add_group_recipient(&group.id, &user.id).await {
+                    Ok(()) => {
+                        let status = format!("Added {} to the group.", user.username);
+                        tx_action.send(AppAction::UpdateStatus(status)).await.ok();
+                    }
+                    Err(e) => report_error(&tx_action, "add the user", e).await,
+                }
+            });
+        }
+        "remove" | "kick" => {
+            let me = state.current_user.as_ref().map(|u| u.id.clone());
+            if group.owner_id.is_some() && group.owner_id != me {
+                state.status_message = "Only the owner of the group can remove people.".to_string();
+                return;
+            }
+            let Some(user) = find_recipient(&group, &query) else {
+                state.status_message = format!("{query} is not in this group.");
+                return;
+            };
+
+            let api_client = state.api_client.clone();
+            let tx_action = tx_action.clone();
+            tokio::spawn(async move {
+                match api_client.remove_group_recipient(&group.id, &user.id).await {
+                    Ok(()) => {
+                        let status = format!("Removed {} from the group.", user.username);
+                        tx_action.send(AppAction::UpdateStatus(status)).await.ok();
+                    }
+                    Err(e) => report_error(&tx_action, "remove the user", e).await,
+                }
+            });
+        }
+        "rename" => {
+            let name = (!query.is_empty()).then_some(query);
+            let api_client = state.api_client.clone();
+            let tx_action = tx_action.clone();
+            tokio::spawn(async move {
+                match api_client.rename_group_dm(&group.id, name.as_deref()).await {
+                    Ok(dm) => {
+                        tx_action.send(AppAction::ApiDMChanged(dm)).await.ok();
+                    }
+                    Err(e) => report_error(&tx_action, "rename the group", e).await,
+                }
+            });
+        }
+        "leave" => {
+            state.form = Some(Form::new(
+                format!("Leave {}?", group.get_name()),
+                FormPurpose::LeaveGroup {
+                    channel_id: group.id,
+                },
+                vec![FormField::checkbox(
+                    "confirm",
+                    "Yes, leave the group",
+                    false,
+                )],
+            ));
+        }
+        _ => {
+            state.status_message = format!("Unknown :gdm subcommand: {subcommand}");
+        }
+    }
+}
+
+/// Leaves the group once confirmed.
+pub fn submit_form(state: &mut App, form: Form, tx_action: &Sender<AppAction>) {
+    let FormPurpose::LeaveGroup { channel_id } = form.purpose.clone() else {
+        return;
+    };
+    if !form.checked("confirm") {
+        state.status_message = "Nothing changed: tick the confirmation first.".to_string();
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client.delete_channel(&channel_id).await {
+            Ok(()) => {
+                tx_action.send(AppAction::ApiDMLeft(channel_id)).await.ok();
+            }
+            Err(e) => report_error(&tx_action, "leave the group", e).await,
+        }
+    });
+}
+
+/// Keeps the chat title in sync when the open DM changes name or recipients.
+fn refresh_title(state: &mut App, channel_id: &str) {
+    let Some(name) = state
+        .dms
+        .iter()
+        .find(|dm| dm.id == channel_id)
+        .map(|dm| dm.get_name())
+    else {
+        return;
+    };
+    if let AppState::Chatting(id, title) = &mut state.state
+        && id == channel_id
+    {
+        *title = name;
+    }
+}
+
+fn save_dms(state: &mut App) {
+    let dms = state.dms.clone();
+    if let Some(store) = state.store.as_mut() {
+        store.save_dms(&dms);
+    }
+}
+
+/// A DM was created or renamed.
+pub fn apply_dm(state: &mut App, dm: DM) {
+    let channel_id = dm.id.clone();
+    for recipient in &dm.recipients {
+        state
+            .user_names
+            .insert(recipient.id.clone(), recipient.username.clone());
+    }
+    match state.dms.iter_mut().find(|d| d.id == dm.id) {
+        Some(existing) => *existing = dm,
+        None => state.dms.insert(0, dm),
+    }
+    save_dms(state);
+    refresh_title(state, &channel_id);
+}
+
+/// We left the DM, or it was deleted.
+pub fn remove_dm(state: &mut App, channel_id: &str) {
+    state.dms.retain(|dm| dm.id != channel_id);
+    state.last_message_ids.remove(channel_id);
+    save_dms(state);
+}
+
+pub fn add_recipient(state: &mut App, channel_id: &str, user: User) {
+    state
+        .user_names
+        .insert(user.id.clone(), user.username.clone());
+    if let Some(dm) = state.dms.iter_mut().find(|dm| dm.id == channel_id)
+        && !dm.recipients.iter().any(|u| u.id == user.id)
+    {
+        dm.recipients.push(user);
+        save_dms(state);
+        refresh_title(state, channel_id);
+    }
+}
+
+pub fn remove_recipient(state: &mut App, channel_id: &str, user_id: &str) {
+    // Someone removing us is the same as leaving
+    if state.current_user.as_ref().is_some_and(|u| u.id == user_id) {
+        remove_dm(state, channel_id);
+        return;
+    }
+    if let Some(dm) = state.dms.iter_mut().find(|dm| dm.id == channel_id) {
+        dm.recipients.retain(|u| u.id != user_id);
+        save_dms(state);
+        refresh_title(state, channel_id);
+    }
+}
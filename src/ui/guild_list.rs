@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    App, AppState,
+    api::{Guild, user::GuildFolder},
+    config::save_config,
+};
+
+/// A line of the server list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuildRow {
+    Folder {
+        key: String,
+        name: String,
+        color: Option<(u8, u8, u8)>,
+        guild_ids: Vec<String>,
+        collapsed: bool,
+    },
+    Guild {
+        id: String,
+        name: String,
+        in_folder: bool,
+        pinned: bool,
+    },
+}
+
+/// A top-level entry of the list, what local reordering moves around.
+enum Entry<'a> {
+    Folder(&'a GuildFolder, Vec<&'a Guild>),
+    Guild(&'a Guild),
+}
+
+fn folder_key(key: &str) -> String {
+    format!("folder:{key}")
+}
+
+/// Folders and guilds outside of them in Discord's order, then the local order on top.
+/// Guilds missing from the settings (just joined, or no settings at all) come first,
+/// as in the official client.
+fn entries(state: &App) -> Vec<(String, Entry<'_>)> {
+    let guilds: HashMap<&str, &Guild> = state.guilds.iter().map(|g| (g.id.as_str(), g)).collect();
+    let pinned: HashSet<&str> = state
+        .config
+        .pinned_guilds
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    for folder in &state.guild_folders {
+        seen.extend(folder.guild_ids.iter().map(String::as_str));
+        let members: Vec<&Guild> = folder
+            .guild_ids
+            .iter()
+            .filter(|id| !pinned.contains(id.as_str()))
+            .filter_map(|id| guilds.get(id.as_str()).copied())
+            .collect();
+
+        match folder.key() {
+            Some(key) if !members.is_empty() => {
+                entries.push((folder_key(&key), Entry::Folder(folder, members)));
+            }
+            _ => entries.extend(members.into_iter().map(|g| (g.id.clone(), Entry::Guild(g)))),
+        }
+    }
+
+    let unsorted: Vec<(String, Entry)> = state
+        .guilds
+        .iter()
+        .filter(|g| !seen.contains(g.id.as_str()) && !pinned.contains(g.id.as_str()))
+        .map(|g| (g.id.clone(), Entry::Guild(g)))
+        .collect();
+    entries.splice(0..0, unsorted);
+
+    let order = &state.config.guild_order;
+    entries.sort_by_key(|(key, _)| order.iter().position(|k| k == key).unwrap_or(usize::MAX));
+    entries
+}
+
+fn folder_name(folder: &GuildFolder, guilds: &[&Guild]) -> String {
+    match folder.name.as_deref().filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => {
+            let names: Vec<&str> = guilds.iter().take(3).map(|g| g.name.as_str()).collect();
+            let more = if guilds.len() > 3 { ", ..." } else { "" };
+            format!("{}{more}", names.join(", "))
+        }
+    }
+}
+
+/// What the server list shows: pinned guilds, then folders and guilds.
+/// Filtering flattens the folders so matches inside collapsed ones show up.
+pub fn guild_rows(state: &App) -> Vec<GuildRow> {
+    guild_rows_matching(state, &state.input)
+}
+
+/// `guild_rows` with another filter than the input line.
+pub fn guild_rows_matching(state: &App, filter: &str) -> Vec<GuildRow> {
+    let pinned = &state.config.pinned_guilds;
+    let filter = filter.to_lowercase();
+    if !filter.is_empty() {
+        let mut matches: Vec<&Guild> = state
+            .guilds
+            .iter()
+            .filter(|g| g.name.to_lowercase().contains(&filter))
+            .collect();
+        matches.sort_by_key(|g| !pinned.contains(&g.id));
+        return matches
+            .into_iter()
+            .map(|g| GuildRow::Guild {
+                id: g.id.clone(),
+                name: g.name.clone(),
+                in_folder: false,
+                pinned: pinned.contains(&g.id),
+            })
+            .collect();
+    }
+
+    let mut rows: Vec<GuildRow> = pinned
+        .iter()
+        .filter_map(|id| state.guilds.iter().find(|g| &g.id == id))
+        .map(|g| GuildRow::Guild {
+            id: g.id.clone(),
+            name: g.name.clone(),
+            in_folder: false,
+            pinned: true,
+        })
+        .collect();
+
+    for (key, entry) in entries(state) {
+        match entry {
+            Entry::Guild(g) => rows.push(GuildRow::Guild {
+                id: g.id.clone(),
+                name: g.name.clone(),
+                in_folder: false,
+                pinned: false,
+            }),
+            Entry::Folder(folder, guilds) => {
+                let collapsed = state.config.collapsed_folders.contains(&key);
+                rows.push(GuildRow::Folder {
+                    key,
+                    name: folder_name(folder, &guilds),
+                    color: folder.rgb(),
+                    guild_ids: guilds.iter().map(|g| g.id.clone()).collect(),
+                    collapsed,
+                });
+                if !collapsed {
+                    rows.extend(guilds.into_iter().map(|g| GuildRow::Guild {
+                        id: g.id.clone(),
+                        name: g.name.clone(),
+                        in_folder: true,
+                        pinned: false,
+                    }));
+                }
+            }
+        }
+    }
+
+    rows
+}
+
+fn in_guild_list(state: &mut App) -> bool {
+    let open = matches!(state.state, AppState::SelectingGuild);
+    if !open {
+        state.status_message = "Open the server list first.".to_string();
+    }
+    open
+}
+
+fn select_row(state: &mut App, row: impl Fn(&GuildRow) -> bool) {
+    if let Some(index) = guild_rows(state).iter().position(row) {
+        state.selection_index = index;
+    }
+}
+
+/// Enter on a folder.
+pub fn toggle_folder(state: &mut App, key: &str) {
+    let collapsed = &mut state.config.collapsed_folders;
+    match collapsed.iter().position(|k| k == key) {
+        Some(i) => {
+            collapsed.remove(i);
+        }
+        None => collapsed.push(key.to_string()),
+    }
+    save_config(&state.config);
+}
+
+/// `:pin` and `:unpin` pin the selected guild to the top of the list or unpin it, `P`
+/// toggles it when `pin` is `None`.
+pub fn set_pin(state: &mut App, pin: Option<bool>) {
+    if !in_guild_list(state) {
+        return;
+    }
+    let Some(GuildRow::Guild { id, name, .. }) =
+        guild_rows(state).get(state.selection_index).cloned()
+    else {
+        state.status_message = "Select a server to pin.".to_string();
+        return;
+    };
+
+    let pinned = &mut state.config.pinned_guilds;
+    let position = pinned.iter().position(|p| *p == id);
+    state.status_message = match (position, pin) {
+        (Some(_), Some(true)) => format!("{name} is already pinned."),
+        (None, Some(false)) => format!("{name} is not pinned."),
+        (Some(i), _) => {
+            pinned.remove(i);
+            save_config(&state.config);
+            format!("Unpinned {name}.")
+        }
+        (None, _) => {
+            pinned.push(id.clone());
+            save_config(&state.config);
+            format!("Pinned {name}.")
+        }
+    };
+    select_row(
+        state,
+        |row| matches!(row, GuildRow::Guild { id: row_id, .. } if *row_id == id),
+    );
+}
+
+/// `J`/`K` or `:moveguild down|up`, moves the selected guild or folder by `delta` places.
+pub fn move_entry(state: &mut App, delta: isize) {
+    if !in_guild_list(state) {
+        return;
+    }
+    if !state.input.is_empty() {
+        state.status_message = "Clear the filter to reorder servers.".to_string();
+        return;
+    }
+    let Some(row) = guild_rows(state).get(state.selection_index).cloned() else {
+        return;
+    };
+
+    let (key, pinned) = match &row {
+        GuildRow::Folder { key, .. } => (key.clone(), false),
+        GuildRow::Guild {
+            in_folder: true, ..
+        } => {
+            state.status_message =
+                "Servers in a folder keep Discord's order, move the folder instead.".to_string();
+            return;
+        }
+        GuildRow::Guild { id, pinned, .. } => (id.clone(), *pinned),
+    };
+
+    let mut order: Vec<String> = if pinned {
+        state.config.pinned_guilds.clone()
+    } else {
+        entries(state).into_iter().map(|(key, _)| key).collect()
+    };
+    let Some(from) = order.iter().position(|k| *k == key) else {
+        return;
+    };
+    let Some(to) = from
+        .checked_add_signed(delta)
+        .filter(|to| *to < order.len())
+    else {
+        return;
+    };
+    order.swap(from, to);
+
+    if pinned {
+        state.config.pinned_guilds = order;
+    } else {
+        state.config.guild_order = order;
+    }
+    save_config(&state.config);
+    select_row(state, |r| *r == row);
+}
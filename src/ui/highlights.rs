@@ -0,0 +1,125 @@
+use regex::Regex;
+
+use crate::{
+    api::Message,
+    config::HighlightRule,
+    logs::{LogType, print_log},
+};
+
+/// A keyword as a case-insensitive regex, bounded on the sides that are word characters,
+/// so `c++` still matches before a space.
+fn keyword_pattern(keyword: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let start = if is_word(keyword.chars().next()) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if is_word(keyword.chars().last()) {
+        r"\b"
+    } else {
+        ""
+    };
+    format!("(?i){start}{}{end}", regex::escape(keyword))
+}
+
+/// The config's highlight rules, compiled once.
+#[derive(Debug, Default)]
+pub struct Highlights {
+    patterns: Vec<Regex>,
+}
+
+impl Highlights {
+    /// Invalid regexes are logged and left out rather than refusing to start.
+    pub fn new(rules: &[HighlightRule]) -> Self {
+        let patterns = rules
+            .iter()
+            .filter(|rule| !rule.pattern.trim().is_empty())
+            .filter_map(|rule| {
+                let pattern = if rule.regex {
+                    rule.pattern.clone()
+                } else {
+                    keyword_pattern(rule.pattern.trim())
+                };
+                match Regex::new(&pattern) {
+                    Ok(regex) => Some(regex),
+                    Err(e) => {
+                        let _ = print_log(
+                            format!("Invalid highlight rule {:?}: {e}", rule.pattern).into(),
+                            LogType::Error,
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Self { patterns }
+    }
+
+    /// Byte ranges of every keyword in `text`, sorted and without overlaps.
+    pub fn find(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = self
+            .patterns
+            .iter()
+            .flat_map(|regex| regex.find_iter(text))
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        ranges.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Whether a message's text hits any keyword.
+    pub fn matches(&self, msg: &Message) -> bool {
+        msg.content
+            .as_deref()
+            .is_some_and(|content| self.patterns.iter().any(|regex| regex.is_match(content)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, regex: bool) -> HighlightRule {
+        HighlightRule {
+            pattern: pattern.to_string(),
+            regex,
+        }
+    }
+
+    #[test]
+    fn keywords_match_whole_words_in_any_case() {
+        let highlights = Highlights::new(&[rule("deploy", false)]);
+        assert_eq!(highlights.find("Deploy done, redeploy later"), vec![(0, 6)]);
+    }
+
+    #[test]
+    fn keywords_are_not_regexes() {
+        let highlights = Highlights::new(&[rule("c++", false)]);
+        assert!(highlights.find("cc+").is_empty());
+        assert_eq!(highlights.find("I like C++ too"), vec![(7, 10)]);
+    }
+
+    #[test]
+    fn regex_rules_and_overlaps_merge() {
+        let highlights =
+            Highlights::new(&[rule("build (failed|broke)", true), rule("failed", false)]);
+        assert_eq!(highlights.find("the build failed"), vec![(4, 16)]);
+    }
+
+    #[test]
+    fn invalid_regexes_are_skipped() {
+        let highlights = Highlights::new(&[rule("(", true), rule("ok", false)]);
+        assert_eq!(highlights.find("ok"), vec![(0, 2)]);
+    }
+}
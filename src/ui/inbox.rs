@@ -0,0 +1,408 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    api::Message,
+    logs::{LogType, print_log},
+    ui::{
+        events::{
+            HistoryGoal, cached_channel, fetch_guild, load_cached_guild, load_history_until,
+            open_chat,
+        },
+        notifications::{self, channel_settings},
+        unread::{self, id_value, mentions_me},
+    },
+};
+
+/// Entries kept in the inbox, older ones fall off.
+const INBOX_LIMIT: usize = 200;
+/// Mentions fetched from Discord when opening the inbox, the most it allows.
+const MENTIONS_PAGE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InboxKind {
+    Mention,
+    DirectMessage,
+    Keyword,
+}
+
+impl InboxKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            InboxKind::Mention => "mention",
+            InboxKind::DirectMessage => "DM",
+            InboxKind::Keyword => "keyword",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InboxEntry {
+    pub message: Message,
+    pub kind: InboxKind,
+    /// "Guild › #channel" or the DM's name, resolved once when the entry comes in
+    pub location: String,
+}
+
+#[derive(Debug, Default)]
+pub struct InboxView {
+    pub selected: usize,
+}
+
+/// A message opened from the inbox, selected once its channel finished loading.
+#[derive(Debug, Clone)]
+pub struct PendingJump {
+    channel_id: String,
+    message_id: String,
+    loaded: bool,
+}
+
+fn location(state: &App, msg: &Message) -> String {
+    if let Some(dm) = state.dms.iter().find(|dm| dm.id == msg.channel_id) {
+        return dm.get_name();
+    }
+
+    let channel = cached_channel(state, &msg.channel_id);
+    let guild = msg
+        .guild_id
+        .clone()
+        .or_else(|| unread::guild_of(state, &msg.channel_id))
+        .or_else(|| channel.as_ref().and_then(|c| c.guild_id.clone()))
+        .and_then(|guild_id| state.guilds.iter().find(|g| g.id == guild_id))
+        .map(|g| g.name.clone());
+
+    match (guild, channel) {
+        (Some(guild), Some(channel)) => format!("{guild} › #{}", channel.name),
+        (None, Some(channel)) => format!("#{}", channel.name),
+        (Some(guild), None) => guild,
+        (None, None) => "Unknown channel".to_string(),
+    }
+}
+
+/// What a message from someone else is to the inbox, if anything.
+fn kind_of(state: &App, msg: &Message) -> Option<InboxKind> {
+    let is_self = state
+        .current_user
+        .as_ref()
+        .is_some_and(|u| u.id == msg.author.id);
+    if is_self || channel_settings(state, &msg.channel_id, msg.guild_id.as_deref()).muted {
+        return None;
+    }
+
+    if state.dms.iter().any(|dm| dm.id == msg.channel_id) {
+        Some(InboxKind::DirectMessage)
+    } else if mentions_me(state, msg) {
+        Some(InboxKind::Mention)
+    } else if state.highlights.matches(msg) {
+        Some(InboxKind::Keyword)
+    } else {
+        None
+    }
+}
+
+fn insert(state: &mut App, entry: InboxEntry) {
+    if state.inbox.iter().any(|e| e.message.id == entry.message.id) {
+        return;
+    }
+    let at = state
+        .inbox
+        .iter()
+        .position(|e| id_value(&e.message.id) < id_value(&entry.message.id))
+        .unwrap_or(state.inbox.len());
+    state.inbox.insert(at, entry);
+    state.inbox.truncate(INBOX_LIMIT);
+}
+
+/// MESSAGE_CREATE outside of the open channel: keeps mentions, DMs and keyword hits.
+pub fn record_message(state: &mut App, msg: &Message) {
+    if let Some(kind) = kind_of(state, msg) {
+        let entry = InboxEntry {
+            message: msg.clone(),
+            kind,
+            location: location(state, msg),
+        };
+        insert(state, entry);
+    }
+}
+
+/// Discord's recent mentions, merged with what came in live.
+pub fn apply_mentions(state: &mut App, messages: Vec<Message>) {
+    for message in messages {
+        let kind = if state.dms.iter().any(|dm| dm.id == message.channel_id) {
+            InboxKind::DirectMessage
+        } else {
+            InboxKind::Mention
+        };
+        let entry = InboxEntry {
+            location: location(state, &message),
+            message,
+            kind,
+        };
+        insert(state, entry);
+    }
+    clamp_selection(state);
+}
+
+pub fn is_unread(state: &App, entry: &InboxEntry) -> bool {
+    !unread::is_read(state, &entry.message.channel_id, &entry.message.id)
+}
+
+/// Entries not read yet, for the Home menu.
+pub fn unread_count(state: &App) -> usize {
+    state
+        .inbox
+        .iter()
+        .filter(|entry| is_unread(state, entry))
+        .count()
+}
+
+/// `:inbox` or the Home menu, also fetches Discord's recent mentions.
+pub fn open_inbox(state: &mut App, tx_action: &Sender<AppAction>) {
+    state.inbox_view = Some(InboxView::default());
+    state.status_message =
+        "Enter opens the message, r marks it read, d removes it, Esc closes.".to_string();
+    if state.offline {
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client.get_mentions(MENTIONS_PAGE).await {
+            Ok(messages) => {
+                tx_action
+                    .send(AppAction::ApiUpdateMentions(messages))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to load recent mentions: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+    });
+}
+
+pub fn clamp_selection(state: &mut App) {
+    let last = state.inbox.len().saturating_sub(1);
+    if let Some(view) = state.inbox_view.as_mut() {
+        view.selected = view.selected.min(last);
+    }
+}
+
+fn selected(state: &App) -> Option<InboxEntry> {
+    let view = state.inbox_view.as_ref()?;
+    state.inbox.get(view.selected).cloned()
+}
+
+/// Marks the entry's channel read up to its message, unless we read further already,
+/// and takes back its notification.
+fn mark_read(state: &mut App, entry: &InboxEntry) {
+    if is_unread(state, entry) {
+        unread::ack(state, &entry.message.channel_id, &entry.message.id);
+    }
+    notifications::channel_read(state, &entry.message.channel_id);
+}
+
+/// Switches to the entry's guild, the channel list and permissions are needed to chat in it.
+/// The cached ones are shown right away, the fresh ones arrive like when picking the guild.
+fn enter_guild(state: &mut App, tx_action: &Sender<AppAction>, guild_id: &str) {
+    if state
+        .context
+        .as_ref()
+        .is_some_and(|context| context.everyone_role_id == guild_id)
+    {
+        return;
+    }
+
+    if !load_cached_guild(state, guild_id) {
+        // Not the previous guild's either
+        state.channels.clear();
+        state.context = None;
+    }
+    if !state.offline {
+        fetch_guild(state, tx_action, guild_id.to_string(), false);
+    }
+}
+
+/// Gets the entry ready to be opened and its message selected there. Returns the channel to
+/// open, or `None` when its guild has to be looked up first.
+fn open_entry(state: &mut App, tx_action: &Sender<AppAction>, entry: InboxEntry) -> Option<String> {
+    let message = entry.message;
+    let is_dm = state.dms.iter().any(|dm| dm.id == message.channel_id);
+    let guild_id = message
+        .guild_id
+        .clone()
+        .or_else(|| unread::guild_of(state, &message.channel_id));
+    if guild_id.is_some() || is_dm || state.offline {
+        return Some(prepare_jump(state, tx_action, message, guild_id));
+    }
+
+    // Which guild the channel is in decides what to load, ask Discord outside of the lock
+    state.status_message = "Opening the channel...".to_string();
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client.get_channel(&message.channel_id).await {
+            Ok(channel) => {
+                tx_action
+                    .send(AppAction::ApiInboxGuild(message, channel.guild_id))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                tx_action
+                    .send(AppAction::UpdateStatus(format!(
+                        "Failed to open the channel: {e}"
+                    )))
+                    .await
+                    .ok();
+            }
+        }
+    });
+    None
+}
+
+/// Switches to the guild of `message`, if any, and remembers to select the message once its
+/// channel is open. Returns the channel to open.
+fn prepare_jump(
+    state: &mut App,
+    tx_action: &Sender<AppAction>,
+    message: Message,
+    guild_id: Option<String>,
+) -> String {
+    if let Some(guild_id) = guild_id {
+        enter_guild(state, tx_action, &guild_id);
+    }
+
+    state.inbox_view = None;
+    state.inbox_jump = Some(PendingJump {
+        channel_id: message.channel_id.clone(),
+        message_id: message.id,
+        loaded: state.offline,
+    });
+    message.channel_id
+}
+
+/// The guild of an inbox message's channel came back, open it. Loading the channel acks it,
+/// which marks the entry read.
+pub async fn open_message(
+    state: &mut MutexGuard<'_, App>,
+    tx_action: &Sender<AppAction>,
+    message: Message,
+    guild_id: Option<String>,
+) {
+    let channel_id = prepare_jump(state, tx_action, message, guild_id);
+    open_chat(state, tx_action, channel_id).await;
+}
+
+/// Called when the messages of `channel_id` arrived.
+pub async fn messages_loaded(
+    state: &mut MutexGuard<'_, App>,
+    channel_id: &str,
+    tx_action: &Sender<AppAction>,
+) {
+    if let Some(jump) = state.inbox_jump.as_mut()
+        && jump.channel_id == channel_id
+    {
+        jump.loaded = true;
+    }
+    finish_jump(state, tx_action).await;
+}
+
+/// Selects the message opened from the inbox once its channel is on screen and loaded,
+/// digging through older history when it is not among the latest messages.
+pub async fn finish_jump(state: &mut MutexGuard<'_, App>, tx_action: &Sender<AppAction>) {
+    let Some(jump) = state.inbox_jump.clone() else {
+        return;
+    };
+    let in_channel =
+        matches!(&state.state, AppState::Chatting(channel_id, _) if *channel_id == jump.channel_id);
+    if !jump.loaded || !in_channel {
+        return;
+    }
+    state.inbox_jump = None;
+
+    load_history_until(
+        state,
+        tx_action,
+        &jump.channel_id,
+        &jump.message_id,
+        HistoryGoal::InboxMessage(jump.message_id.clone()),
+    )
+    .await;
+}
+
+/// History was loaded back to the message opened from the inbox.
+pub fn history_loaded(state: &mut MutexGuard<'_, App>, message_id: &str) {
+    match state.messages.iter().position(|m| m.id == message_id) {
+        Some(index) => state.selection_index = index + 1,
+        None => state.status_message = "That message is not there anymore.".to_string(),
+    }
+}
+
+/// Routes input actions to the open inbox.
+/// Returns `true` when the action was consumed by the view.
+pub async fn handle_inbox_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let last = state.inbox.len().saturating_sub(1);
+    let Some(view) = state.inbox_view.as_mut() else {
+        return false;
+    };
+
+    match action {
+        AppAction::InputEscape | AppAction::InputChar('q') => {
+            state.inbox_view = None;
+        }
+        AppAction::SelectNext | AppAction::InputChar('j') => {
+            view.selected = (view.selected + 1).min(last);
+        }
+        AppAction::SelectPrevious | AppAction::InputChar('k') => {
+            view.selected = view.selected.saturating_sub(1);
+        }
+        AppAction::InputSubmit => {
+            if let Some(channel_id) =
+                selected(state).and_then(|entry| open_entry(state, tx_action, entry))
+            {
+                open_chat(state, tx_action, channel_id).await;
+            }
+        }
+        AppAction::InputChar('r') => {
+            if let Some(entry) = selected(state) {
+                mark_read(state, &entry);
+            }
+        }
+        AppAction::InputChar('R') => {
+            let entries = state.inbox.clone();
+            for entry in &entries {
+                mark_read(state, entry);
+            }
+            state.status_message = "Marked the whole inbox read.".to_string();
+        }
+        AppAction::InputChar('d') => {
+            let Some(entry) = selected(state) else {
+                return true;
+            };
+            state.inbox.retain(|e| e.message.id != entry.message.id);
+            clamp_selection(state);
+            if entry.kind == InboxKind::Mention && !state.offline {
+                let api_client = state.api_client.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = api_client.delete_mention(&entry.message.id).await {
+                        let _ = print_log(
+                            format!("Failed to remove the mention: {e}").into(),
+                            LogType::Error,
+                        );
+                    }
+                });
+            }
+        }
+        _ => {}
+    }
+    true
+}
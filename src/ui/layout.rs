@@ -0,0 +1,219 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    ui::{
+        events::{cached_channel, channel_list},
+        guild_list::{GuildRow, guild_rows},
+        windows,
+    },
+};
+
+/// The server column, channel tree and chat drawn side by side. The focused pane is
+/// whatever the app state shows, the others keep showing where we were.
+#[derive(Debug, Clone, Default)]
+pub struct PaneLayout {
+    /// Whether the last frame was drawn as panes
+    pub active: bool,
+    /// `Ctrl-w` was pressed, the next key picks a pane
+    pub pending: bool,
+    /// Guild whose channels the tree shows, the DM list when `None`
+    pub guild_id: Option<String>,
+    /// Channel id and name of the chat pane
+    pub chat: Option<(String, String)>,
+}
+
+/// Whether the DM list or a guild's channels hold `channel_id`.
+fn is_dm(state: &App, channel_id: &str) -> bool {
+    state.dms.iter().any(|dm| dm.id == channel_id)
+}
+
+/// The chat went to another channel: the tree follows it to its guild or the DMs.
+pub fn chat_opened(state: &mut App, channel_id: &str, channel_name: &str) {
+    state.panes.chat = Some((channel_id.to_string(), channel_name.to_string()));
+    if is_dm(state, channel_id) {
+        state.panes.guild_id = None;
+    } else if let Some(guild_id) = cached_channel(state, channel_id).and_then(|c| c.guild_id) {
+        state.panes.guild_id = Some(guild_id);
+    }
+}
+
+/// A DM we left or a deleted channel no longer stays in the chat pane.
+pub fn channel_gone(state: &mut App, channel_id: &str) {
+    if state
+        .panes
+        .chat
+        .as_ref()
+        .is_some_and(|(id, _)| id == channel_id)
+    {
+        state.panes.chat = None;
+    }
+}
+
+/// The channel whose messages are on screen, focused or not.
+pub fn visible_chat(state: &App) -> Option<&str> {
+    match &state.state {
+        AppState::Chatting(channel_id, _) => Some(channel_id),
+        _ if state.panes.active => state.panes.chat.as_ref().map(|(id, _)| id.as_str()),
+        _ => None,
+    }
+}
+
+/// With panes, a list that gets the focus back selects the guild, channel or DM the
+/// other panes show rather than its first entry.
+pub fn select_current(state: &mut App) {
+    if !state.panes.active {
+        return;
+    }
+    let chat_id = state.panes.chat.as_ref().map(|(id, _)| id.as_str());
+    let index = match &state.state {
+        AppState::SelectingGuild => guild_rows(state).iter().position(|row| {
+            matches!(row, GuildRow::Guild { id, .. } if Some(id) == state.panes.guild_id.as_ref())
+        }),
+        AppState::SelectingChannel(_, _) => channel_list(state)
+            .iter()
+            .position(|c| Some(c.id.as_str()) == chat_id),
+        AppState::SelectingDM => state
+            .dms
+            .iter()
+            .position(|dm| Some(dm.id.as_str()) == chat_id),
+        _ => None,
+    };
+    if let Some(index) = index {
+        state.selection_index = index;
+    }
+}
+
+/// Moves the focus one pane to the left: chat, then channels or DMs, then servers.
+pub async fn focus_left(state: &mut App, tx_action: &Sender<AppAction>) {
+    let action = match &state.state {
+        AppState::Chatting(channel_id, _) => {
+            match cached_channel(state, channel_id).and_then(|c| c.guild_id) {
+                Some(guild_id) if !is_dm(state, channel_id) => {
+                    AppAction::TransitionToChannels(guild_id)
+                }
+                _ => AppAction::TransitionToDM,
+            }
+        }
+        AppState::SelectingChannel(_, _) | AppState::SelectingDM => AppAction::TransitionToGuilds,
+        _ => return,
+    };
+    tx_action.send(action).await.ok();
+}
+
+/// Moves the focus one pane to the right, back to what that pane was showing.
+async fn focus_right(state: &mut App, tx_action: &Sender<AppAction>) {
+    let action = match (&state.state, &state.panes.chat) {
+        (AppState::SelectingGuild, _) => match &state.panes.guild_id {
+            Some(guild_id) => AppAction::TransitionToChannels(guild_id.clone()),
+            None => AppAction::TransitionToDM,
+        },
+        (AppState::SelectingChannel(_, _) | AppState::SelectingDM, Some((channel_id, _))) => {
+            AppAction::TransitionToChat(channel_id.clone())
+        }
+        _ => return,
+    };
+    tx_action.send(action).await.ok();
+}
+
+/// The key after `Ctrl-w`: `h`/`l` (or the arrows) move the focus between panes.
+/// Works in single-screen mode too, where it switches screens the same way. Split
+/// windows get the key first.
+pub async fn handle_layout_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    if !state.panes.pending {
+        return false;
+    }
+
+    if windows::handle_window_key(state, action, tx_action).await {
+        state.panes.pending = false;
+        return true;
+    }
+    match action {
+        AppAction::InputChar('h') | AppAction::SelectLeft => focus_left(state, tx_action).await,
+        AppAction::InputChar('l') | AppAction::SelectRight => focus_right(state, tx_action).await,
+        AppAction::InputChar(_) | AppAction::InputEscape => {}
+        // Ticks and gateway events don't cancel the pending command
+        _ => return false,
+    }
+    state.panes.pending = false;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{self, DM_CHANNEL, FRIEND, dispatch, dispatch_sent, dm, message};
+
+    fn chatting_in_dm() -> App {
+        let mut app = testing::app();
+        app.dms = vec![dm("21"), dm(DM_CHANNEL)];
+        app.state = AppState::Chatting(DM_CHANNEL.to_string(), "user2".to_string());
+        app.panes.chat = Some((DM_CHANNEL.to_string(), "user2".to_string()));
+        app
+    }
+
+    #[tokio::test]
+    async fn ctrl_w_h_leaves_a_dm_for_the_dm_list() {
+        let (app, sent) = dispatch_sent(
+            chatting_in_dm(),
+            vec![AppAction::WindowCommand, AppAction::InputChar('h')],
+        )
+        .await;
+        assert!(matches!(sent.as_slice(), [AppAction::TransitionToDM]));
+        assert!(!app.panes.pending);
+    }
+
+    #[tokio::test]
+    async fn ctrl_w_l_goes_back_to_the_chat_pane() {
+        let mut app = chatting_in_dm();
+        app.state = AppState::SelectingDM;
+        let (_, sent) = dispatch_sent(
+            app,
+            vec![
+                AppAction::WindowCommand,
+                AppAction::Tick,
+                AppAction::InputChar('l'),
+            ],
+        )
+        .await;
+        assert!(matches!(sent.as_slice(), [AppAction::TransitionToChat(id)] if id == DM_CHANNEL));
+    }
+
+    #[tokio::test]
+    async fn lists_getting_the_focus_select_the_open_chat() {
+        let mut app = chatting_in_dm();
+        app.panes.active = true;
+        let app = dispatch(app, vec![AppAction::TransitionToDM]).await;
+        assert_eq!(app.selection_index, 1);
+    }
+
+    #[tokio::test]
+    async fn opening_a_dm_points_the_tree_at_the_dms() {
+        let mut app = chatting_in_dm();
+        app.panes.guild_id = Some(testing::GUILD.to_string());
+        app.panes.chat = None;
+        let app = dispatch(
+            app,
+            vec![AppAction::TransitionToChat(DM_CHANNEL.to_string())],
+        )
+        .await;
+        assert_eq!(app.panes.guild_id, None);
+        assert_eq!(
+            app.panes.chat.map(|(id, _)| id),
+            Some(DM_CHANNEL.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn the_unfocused_chat_pane_stays_live() {
+        let mut app = chatting_in_dm();
+        app.state = AppState::SelectingDM;
+        app.panes.active = true;
+        let app = testing::receive(app, message("100", DM_CHANNEL, None, FRIEND, "hi")).await;
+        assert_eq!(app.messages.len(), 1);
+    }
+}
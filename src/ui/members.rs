@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, HashSet};
+
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    App, AppAction, AppState,
+    api::User,
+    logs::{LogType, print_log},
+    ui::events::current_guild_id,
+};
+
+/// How many members to ask for when the guild member list is available to us.
+const MEMBER_LIST_LIMIT: u16 = 1000;
+
+/// The right-hand member list of the chat view.
+#[derive(Debug, Clone, Default)]
+pub struct MemberList {
+    pub visible: bool,
+    pub filter: String,
+    /// Guilds whose member list was already requested
+    fetched: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberEntry {
+    pub name: String,
+    pub status: String,
+    pub color: Option<(u8, u8, u8)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberGroup {
+    pub name: String,
+    pub members: Vec<MemberEntry>,
+}
+
+/// Sort rank of a presence, online first.
+fn status_rank(status: &str) -> u8 {
+    match status {
+        "online" => 0,
+        "idle" => 1,
+        "dnd" => 2,
+        _ => 3,
+    }
+}
+
+fn status_of(state: &App, user_id: &str) -> String {
+    match state.user_statuses.get(user_id) {
+        Some(status) if status != "invisible" => status.clone(),
+        // We are connected, so at least online to ourselves
+        _ if state.current_user.as_ref().is_some_and(|u| u.id == user_id) => "online".to_string(),
+        _ => "offline".to_string(),
+    }
+}
+
+fn matches_filter(filter: &str, names: &[Option<&str>]) -> bool {
+    filter.is_empty()
+        || names
+            .iter()
+            .flatten()
+            .any(|name| name.to_lowercase().contains(&filter.to_lowercase()))
+}
+
+fn display_name(user: &User) -> String {
+    user.global_name.clone().unwrap_or(user.username.clone())
+}
+
+/// Members of the open guild (or DM) as Discord groups them: online members under their
+/// highest hoisted role, or "Online" without one, then everyone offline.
+pub fn member_groups(state: &App) -> Vec<MemberGroup> {
+    let (AppState::Chatting(channel_id, _)
+    | AppState::EmojiSelection(channel_id, _)
+    | AppState::Editing(channel_id, _, _, _)) = &state.state
+    else {
+        return Vec::new();
+    };
+    let filter = state.member_list.filter.as_str();
+
+    // Group key: hoisted roles by position, then online, then offline
+    let mut groups: BTreeMap<(u8, std::cmp::Reverse<i64>, String), Vec<MemberEntry>> =
+        BTreeMap::new();
+
+    if let Some(dm) = state.dms.iter().find(|dm| &dm.id == channel_id) {
+        for user in dm.recipients.iter().chain(state.current_user.as_ref()) {
+            if !matches_filter(filter, &[Some(&user.username), user.global_name.as_deref()]) {
+                continue;
+            }
+            let status = status_of(state, &user.id);
+            let key = if status == "offline" {
+                (2, std::cmp::Reverse(0), "Offline".to_string())
+            } else {
+                (1, std::cmp::Reverse(0), "Online".to_string())
+            };
+            groups.entry(key).or_default().push(MemberEntry {
+                name: display_name(user),
+                status,
+                color: None,
+            });
+        }
+    } else if state.channel_permissions.is_some()
+        && let Some(context) = state.context.as_ref()
+        && let Some(members) = state.members.get(&context.everyone_role_id)
+    {
+        for member in members.values() {
+            if !matches_filter(
+                filter,
+                &[
+                    Some(&member.user.username),
+                    member.user.global_name.as_deref(),
+                    member.nick.as_deref(),
+                ],
+            ) {
+                continue;
+            }
+
+            let status = status_of(state, &member.user.id);
+            let roles = context.roles_of(&member.roles);
+            let key = match roles.iter().find(|r| r.hoist) {
+                _ if status == "offline" => (2, std::cmp::Reverse(0), "Offline".to_string()),
+                Some(role) => (0, std::cmp::Reverse(role.position), role.name.clone()),
+                None => (1, std::cmp::Reverse(0), "Online".to_string()),
+            };
+
+            groups.entry(key).or_default().push(MemberEntry {
+                name: member
+                    .nick
+                    .clone()
+                    .unwrap_or_else(|| display_name(&member.user)),
+                status,
+                color: roles.iter().find_map(|r| r.rgb()),
+            });
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((_, _, name), mut members)| {
+            members.sort_by_key(|m| (status_rank(&m.status), m.name.to_lowercase()));
+            MemberGroup { name, members }
+        })
+        .collect()
+}
+
+/// Asks Discord for the whole member list of the current guild, once per guild.
+/// Most accounts are not allowed to list members, the list then grows from the
+/// lazily fetched authors of the messages we see.
+pub fn request_member_list(state: &mut App, tx_action: &Sender<AppAction>) {
+    if !state.member_list.visible || state.offline || state.channel_permissions.is_none() {
+        return;
+    }
+    let Some(guild_id) = current_guild_id(state) else {
+        return;
+    };
+    if !state.member_list.fetched.insert(guild_id.clone()) {
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client
+            .list_guild_members(&guild_id, MEMBER_LIST_LIMIT)
+            .await
+        {
+            Ok(members) => {
+                tx_action
+                    .send(AppAction::ApiUpdateMembers(guild_id, members))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                let _ = print_log(
+                    format!("Member list unavailable, using the member cache: {e}").into(),
+                    LogType::Error,
+                );
+            }
+        }
+    });
+}
+
+/// `M` or `:members [filter]`
+pub fn toggle(state: &mut App, filter: Option<String>, tx_action: &Sender<AppAction>) {
+    match filter {
+        Some(filter) => {
+            state.member_list.filter = filter;
+            state.member_list.visible = true;
+        }
+        None => state.member_list.visible = !state.member_list.visible,
+    }
+
+    request_member_list(state, tx_action);
+}
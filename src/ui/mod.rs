@@ -1,6 +1,30 @@
+pub mod audit_log;
+pub mod buffers;
+pub mod channel_admin;
+pub mod command;
 pub mod draw;
 pub mod events;
+pub mod explain;
+pub mod form;
+pub mod friends;
+pub mod group_dm;
+pub mod guild_list;
+pub mod highlights;
+pub mod inbox;
+pub mod layout;
+pub mod members;
+pub mod moderation;
+pub mod notifications;
+pub mod popup;
+pub mod profile;
+pub mod prompt;
+pub mod roles;
+pub mod search;
+#[cfg(test)]
+pub mod testing;
+pub mod unread;
 pub mod vim;
+pub mod windows;
 
 pub use draw::draw_ui;
 pub use events::{handle_input_events, handle_keys_events};
@@ -0,0 +1,562 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    App, AppAction, AppState, Error,
+    api::{ApiClient, permissions::Permissions, snowflake_timestamp_ms},
+    logs::{LogType, print_log},
+    ui::{
+        buffers::current_chat,
+        events::{OFFLINE_READ_ONLY, can_delete_message, is_own_message},
+        prompt::{Prompt, PromptKind},
+    },
+};
+
+/// Discord refuses to delete more than a week of messages when banning.
+const MAX_BAN_DELETE_SECONDS: u64 = 7 * 24 * 60 * 60;
+/// Timeouts can last at most 28 days.
+const MAX_TIMEOUT_SECONDS: u64 = 28 * 24 * 60 * 60;
+/// Bulk deletion rejects messages older than two weeks, keep a minute of margin.
+const BULK_DELETE_MAX_AGE_MS: i64 = 14 * 24 * 60 * 60 * 1000 - 60 * 1000;
+const BULK_DELETE_MAX_MESSAGES: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModerationAction {
+    Kick,
+    /// Also deletes the member's messages from the last `delete_message_seconds`
+    Ban {
+        delete_message_seconds: u64,
+    },
+    Unban,
+    /// `None` lifts an active timeout
+    Timeout {
+        seconds: Option<u64>,
+    },
+}
+
+impl ModerationAction {
+    fn permission(&self) -> Permissions {
+        match self {
+            Self::Kick => Permissions::KICK_MEMBERS,
+            Self::Ban { .. } | Self::Unban => Permissions::BAN_MEMBERS,
+            Self::Timeout { .. } => Permissions::MODERATE_MEMBERS,
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            Self::Kick => "kick",
+            Self::Ban { .. } => "ban",
+            Self::Unban => "unban",
+            Self::Timeout { seconds: Some(_) } => "time out",
+            Self::Timeout { seconds: None } => "lift the timeout of",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Kick => "Kicked",
+            Self::Ban { .. } => "Banned",
+            Self::Unban => "Unbanned",
+            Self::Timeout { seconds: Some(_) } => "Timed out",
+            Self::Timeout { seconds: None } => "Lifted the timeout of",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationTarget {
+    pub guild_id: String,
+    pub user_id: String,
+    pub name: String,
+}
+
+/// Parses durations like `30s`, `10m`, `2h`, `1d` or `1w` into seconds. Bare numbers are seconds.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let (value, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let value: u64 = value.parse().ok()?;
+
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    value.checked_mul(multiplier)
+}
+
+pub fn parse_ban_window(text: &str) -> Option<u64> {
+    parse_duration(text).filter(|&s| s <= MAX_BAN_DELETE_SECONDS)
+}
+
+pub fn parse_timeout(text: &str) -> Option<u64> {
+    parse_duration(text).filter(|&s| s > 0 && s <= MAX_TIMEOUT_SECONDS)
+}
+
+/// Checks we may moderate the author of the selected message (or `user_id`) and asks for a reason.
+pub fn start_moderation(state: &mut App, action: ModerationAction, user_id: Option<String>) {
+    let verb = action.verb();
+
+    if state.offline {
+        state.status_message = OFFLINE_READ_ONLY.to_string();
+        return;
+    }
+    let Some(context) = state.context.as_ref() else {
+        state.status_message = "Permissions are still loading.".to_string();
+        return;
+    };
+    if !matches!(state.state, AppState::Chatting(_, _)) || state.channel_permissions.is_none() {
+        state.status_message = "Moderation only works from a server channel.".to_string();
+        return;
+    }
+    if !context.has_guild_permission(action.permission()) {
+        let name = action
+            .permission()
+            .iter_names()
+            .next()
+            .map(|(name, _)| name)
+            .unwrap_or_default();
+        state.status_message = format!("You need {name} to {verb} members.");
+        return;
+    }
+
+    let selected_author = state
+        .selection_index
+        .checked_sub(1)
+        .and_then(|i| state.messages.get(i))
+        .map(|m| &m.author);
+
+    let target = match (user_id, selected_author) {
+        (Some(user_id), _) => ModerationTarget {
+            guild_id: context.everyone_role_id.clone(),
+            name: state
+                .user_names
+                .get(&user_id)
+                .cloned()
+                .unwrap_or(user_id.clone()),
+            user_id,
+        },
+        (None, Some(author)) => ModerationTarget {
+            guild_id: context.everyone_role_id.clone(),
+            user_id: author.id.clone(),
+            name: author
+                .global_name
+                .clone()
+                .unwrap_or(author.username.clone()),
+        },
+        (None, None) => {
+            state.status_message = format!("Select a message to choose who to {verb}.");
+            return;
+        }
+    };
+
+    if target.user_id == context.user_id {
+        state.status_message = format!("You can't {verb} yourself.");
+        return;
+    }
+
+    state.prompt = Some(Prompt::new(PromptKind::Reason(action, target)));
+}
+
+pub fn reason_prompt(action: &ModerationAction, target: &ModerationTarget) -> String {
+    format!("Reason to {} {} (optional): ", action.verb(), target.name)
+}
+
+pub fn delete_reason_prompt(count: usize) -> String {
+    match count {
+        1 => "Reason to delete this message (optional): ".to_string(),
+        _ => format!("Reason to delete {count} messages (optional): "),
+    }
+}
+
+/// Sends a confirmed moderation action, reporting the outcome in the status line.
+pub fn run_moderation(
+    state: &mut App,
+    action: ModerationAction,
+    target: ModerationTarget,
+    reason: String,
+    tx_action: &Sender<AppAction>,
+) {
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    let verb = action.verb();
+    let reason = Some(reason.trim().to_string()).filter(|r| !r.is_empty());
+
+    state.status_message = format!("Trying to {verb} {}...", target.name);
+
+    tokio::spawn(async move {
+        let (guild_id, user_id, reason) = (&target.guild_id, &target.user_id, reason.as_deref());
+
+        let result = match &action {
+            ModerationAction::Kick => api_client.kick_member(guild_id, user_id, reason).await,
+            ModerationAction::Ban {
+                delete_message_seconds,
+            } => {
+                api_client
+                    .ban_member(guild_id, user_id, *delete_message_seconds, reason)
+                    .await
+            }
+            ModerationAction::Unban => api_client.unban_member(guild_id, user_id, reason).await,
+            ModerationAction::Timeout { seconds } => {
+                let until = seconds.map(|s| {
+                    (chrono::Utc::now() + chrono::Duration::seconds(s as i64)).to_rfc3339()
+                });
+                api_client
+                    .timeout_member(guild_id, user_id, until, reason)
+                    .await
+            }
+        };
+
+        let status = match result {
+            Ok(()) => format!("{} {}.", action.past_tense(), target.name),
+            Err(e) => {
+                let _ = print_log(
+                    format!("Failed to {verb} {}: {e}", target.user_id).into(),
+                    LogType::Error,
+                );
+                format!("Failed to {verb} {}: {e}", target.name)
+            }
+        };
+
+        tx_action.send(AppAction::UpdateStatus(status)).await.ok();
+    });
+}
+
+/// Message indexes covered by the visual selection, or `None` outside of visual mode.
+pub fn visual_range(state: &App) -> Option<std::ops::RangeInclusive<usize>> {
+    let anchor_id = state.visual_anchor.as_ref()?;
+    let anchor = state.messages.iter().position(|m| m.id == *anchor_id)? + 1;
+    let (low, high) = if anchor < state.selection_index {
+        (anchor, state.selection_index)
+    } else {
+        (state.selection_index, anchor)
+    };
+
+    // Selection index 0 is the input line, not a message
+    Some(low.max(1) - 1..=high.saturating_sub(1))
+}
+
+/// `dd`: deletes the selected message, asking for an audit log reason first when it is
+/// someone else's, as a visual selection does.
+pub async fn delete_selected_message(state: &mut App, tx_action: &Sender<AppAction>) {
+    let AppState::Chatting(channel_id, _) = state.state.clone() else {
+        return;
+    };
+    let Some(msg) = state.messages.get(state.selection_index.wrapping_sub(1)) else {
+        return;
+    };
+
+    if !can_delete_message(state, msg) {
+        state.status_message =
+            "You need Manage Messages to delete other people's messages.".to_string();
+        return;
+    }
+    let message_id = msg.id.clone();
+
+    if is_own_message(state, msg) {
+        tx_action
+            .send(AppAction::ApiDeleteMessage(channel_id, message_id, None))
+            .await
+            .ok();
+    } else if state.offline {
+        state.status_message = OFFLINE_READ_ONLY.to_string();
+    } else {
+        state.prompt = Some(Prompt::new(PromptKind::DeleteReason(
+            channel_id,
+            vec![message_id],
+        )));
+    }
+}
+
+/// The message under the cursor while a visual selection of `channel_id` is open, to find
+/// it again once the gateway changed the messages around it.
+pub fn visual_cursor(state: &App, channel_id: &str) -> Option<String> {
+    state.visual_anchor.as_ref()?;
+    if current_chat(state) != Some(channel_id) {
+        return None;
+    }
+    let msg = state.messages.get(state.selection_index.wrapping_sub(1))?;
+    Some(msg.id.clone())
+}
+
+/// Puts the cursor back on the message `visual_cursor` found, so the selection still
+/// covers what was highlighted. The selection is cancelled when either end is gone.
+pub fn restore_visual_cursor(state: &mut App, cursor: Option<String>) {
+    let Some(cursor) = cursor else {
+        return;
+    };
+    match state.messages.iter().position(|m| m.id == cursor) {
+        Some(index) if visual_range(state).is_some() => state.selection_index = index + 1,
+        _ => {
+            state.visual_anchor = None;
+            state.selection_index = state.selection_index.min(state.messages.len());
+            state.status_message =
+                "Visual selection cancelled, its messages were deleted.".to_string();
+        }
+    }
+}
+
+/// Deletes every message in the visual selection, asking for an audit log reason first
+/// when some of them are someone else's.
+pub async fn delete_visual_selection(state: &mut App, tx_action: &Sender<AppAction>) {
+    let range = visual_range(state);
+    state.visual_anchor = None;
+    let Some(range) = range else {
+        return;
+    };
+
+    if state.offline {
+        state.status_message = OFFLINE_READ_ONLY.to_string();
+        return;
+    }
+
+    let AppState::Chatting(channel_id, _) = state.state.clone() else {
+        return;
+    };
+
+    let messages = state.messages.get(range).unwrap_or_default();
+    let allowed = messages.iter().all(|m| can_delete_message(state, m));
+    let moderating = !messages.iter().all(|m| is_own_message(state, m));
+    let ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+
+    if !allowed {
+        state.status_message =
+            "You need Manage Messages to delete other people's messages.".to_string();
+        return;
+    }
+
+    if moderating {
+        state.prompt = Some(Prompt::new(PromptKind::DeleteReason(channel_id, ids)));
+        return;
+    }
+
+    tx_action
+        .send(AppAction::ApiBulkDeleteMessages(channel_id, ids, None))
+        .await
+        .ok();
+}
+
+/// Deletes the messages a `DeleteReason` prompt asked about, once it is confirmed.
+pub async fn delete_with_reason(
+    channel_id: String,
+    message_ids: Vec<String>,
+    reason: String,
+    tx_action: &Sender<AppAction>,
+) {
+    let reason = Some(reason.trim().to_string()).filter(|r| !r.is_empty());
+    let action = match <[String; 1]>::try_from(message_ids) {
+        Ok([message_id]) => AppAction::ApiDeleteMessage(channel_id, message_id, reason),
+        Err(message_ids) => AppAction::ApiBulkDeleteMessages(channel_id, message_ids, reason),
+    };
+    tx_action.send(action).await.ok();
+}
+
+/// Deletes `message_ids`, in bulk where Discord allows it when `bulk` is set (MANAGE_MESSAGES).
+/// Bulk deletion only takes 2 to 100 messages younger than two weeks, the rest go one by one.
+/// `reason` is recorded in the audit log for every request.
+pub async fn delete_messages(
+    api_client: &ApiClient,
+    channel_id: &str,
+    message_ids: Vec<String>,
+    bulk: bool,
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    let cutoff = chrono::Utc::now().timestamp_millis() - BULK_DELETE_MAX_AGE_MS;
+    let (recent, old): (Vec<String>, Vec<String>) = message_ids
+        .into_iter()
+        .partition(|id| bulk && snowflake_timestamp_ms(id) > cutoff);
+
+    for chunk in recent.chunks(BULK_DELETE_MAX_MESSAGES) {
+        if let [id] = chunk {
+            api_client.delete_message(channel_id, id, reason).await?;
+        } else {
+            api_client
+                .bulk_delete_messages(channel_id, chunk, reason)
+                .await?;
+        }
+    }
+
+    for id in old {
+        api_client.delete_message(channel_id, &id, reason).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{CHANNEL, FRIEND, ME, app, dispatch, message, receive};
+
+    #[test]
+    fn durations_take_a_unit_or_seconds() {
+        assert_eq!(parse_duration("45"), Some(45));
+        assert_eq!(parse_duration("30s"), Some(30));
+        assert_eq!(parse_duration("10m"), Some(600));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("1d"), Some(86400));
+        assert_eq!(parse_duration("1w"), Some(604800));
+    }
+
+    #[test]
+    fn malformed_durations_are_rejected() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("10y"), None);
+        assert_eq!(parse_duration("1h30m"), None);
+        assert_eq!(parse_duration("-5m"), None);
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+    }
+
+    #[test]
+    fn timeouts_and_ban_windows_are_capped() {
+        assert_eq!(parse_timeout("0"), None);
+        assert_eq!(parse_timeout("4w"), Some(MAX_TIMEOUT_SECONDS));
+        assert_eq!(parse_timeout("29d"), None);
+        assert_eq!(parse_ban_window("0"), Some(0));
+        assert_eq!(parse_ban_window("8d"), None);
+    }
+
+    /// Chatting in `CHANNEL` with messages 104 down to 100, the newest first.
+    fn chatting() -> App {
+        let mut app = app();
+        app.offline = false;
+        app.state = AppState::Chatting(CHANNEL.to_string(), "general".to_string());
+        app.channel_permissions = Some(Permissions::all());
+        app.messages = (100..105)
+            .rev()
+            .map(|id| message(&id.to_string(), CHANNEL, None, FRIEND, "spam"))
+            .collect();
+        app
+    }
+
+    #[test]
+    fn visual_range_is_ordered_whichever_way_it_was_selected() {
+        let mut app = chatting();
+        app.visual_anchor = Some("103".to_string());
+        app.selection_index = 4;
+        assert_eq!(visual_range(&app), Some(1..=3));
+
+        app.visual_anchor = Some("101".to_string());
+        app.selection_index = 2;
+        assert_eq!(visual_range(&app), Some(1..=3));
+    }
+
+    #[test]
+    fn visual_range_skips_the_input_line() {
+        let mut app = chatting();
+        app.visual_anchor = Some("102".to_string());
+        app.selection_index = 0;
+        assert_eq!(visual_range(&app), Some(0..=2));
+
+        app.visual_anchor = None;
+        assert_eq!(visual_range(&app), None);
+    }
+
+    #[tokio::test]
+    async fn new_messages_do_not_shift_the_visual_selection() {
+        let mut app = chatting();
+        app.visual_anchor = Some("103".to_string());
+        app.selection_index = 3;
+        let mut app = receive(app, message("105", CHANNEL, None, FRIEND, "new")).await;
+        assert_eq!(visual_range(&app), Some(2..=3));
+
+        let (tx_action, _rx_action) = tokio::sync::mpsc::channel(8);
+        delete_visual_selection(&mut app, &tx_action).await;
+        assert_eq!(
+            app.prompt.map(|p| p.kind),
+            Some(PromptKind::DeleteReason(
+                CHANNEL.to_string(),
+                vec!["103".to_string(), "102".to_string()]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_an_end_of_the_visual_selection_cancels_it() {
+        let mut app = chatting();
+        app.visual_anchor = Some("103".to_string());
+        app.selection_index = 3;
+        let app = dispatch(
+            app,
+            vec![AppAction::GatewayMessageDelete(
+                "103".to_string(),
+                CHANNEL.to_string(),
+            )],
+        )
+        .await;
+        assert_eq!(app.visual_anchor, None);
+    }
+
+    #[tokio::test]
+    async fn deleting_others_messages_asks_for_a_reason() {
+        let mut app = chatting();
+        app.visual_anchor = Some("100".to_string());
+        app.selection_index = 5;
+
+        let (tx_action, mut rx_action) = tokio::sync::mpsc::channel(8);
+        delete_visual_selection(&mut app, &tx_action).await;
+        assert!(rx_action.try_recv().is_err());
+        assert_eq!(
+            app.prompt.map(|p| p.kind),
+            Some(PromptKind::DeleteReason(
+                CHANNEL.to_string(),
+                vec!["100".to_string()]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_one_of_others_messages_asks_for_a_reason_too() {
+        let mut app = chatting();
+        app.messages[0].author.id = ME.to_string();
+        app.selection_index = 2;
+        let (tx_action, mut rx_action) = tokio::sync::mpsc::channel(8);
+        delete_selected_message(&mut app, &tx_action).await;
+        assert_eq!(
+            app.prompt.map(|p| p.kind),
+            Some(PromptKind::DeleteReason(
+                CHANNEL.to_string(),
+                vec!["103".to_string()]
+            ))
+        );
+
+        delete_with_reason(
+            CHANNEL.to_string(),
+            vec!["103".to_string()],
+            " spam ".to_string(),
+            &tx_action,
+        )
+        .await;
+        assert!(matches!(
+            rx_action.try_recv(),
+            Ok(AppAction::ApiDeleteMessage(_, id, Some(reason))) if id == "103" && reason == "spam"
+        ));
+
+        // Our own messages go without asking
+        app.prompt = None;
+        app.selection_index = 1;
+        delete_selected_message(&mut app, &tx_action).await;
+        assert!(app.prompt.is_none());
+        assert!(matches!(
+            rx_action.try_recv(),
+            Ok(AppAction::ApiDeleteMessage(_, id, None)) if id == "104"
+        ));
+    }
+
+    #[test]
+    fn moderation_is_read_only_offline() {
+        // The test session is offline, with permissions as cached
+        let mut app = app();
+        app.state = AppState::Chatting(CHANNEL.to_string(), "general".to_string());
+        app.channel_permissions = Some(Permissions::all());
+        start_moderation(&mut app, ModerationAction::Kick, Some(FRIEND.to_string()));
+        assert!(app.prompt.is_none());
+        assert_eq!(app.status_message, OFFLINE_READ_ONLY);
+    }
+}
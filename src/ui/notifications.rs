@@ -0,0 +1,832 @@
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::{
+    App, AppState,
+    api::{
+        Message,
+        guild::Guild,
+        user::{
+            ChannelOverride, GuildSettings, MuteConfig, NOTIFY_ALL, NOTIFY_MENTIONS, NOTIFY_NOTHING,
+        },
+    },
+    config::{NotificationOverride, NotifyLevel, QuietHours, save_config},
+    notifier::Notification,
+    ui::{
+        events::current_guild_id,
+        form::{Form, FormField, FormPurpose},
+        guild_list::{GuildRow, guild_rows},
+        moderation::parse_duration,
+        unread::mentions_me,
+    },
+};
+
+/// Key of the DM settings in `App.guild_settings`, which Discord sends with a null guild.
+const DM_SETTINGS: &str = "@me";
+
+/// A channel without new messages for this long starts a new burst.
+const BURST_WINDOW: Duration = Duration::from_secs(120);
+/// Backends that can't update a notification get one per channel this often at most.
+const BURST_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How the mute select of the form spells durations.
+const MUTE_OPTIONS: [(&str, &str); 6] = [
+    ("off", "Not muted"),
+    ("15m", "For 15 minutes"),
+    ("1h", "For an hour"),
+    ("8h", "For 8 hours"),
+    ("24h", "For 24 hours"),
+    ("forever", "Until I turn it back on"),
+];
+
+/// What Discord's settings and ours add up to for a channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effective {
+    pub muted: bool,
+    pub level: NotifyLevel,
+    pub suppress_everyone: bool,
+    pub suppress_roles: bool,
+}
+
+fn level_of(value: u8) -> Option<NotifyLevel> {
+    match value {
+        NOTIFY_ALL => Some(NotifyLevel::All),
+        NOTIFY_MENTIONS => Some(NotifyLevel::Mentions),
+        NOTIFY_NOTHING => Some(NotifyLevel::Nothing),
+        _ => None,
+    }
+}
+
+fn level_name(level: NotifyLevel) -> &'static str {
+    match level {
+        NotifyLevel::All => "all messages",
+        NotifyLevel::Mentions => "mentions only",
+        NotifyLevel::Nothing => "nothing",
+    }
+}
+
+/// Discord mutes are timed when `end_time` is set, and over once it passed.
+fn discord_muted(muted: bool, mute_config: &Option<MuteConfig>) -> bool {
+    let end = mute_config
+        .as_ref()
+        .and_then(|config| config.end_time.as_deref())
+        .and_then(|end| chrono::DateTime::parse_from_rfc3339(end).ok());
+    muted && end.is_none_or(|end| end > chrono::Utc::now())
+}
+
+fn local_muted(local: &NotificationOverride) -> bool {
+    local.muted
+        && local
+            .muted_until
+            .is_none_or(|until| until > chrono::Utc::now().timestamp())
+}
+
+/// The category a channel of the open guild sits in, Discord's overrides apply to it too.
+fn parent_of(state: &App, channel_id: &str) -> Option<String> {
+    state.channels.iter().find_map(|c| {
+        c.children
+            .iter()
+            .flatten()
+            .any(|child| child.id == channel_id)
+            .then(|| c.id.clone())
+    })
+}
+
+/// Settings for `channel_id`, in `guild_id` or a DM. A channel is muted when anything
+/// mutes it, the level comes from the closest layer that sets one: our channel override,
+/// Discord's channel and category overrides, our guild override, then Discord's guild.
+pub fn channel_settings(state: &App, channel_id: &str, guild_id: Option<&str>) -> Effective {
+    let discord: Option<&GuildSettings> = state.guild_settings.get(guild_id.unwrap_or(DM_SETTINGS));
+    let parent = parent_of(state, channel_id);
+    let mut discord_overrides: Vec<&ChannelOverride> = discord
+        .into_iter()
+        .flat_map(|settings| &settings.channel_overrides)
+        .filter(|o| o.channel_id == channel_id || Some(&o.channel_id) == parent.as_ref())
+        .collect();
+    // The channel's own override before its category's
+    discord_overrides.sort_by_key(|o| o.channel_id != channel_id);
+    let local_channel = state.config.notifications.get(channel_id);
+    let local_guild = guild_id.and_then(|id| state.config.notifications.get(id));
+
+    let muted = local_channel.is_some_and(local_muted)
+        || local_guild.is_some_and(local_muted)
+        || discord_overrides
+            .iter()
+            .any(|o| discord_muted(o.muted, &o.mute_config))
+        || discord.is_some_and(|settings| discord_muted(settings.muted, &settings.mute_config));
+
+    let level = local_channel
+        .and_then(|local| local.level)
+        .or_else(|| {
+            discord_overrides
+                .iter()
+                .find_map(|o| level_of(o.message_notifications))
+        })
+        .or_else(|| local_guild.and_then(|local| local.level))
+        .or_else(|| discord.and_then(|settings| level_of(settings.message_notifications)))
+        .or_else(|| {
+            let guild = state
+                .guilds
+                .iter()
+                .find(|g| Some(g.id.as_str()) == guild_id)?;
+            level_of(guild.default_message_notifications?)
+        })
+        // Without settings Discord notifies every DM, and guilds we know little of only on
+        // mentions
+        .unwrap_or(if guild_id.is_some() {
+            NotifyLevel::Mentions
+        } else {
+            NotifyLevel::All
+        });
+
+    Effective {
+        muted,
+        level,
+        suppress_everyone: discord.is_some_and(|settings| settings.suppress_everyone),
+        suppress_roles: discord.is_some_and(|settings| settings.suppress_roles),
+    }
+}
+
+/// Whether a guild is muted as a whole, which hides it from the unread badges.
+pub fn guild_muted(state: &App, guild_id: &str) -> bool {
+    state
+        .config
+        .notifications
+        .get(guild_id)
+        .is_some_and(local_muted)
+        || state
+            .guild_settings
+            .get(guild_id)
+            .is_some_and(|settings| discord_muted(settings.muted, &settings.mute_config))
+}
+
+/// Whether a message from someone else deserves a desktop notification.
+/// Keywords count as mentions, so they notify unless muted or set to nothing.
+pub fn should_notify(state: &App, msg: &Message) -> bool {
+    let settings = channel_settings(state, &msg.channel_id, msg.guild_id.as_deref());
+    if settings.muted {
+        return false;
+    }
+    match settings.level {
+        NotifyLevel::All => true,
+        NotifyLevel::Mentions => mentions_me(state, msg) || state.highlights.matches(msg),
+        NotifyLevel::Nothing => false,
+    }
+}
+
+/// Whether a quiet-hours rule covers `now`. Rules spanning midnight belong to the day
+/// they start on, invalid ones never apply.
+fn in_quiet_hours(rule: &QuietHours, now: NaiveDateTime) -> bool {
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(&rule.start, "%H:%M"),
+        NaiveTime::parse_from_str(&rule.end, "%H:%M"),
+    ) else {
+        return false;
+    };
+    let time = now.time();
+    let started_on = if start <= end {
+        (time >= start && time < end).then(|| now.weekday())
+    } else if time >= start {
+        Some(now.weekday())
+    } else if time < end {
+        Some(now.weekday().pred())
+    } else {
+        None
+    };
+
+    started_on.is_some_and(|day| {
+        rule.days.is_empty()
+            || rule
+                .days
+                .iter()
+                .any(|d| d.parse::<Weekday>().is_ok_and(|d| d == day))
+    })
+}
+
+/// Why nothing notifies right now, if so: our Do Not Disturb status or quiet hours.
+pub fn quiet_reason(state: &App) -> Option<&'static str> {
+    if !state.config.notify_when_dnd && state.own_status.as_deref() == Some("dnd") {
+        return Some("Do Not Disturb");
+    }
+    let now = chrono::Local::now().naive_local();
+    state
+        .config
+        .quiet_hours
+        .iter()
+        .any(|rule| in_quiet_hours(rule, now))
+        .then_some("quiet hours")
+}
+
+/// Messages of a channel notified since it was last read.
+#[derive(Debug)]
+pub struct Burst {
+    count: usize,
+    authors: Vec<String>,
+    last_message: Instant,
+    last_shown: Option<Instant>,
+}
+
+impl Burst {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            authors: Vec::new(),
+            last_message: Instant::now(),
+            last_shown: None,
+        }
+    }
+
+    fn summary(&self) -> String {
+        let authors = match self.authors.as_slice() {
+            [one] => one.clone(),
+            [first, second] => format!("{first} and {second}"),
+            [first, second, ..] => format!("{first}, {second} and others"),
+            [] => "someone".to_string(),
+        };
+        format!("{} new messages from {authors}", self.count)
+    }
+}
+
+/// MESSAGE_CREATE outside of the open channel: tells the notifier when the channel's
+/// settings ask for it. Messages coming in quick succession in a channel update one
+/// notification, or are rate limited with backends that can only stack them.
+pub fn notify_message(state: &mut App, msg: &Message) {
+    let is_self = state
+        .current_user
+        .as_ref()
+        .is_some_and(|u| u.id == msg.author.id);
+    if is_self || !should_notify(state, msg) || quiet_reason(state).is_some() {
+        return;
+    }
+
+    let burst = state
+        .notification_bursts
+        .entry(msg.channel_id.clone())
+        .or_insert_with(Burst::new);
+    if burst.last_message.elapsed() > BURST_WINDOW {
+        *burst = Burst::new();
+    }
+    burst.count += 1;
+    burst.last_message = Instant::now();
+    if !burst.authors.contains(&msg.author.username) {
+        burst.authors.push(msg.author.username.clone());
+    }
+    let show = state.notifier.updates_in_place()
+        || burst
+            .last_shown
+            .is_none_or(|shown| shown.elapsed() >= BURST_INTERVAL);
+    if !show {
+        return;
+    }
+    burst.last_shown = Some(Instant::now());
+
+    let author = if burst.count > 1 {
+        burst.summary()
+    } else {
+        msg.author.username.clone()
+    };
+    let summary = match msg
+        .guild_id
+        .as_ref()
+        .and_then(|guild_id| state.guilds.iter().find(|g| &g.id == guild_id))
+    {
+        Some(guild) => format!("{author} ({})", guild.name),
+        None => author,
+    };
+    let body = match (state.discreet_notifs, &msg.guild_id) {
+        (true, None) => "Sent you a DM".to_string(),
+        (true, Some(_)) => "Sent a message".to_string(),
+        (false, _) => msg
+            .content
+            .clone()
+            .filter(|content| !content.is_empty())
+            .unwrap_or_else(|| "Sent an attachment".to_string()),
+    };
+
+    let notification = Notification {
+        channel_id: msg.channel_id.clone(),
+        guild_id: msg.guild_id.clone(),
+        message_id: msg.id.clone(),
+        author: msg.author.username.clone(),
+        summary,
+        body,
+    };
+    state.notifier.notify(&notification);
+}
+
+/// The channel was viewed or read elsewhere: its burst ends and its notification goes away.
+pub fn channel_read(state: &mut App, channel_id: &str) {
+    state.notification_bursts.remove(channel_id);
+    state.notifier.clear(channel_id);
+}
+
+/// Discord's settings from READY, or an update to one guild's.
+pub fn apply_guild_settings(state: &mut App, settings: Vec<GuildSettings>) {
+    for settings in settings {
+        let key = settings
+            .guild_id
+            .clone()
+            .unwrap_or_else(|| DM_SETTINGS.to_string());
+        state.guild_settings.insert(key, settings);
+    }
+}
+
+/// READY's full guilds, for the notification level each server defaults to. Guilds our
+/// list does not have yet are added, the list keeps their default once it loads.
+pub fn apply_guild_defaults(state: &mut App, guilds: Vec<Guild>) {
+    for guild in guilds {
+        match state.guilds.iter_mut().find(|g| g.id == guild.id) {
+            Some(known) => {
+                known.default_message_notifications = guild.default_message_notifications
+            }
+            None => state.guilds.push(guild),
+        }
+    }
+}
+
+/// What `:notify` and `:mute` act on: `(id, name)` of the open channel or DM, or the
+/// guild with `server`, which is also the default outside of a chat.
+fn target(state: &mut App, server: bool) -> Option<(String, String)> {
+    let target = match (&state.state, server) {
+        (AppState::Chatting(channel_id, name), false) => Some((channel_id.clone(), name.clone())),
+        (AppState::SelectingGuild, _) => match guild_rows(state).get(state.selection_index) {
+            Some(GuildRow::Guild { id, name, .. }) => Some((id.clone(), name.clone())),
+            _ => None,
+        },
+        (AppState::Chatting(_, _) | AppState::SelectingChannel(_, _), _) => current_guild_id(state)
+            .and_then(|guild_id| {
+                state
+                    .guilds
+                    .iter()
+                    .find(|g| g.id == guild_id)
+                    .map(|g| (g.id.clone(), g.name.clone()))
+            }),
+        _ => None,
+    };
+    if target.is_none() {
+        state.status_message = if server {
+            "Open a server first.".to_string()
+        } else {
+            "Open a channel, a DM or a server first.".to_string()
+        };
+    }
+    target
+}
+
+/// Splits the optional `server` word off a command's arguments.
+fn parse_scope<'a>(args: &'a [&'a str]) -> (bool, &'a [&'a str]) {
+    match args.split_first() {
+        Some((&"server", rest)) => (true, rest),
+        _ => (false, args),
+    }
+}
+
+fn mute_label(local: &NotificationOverride) -> String {
+    match local.muted_until {
+        Some(until) => chrono::DateTime::from_timestamp(until, 0)
+            .map(|t| {
+                format!(
+                    "Until {}",
+                    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                )
+            })
+            .unwrap_or_default(),
+        None => "Until I turn it back on".to_string(),
+    }
+}
+
+/// `:notify [server]`, the form with our overrides for the channel or guild.
+pub fn open_settings(state: &mut App, args: &[&str]) {
+    let (server, _) = parse_scope(args);
+    let Some((id, name)) = target(state, server) else {
+        return;
+    };
+    let local = state
+        .config
+        .notifications
+        .get(&id)
+        .cloned()
+        .unwrap_or_default();
+
+    let level = match local.level {
+        Some(NotifyLevel::All) => "all",
+        Some(NotifyLevel::Mentions) => "mentions",
+        Some(NotifyLevel::Nothing) => "nothing",
+        None => "default",
+    };
+    let mut mute_options: Vec<(String, String)> = MUTE_OPTIONS
+        .iter()
+        .map(|(value, label)| (value.to_string(), label.to_string()))
+        .collect();
+    let mute = if local_muted(&local) {
+        mute_options.insert(1, ("keep".to_string(), mute_label(&local)));
+        "keep"
+    } else {
+        "off"
+    };
+
+    state.form = Some(Form::new(
+        format!("Notifications for {name}"),
+        FormPurpose::NotificationSettings { target_id: id },
+        vec![
+            FormField::select(
+                "level",
+                "Notify me of",
+                vec![
+                    ("default".to_string(), "Discord's setting".to_string()),
+                    ("all".to_string(), "All messages".to_string()),
+                    ("mentions".to_string(), "Mentions only".to_string()),
+                    ("nothing".to_string(), "Nothing".to_string()),
+                ],
+                level,
+            ),
+            FormField::select("mute", "Mute", mute_options, mute),
+        ],
+    ));
+}
+
+/// Stores an override, dropping it once it is back to the defaults.
+fn save_override(state: &mut App, id: &str, local: NotificationOverride) {
+    if local == NotificationOverride::default() {
+        state.config.notifications.remove(id);
+    } else {
+        state.config.notifications.insert(id.to_string(), local);
+    }
+    save_config(&state.config);
+}
+
+fn muted_until(duration: &str) -> Option<Option<i64>> {
+    if duration == "forever" {
+        return Some(None);
+    }
+    parse_duration(duration).map(|s| Some(chrono::Utc::now().timestamp() + s as i64))
+}
+
+pub fn submit_form(state: &mut App, form: Form) {
+    let FormPurpose::NotificationSettings { target_id } = form.purpose.clone() else {
+        return;
+    };
+    let mut local = state
+        .config
+        .notifications
+        .get(&target_id)
+        .cloned()
+        .unwrap_or_default();
+
+    local.level = match form.selected("level").as_str() {
+        "all" => Some(NotifyLevel::All),
+        "mentions" => Some(NotifyLevel::Mentions),
+        "nothing" => Some(NotifyLevel::Nothing),
+        _ => None,
+    };
+    match form.selected("mute").as_str() {
+        "keep" => {}
+        "off" => {
+            local.muted = false;
+            local.muted_until = None;
+        }
+        duration => {
+            local.muted = true;
+            local.muted_until = muted_until(duration).flatten();
+        }
+    }
+
+    let summary = match (local_muted(&local), local.level) {
+        (true, _) => "muted".to_string(),
+        (false, Some(level)) => level_name(level).to_string(),
+        (false, None) => "Discord's setting".to_string(),
+    };
+    save_override(state, &target_id, local);
+    state.status_message = format!("Notifications: {summary}.");
+}
+
+/// `:mute [server] [duration]`, forever without a duration.
+pub fn mute(state: &mut App, args: &[&str]) {
+    let (server, rest) = parse_scope(args);
+    let until = match rest.first() {
+        Some(duration) => match muted_until(duration) {
+            Some(until) => until,
+            None => {
+                state.status_message = format!("Invalid duration: {duration}, try 30m, 8h or 2d.");
+                return;
+            }
+        },
+        None => None,
+    };
+    let Some((id, name)) = target(state, server) else {
+        return;
+    };
+
+    let mut local = state
+        .config
+        .notifications
+        .get(&id)
+        .cloned()
+        .unwrap_or_default();
+    local.muted = true;
+    local.muted_until = until;
+    state.status_message = match until {
+        Some(_) => format!("Muted {name} {}.", mute_label(&local).to_lowercase()),
+        None => format!("Muted {name}."),
+    };
+    save_override(state, &id, local);
+}
+
+/// `:unmute [server]`
+pub fn unmute(state: &mut App, args: &[&str]) {
+    let (server, _) = parse_scope(args);
+    let Some((id, name)) = target(state, server) else {
+        return;
+    };
+
+    let mut local = state
+        .config
+        .notifications
+        .get(&id)
+        .cloned()
+        .unwrap_or_default();
+    local.muted = false;
+    local.muted_until = None;
+    save_override(state, &id, local);
+
+    let guild_id = current_guild_id(state);
+    let still_muted = if state.guilds.iter().any(|g| g.id == id) {
+        guild_muted(state, &id)
+    } else {
+        channel_settings(state, &id, guild_id.as_deref()).muted
+    };
+    state.status_message = if still_muted {
+        format!("Unmuted {name} here, but it is still muted in Discord or by its server.")
+    } else {
+        format!("Unmuted {name}.")
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        AppAction,
+        config::HighlightRule,
+        notifier::RecordingNotifier,
+        ui::{
+            highlights::Highlights,
+            testing::{
+                self, CHANNEL, DM_CHANNEL, FRIEND, GUILD, ME, dispatch, message, receive, user,
+            },
+        },
+    };
+
+    fn app() -> (App, RecordingNotifier) {
+        let mut app = testing::app();
+        let recorder = RecordingNotifier::default();
+        app.notifier = Box::new(recorder.clone());
+        (app, recorder)
+    }
+
+    fn shown(recorder: &RecordingNotifier) -> Vec<Notification> {
+        recorder.shown.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn dms_notify_with_their_content() {
+        let (app, recorder) = app();
+        receive(app, message("100", DM_CHANNEL, None, FRIEND, "hello")).await;
+
+        let shown = shown(&recorder);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].summary, "user2");
+        assert_eq!(shown[0].body, "hello");
+        assert_eq!(shown[0].channel_id, DM_CHANNEL);
+    }
+
+    #[tokio::test]
+    async fn our_own_messages_never_notify() {
+        let (app, recorder) = app();
+        receive(app, message("100", DM_CHANNEL, None, ME, "hello")).await;
+        assert!(shown(&recorder).is_empty());
+    }
+
+    #[tokio::test]
+    async fn discreet_notifications_hide_the_content() {
+        let (mut app, recorder) = app();
+        app.discreet_notifs = true;
+        receive(app, message("100", DM_CHANNEL, None, FRIEND, "secret")).await;
+        assert_eq!(shown(&recorder)[0].body, "Sent you a DM");
+    }
+
+    #[tokio::test]
+    async fn the_open_channel_does_not_notify() {
+        let (mut app, recorder) = app();
+        app.state = AppState::Chatting(DM_CHANNEL.to_string(), "user2".to_string());
+        receive(app, message("100", DM_CHANNEL, None, FRIEND, "hello")).await;
+        assert!(shown(&recorder).is_empty());
+    }
+
+    #[tokio::test]
+    async fn guild_messages_only_notify_on_mentions_by_default() {
+        let (app, recorder) = app();
+        let app = receive(app, message("100", CHANNEL, Some(GUILD), FRIEND, "hi all")).await;
+        assert!(shown(&recorder).is_empty());
+
+        let mut mention = message("101", CHANNEL, Some(GUILD), FRIEND, "hi <@1>");
+        mention.mentions.push(user(ME));
+        receive(app, mention).await;
+        assert_eq!(shown(&recorder).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn guilds_notify_every_message_when_the_server_defaults_to_it() {
+        let (mut app, recorder) = app();
+        let ready: Guild = serde_json::from_value(
+            json!({ "id": GUILD, "name": "guild", "default_message_notifications": 0 }),
+        )
+        .unwrap();
+        apply_guild_defaults(&mut app, vec![ready]);
+        // Inheriting from the server, as Discord sends for guilds left alone
+        apply_guild_settings(
+            &mut app,
+            vec![
+                serde_json::from_value(json!({ "guild_id": GUILD, "message_notifications": 3 }))
+                    .unwrap(),
+            ],
+        );
+        let app = receive(app, message("100", CHANNEL, Some(GUILD), FRIEND, "hi all")).await;
+        assert_eq!(shown(&recorder).len(), 1);
+
+        // Our list of guilds does not know the default and must not forget it
+        let listed: Guild =
+            serde_json::from_value(json!({ "id": GUILD, "name": "guild" })).unwrap();
+        let app = dispatch(app, vec![AppAction::ApiUpdateGuilds(vec![listed])]).await;
+        assert_eq!(
+            channel_settings(&app, CHANNEL, Some(GUILD)).level,
+            NotifyLevel::All
+        );
+    }
+
+    #[tokio::test]
+    async fn suppressed_everyone_does_not_notify() {
+        let (mut app, recorder) = app();
+        apply_guild_settings(
+            &mut app,
+            vec![
+                serde_json::from_value(json!({ "guild_id": GUILD, "suppress_everyone": true }))
+                    .unwrap(),
+            ],
+        );
+        let mut everyone = message("100", CHANNEL, Some(GUILD), FRIEND, "@everyone");
+        everyone.mention_everyone = true;
+        receive(app, everyone).await;
+        assert!(shown(&recorder).is_empty());
+    }
+
+    #[tokio::test]
+    async fn keywords_notify_in_guilds() {
+        let (mut app, recorder) = app();
+        app.highlights = Highlights::new(&[HighlightRule {
+            pattern: "deploy".to_string(),
+            regex: false,
+        }]);
+        receive(
+            app,
+            message("100", CHANNEL, Some(GUILD), FRIEND, "Deploy is done"),
+        )
+        .await;
+        assert_eq!(shown(&recorder).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn muted_channels_stay_quiet() {
+        let (mut app, recorder) = app();
+        app.config.notifications.insert(
+            DM_CHANNEL.to_string(),
+            NotificationOverride {
+                muted: true,
+                ..Default::default()
+            },
+        );
+        receive(app, message("100", DM_CHANNEL, None, FRIEND, "hello")).await;
+        assert!(shown(&recorder).is_empty());
+    }
+
+    #[tokio::test]
+    async fn channel_level_all_notifies_every_message() {
+        let (mut app, recorder) = app();
+        app.config.notifications.insert(
+            CHANNEL.to_string(),
+            NotificationOverride {
+                level: Some(NotifyLevel::All),
+                ..Default::default()
+            },
+        );
+        receive(app, message("100", CHANNEL, Some(GUILD), FRIEND, "hi all")).await;
+        assert_eq!(shown(&recorder).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn loading_a_channel_clears_its_notifications() {
+        let (mut app, recorder) = app();
+        app.state = AppState::Chatting(DM_CHANNEL.to_string(), "user2".to_string());
+        dispatch(
+            app,
+            vec![AppAction::ApiUpdateMessages(
+                DM_CHANNEL.to_string(),
+                Vec::new(),
+            )],
+        )
+        .await;
+        assert_eq!(
+            *recorder.cleared.lock().unwrap(),
+            vec![DM_CHANNEL.to_string()]
+        );
+    }
+
+    fn quiet(start: &str, end: &str, days: &[&str]) -> QuietHours {
+        QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight_from_their_start_day() {
+        // 2026-01-02 is a Friday
+        let rule = quiet("22:00", "07:00", &["fri"]);
+        assert!(in_quiet_hours(&rule, at("2026-01-02", "23:30")));
+        assert!(in_quiet_hours(&rule, at("2026-01-03", "06:59")));
+        assert!(!in_quiet_hours(&rule, at("2026-01-03", "07:00")));
+        assert!(!in_quiet_hours(&rule, at("2026-01-02", "06:00")));
+        assert!(!in_quiet_hours(
+            &quiet("9", "17:00", &[]),
+            at("2026-01-02", "12:00")
+        ));
+    }
+
+    #[tokio::test]
+    async fn do_not_disturb_silences_notifications() {
+        let (app, recorder) = app();
+        let app = dispatch(app, vec![AppAction::GatewayOwnStatus("dnd".to_string())]).await;
+        let mut app = receive(app, message("100", DM_CHANNEL, None, FRIEND, "hello")).await;
+        assert!(shown(&recorder).is_empty());
+
+        app.config.notify_when_dnd = true;
+        receive(app, message("101", DM_CHANNEL, None, FRIEND, "hello")).await;
+        assert_eq!(shown(&recorder).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bursts_update_one_notification() {
+        let (mut app, mut recorder) = app();
+        recorder.in_place = true;
+        app.notifier = Box::new(recorder.clone());
+        let mut app = receive(app, message("100", DM_CHANNEL, None, FRIEND, "hey")).await;
+        for id in ["101", "102"] {
+            app = receive(app, message(id, DM_CHANNEL, None, FRIEND, "you there?")).await;
+        }
+
+        let shown = shown(&recorder);
+        assert_eq!(shown.len(), 3);
+        assert_eq!(shown[2].summary, "3 new messages from user2");
+        assert_eq!(shown[2].body, "you there?");
+    }
+
+    #[tokio::test]
+    async fn bursts_are_rate_limited_when_notifications_stack() {
+        let (app, recorder) = app();
+        let mut app = receive(app, message("100", DM_CHANNEL, None, FRIEND, "hey")).await;
+        for id in ["101", "102"] {
+            app = receive(app, message(id, DM_CHANNEL, None, FRIEND, "you there?")).await;
+        }
+        assert_eq!(shown(&recorder).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn viewing_the_channel_ends_its_burst() {
+        let (app, recorder) = app();
+        let mut app = receive(app, message("100", DM_CHANNEL, None, FRIEND, "hey")).await;
+        app.state = AppState::Chatting(DM_CHANNEL.to_string(), "user2".to_string());
+        let mut app = dispatch(
+            app,
+            vec![AppAction::ApiUpdateMessages(
+                DM_CHANNEL.to_string(),
+                Vec::new(),
+            )],
+        )
+        .await;
+        assert!(app.notification_bursts.is_empty());
+
+        app.state = AppState::Home;
+        receive(app, message("101", DM_CHANNEL, None, FRIEND, "back")).await;
+        let shown = shown(&recorder);
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[1].summary, "user2");
+    }
+}
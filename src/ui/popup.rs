@@ -0,0 +1,60 @@
+use ratatui::text::Line;
+use tokio::sync::MutexGuard;
+
+use crate::{App, AppAction};
+
+/// A read-only, scrollable popup drawn over the main view.
+#[derive(Debug, Clone)]
+pub struct Popup {
+    pub title: String,
+    pub lines: Vec<Line<'static>>,
+    pub scroll: usize,
+}
+
+impl Popup {
+    pub fn new(title: String, lines: Vec<Line<'static>>) -> Self {
+        Self {
+            title,
+            lines,
+            scroll: 0,
+        }
+    }
+}
+
+/// Routes input actions to the open popup.
+/// Returns `true` when the action was consumed by the popup.
+pub fn handle_popup_action(state: &mut MutexGuard<'_, App>, action: &AppAction) -> bool {
+    let Some(popup) = state.popup.as_mut() else {
+        return false;
+    };
+    let last_line = popup.lines.len().saturating_sub(1);
+
+    match action {
+        AppAction::InputEscape | AppAction::InputChar('q') => {
+            state.popup = None;
+        }
+        AppAction::SelectNext | AppAction::InputChar('j') => {
+            popup.scroll = (popup.scroll + 1).min(last_line);
+        }
+        AppAction::SelectPrevious | AppAction::InputChar('k') => {
+            popup.scroll = popup.scroll.saturating_sub(1);
+        }
+        AppAction::InputChar('g') => {
+            popup.scroll = 0;
+        }
+        AppAction::InputChar('G') => {
+            popup.scroll = last_line;
+        }
+        // Swallow other input so it doesn't leak into the view underneath
+        AppAction::InputChar(_)
+        | AppAction::InputSubmit
+        | AppAction::InputBackspace
+        | AppAction::InputDelete
+        | AppAction::SelectLeft
+        | AppAction::SelectRight
+        | AppAction::Paste(_) => {}
+        _ => return false,
+    }
+
+    true
+}
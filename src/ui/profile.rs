@@ -0,0 +1,259 @@
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    App, AppAction,
+    api::{User, snowflake_timestamp_ms, user::UserProfile},
+    ui::{events::current_guild_id, popup::Popup},
+};
+
+/// Finds a user we have seen by id, username or display name.
+pub fn find_user(state: &App, query: &str) -> Option<User> {
+    let guild_members = current_guild_id(state)
+        .and_then(|guild_id| state.members.get(&guild_id))
+        .into_iter()
+        .flat_map(|members| members.values().map(|m| &m.user));
+    let authors = state.messages.iter().map(|m| &m.author);
+    let recipients = state.dms.iter().flat_map(|dm| dm.recipients.iter());
+    let relationships = state.relationships.iter().map(|r| &r.user);
+
+    guild_members
+        .chain(authors)
+        .chain(recipients)
+        .chain(relationships)
+        .find(|user| {
+            user.id == query
+                || user.username.eq_ignore_ascii_case(query)
+                || user
+                    .global_name
+                    .as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(query))
+        })
+        .cloned()
+}
+
+/// `p` on a message or `:profile [user]` shows a profile, fetched from Discord when online.
+pub fn open_profile(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    let query = args.join(" ");
+    let user = if query.is_empty() {
+        state
+            .selection_index
+            .checked_sub(1)
+            .and_then(|i| state.messages.get(i))
+            .map(|m| m.author.clone())
+    } else {
+        find_user(state, query.trim_start_matches('@'))
+    };
+    let Some(user) = user else {
+        state.status_message = if query.is_empty() {
+            "Select a message to see its author's profile.".to_string()
+        } else {
+            format!("No user named {query} seen yet.")
+        };
+        return;
+    };
+
+    if state.offline {
+        show_profile(state, user, None, None);
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    let guild_id = current_guild_id(state);
+    state.status_message = format!("Loading the profile of {}...", user.username);
+
+    tokio::spawn(async move {
+        // Bots can't see profiles, fall back to what we know locally
+        let profile = api_client
+            .get_user_profile(&user.id, guild_id.as_deref())
+            .await
+            .ok();
+        let note = api_client.get_user_note(&user.id).await.ok();
+        tx_action
+            .send(AppAction::ApiOpenProfile(user, profile, note))
+            .await
+            .ok();
+    });
+}
+
+fn field(label: &str, value: impl Into<String>) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{label}: "), Style::default().fg(Color::Cyan)),
+        Span::raw(value.into()),
+    ])
+}
+
+fn date(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .unwrap_or(timestamp.to_string())
+}
+
+/// Builds the profile popup from the fetched profile and whatever is cached.
+pub fn show_profile(
+    state: &mut App,
+    user: User,
+    profile: Option<UserProfile>,
+    note: Option<String>,
+) {
+    let guild_id = current_guild_id(state);
+    let user = profile.as_ref().map_or(user, |p| p.user.clone());
+    let member = profile
+        .as_ref()
+        .and_then(|p| p.guild_member.clone())
+        .or_else(|| {
+            guild_id
+                .as_ref()
+                .and_then(|guild_id| state.members.get(guild_id))
+                .and_then(|members| members.get(&user.id))
+                .cloned()
+        });
+
+    let mut lines = Vec::new();
+
+    let mut header = vec![Span::styled(
+        user.global_name.clone().unwrap_or(user.username.clone()),
+        Style::default().fg(Color::Yellow).bold(),
+    )];
+    if user.bot {
+        header.push(Span::raw(" "));
+        header.push(Span::styled(" BOT ", Style::default().bg(Color::Blue)));
+    }
+    lines.push(Line::from(header));
+    lines.push(field("Username", user.tag()));
+    if let Some(nick) = member.as_ref().and_then(|m| m.nick.clone()) {
+        lines.push(field("Server nick", nick));
+    }
+    if let Some(pronouns) = profile
+        .as_ref()
+        .and_then(|p| p.user_profile.as_ref())
+        .and_then(|p| p.pronouns.clone())
+        .filter(|p| !p.is_empty())
+    {
+        lines.push(field("Pronouns", pronouns));
+    }
+
+    let status = state
+        .user_statuses
+        .get(&user.id)
+        .cloned()
+        .unwrap_or("offline".to_string());
+    let status_color = match status.as_str() {
+        "online" => Color::Green,
+        "idle" => Color::Yellow,
+        "dnd" => Color::Red,
+        _ => Color::DarkGray,
+    };
+    let mut presence = vec![
+        Span::styled("Status: ", Style::default().fg(Color::Cyan)),
+        Span::styled("● ", Style::default().fg(status_color)),
+        Span::raw(status),
+    ];
+    if let Some(activity) = state.user_activities.get(&user.id) {
+        presence.push(Span::styled(
+            format!(" — {activity}"),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    lines.push(Line::from(presence));
+
+    lines.push(Line::from(""));
+    if let Some(created) = chrono::DateTime::from_timestamp_millis(snowflake_timestamp_ms(&user.id))
+    {
+        lines.push(field(
+            "Account created",
+            created
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string(),
+        ));
+    }
+    if let Some(joined) = member.as_ref().and_then(|m| m.joined_at.as_deref()) {
+        lines.push(field("Joined server", date(joined)));
+    }
+    if let Some(since) = profile.as_ref().and_then(|p| p.premium_since.as_deref()) {
+        lines.push(field("Nitro since", date(since)));
+    }
+
+    if let (Some(member), Some(context)) = (&member, &state.context) {
+        let roles = context.roles_of(&member.roles);
+        if !roles.is_empty() {
+            let mut spans = vec![Span::styled("Roles: ", Style::default().fg(Color::Cyan))];
+            for (i, role) in roles.into_iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::raw(", "));
+                }
+                let color = role
+                    .rgb()
+                    .map_or(Color::White, |(r, g, b)| Color::Rgb(r, g, b));
+                spans.push(Span::styled(
+                    format!("@{}", role.name),
+                    Style::default().fg(color),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+
+    if let Some(profile) = &profile
+        && !profile.mutual_guilds.is_empty()
+    {
+        let names: Vec<String> = profile
+            .mutual_guilds
+            .iter()
+            .map(|mutual| {
+                state
+                    .guilds
+                    .iter()
+                    .find(|g| g.id == mutual.id)
+                    .map_or(mutual.id.clone(), |g| g.name.clone())
+            })
+            .collect();
+        lines.push(field("Mutual servers", names.join(", ")));
+    }
+
+    if let Some(bio) = profile
+        .as_ref()
+        .and_then(|p| p.user_profile.as_ref())
+        .and_then(|p| p.bio.clone())
+        .filter(|bio| !bio.is_empty())
+    {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "About me",
+            Style::default().fg(Color::Cyan),
+        )));
+        lines.extend(bio.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    if let Some(note) = note.filter(|note| !note.is_empty()) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Note",
+            Style::default().fg(Color::Cyan),
+        )));
+        lines.extend(
+            note.lines()
+                .map(|line| Line::from(Span::styled(line.to_string(), Style::default().italic()))),
+        );
+    }
+
+    if profile.is_none() && !state.offline {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "The full profile could not be loaded, showing cached details.",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    state.status_message = "Profile: j/k scroll, Esc closes".to_string();
+    state.popup = Some(Popup::new(format!("Profile of {}", user.username), lines));
+}
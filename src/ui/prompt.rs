@@ -0,0 +1,140 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction,
+    ui::{
+        command, friends,
+        moderation::{self, ModerationAction, ModerationTarget},
+        search::{self, SearchDirection},
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptKind {
+    SearchForward,
+    SearchBackward,
+    Command,
+    /// Audit log reason for a moderation action that runs on submit
+    Reason(ModerationAction, ModerationTarget),
+    /// Audit log reason for deleting the selected messages of others: channel id, message ids
+    DeleteReason(String, Vec<String>),
+    /// Filters the member list as it is typed
+    MemberFilter,
+    /// Username to send a friend request to
+    FriendRequest,
+}
+
+/// A one-line prompt rendered in place of the input box, like Vim's command line.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            input: String::new(),
+        }
+    }
+
+    pub fn prefix(&self) -> String {
+        match &self.kind {
+            PromptKind::SearchForward => "/".to_string(),
+            PromptKind::SearchBackward => "?".to_string(),
+            PromptKind::Command => ":".to_string(),
+            PromptKind::Reason(action, target) => moderation::reason_prompt(action, target),
+            PromptKind::DeleteReason(_, message_ids) => {
+                moderation::delete_reason_prompt(message_ids.len())
+            }
+            PromptKind::MemberFilter => "Filter members: ".to_string(),
+            PromptKind::FriendRequest => "Add friend by username: ".to_string(),
+        }
+    }
+
+    pub fn display(&self) -> String {
+        format!("{}{}", self.prefix(), self.input)
+    }
+}
+
+/// Routes input actions to the active prompt.
+/// Returns `true` when the action was consumed by the prompt.
+pub async fn handle_prompt_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let Some(prompt) = state.prompt.as_mut() else {
+        return false;
+    };
+
+    match action {
+        AppAction::InputChar(c) => {
+            prompt.input.push(*c);
+        }
+        AppAction::Paste(text) => {
+            // Prompts are single line, newlines would never match anything useful
+            prompt.input.push_str(&text.replace('\n', " "));
+        }
+        AppAction::InputBackspace => {
+            // Like Vim, backspacing past the start closes the prompt, but a reason may be empty
+            if prompt.input.pop().is_none()
+                && !matches!(
+                    prompt.kind,
+                    PromptKind::Reason(_, _) | PromptKind::DeleteReason(_, _)
+                )
+            {
+                state.prompt = None;
+            }
+        }
+        AppAction::InputEscape => {
+            if prompt.kind == PromptKind::MemberFilter {
+                state.member_list.filter.clear();
+            }
+            state.prompt = None;
+        }
+        AppAction::InputSubmit => {
+            let prompt = state.prompt.take().unwrap();
+            match prompt.kind {
+                PromptKind::SearchForward => {
+                    search::start_search(state, prompt.input, SearchDirection::Forward, tx_action)
+                        .await;
+                }
+                PromptKind::SearchBackward => {
+                    search::start_search(state, prompt.input, SearchDirection::Backward, tx_action)
+                        .await;
+                }
+                PromptKind::Command => {
+                    command::execute_command(state, &prompt.input, tx_action).await;
+                }
+                PromptKind::Reason(action, target) => {
+                    moderation::run_moderation(state, action, target, prompt.input, tx_action);
+                }
+                PromptKind::DeleteReason(channel_id, message_ids) => {
+                    moderation::delete_with_reason(
+                        channel_id,
+                        message_ids,
+                        prompt.input,
+                        tx_action,
+                    )
+                    .await;
+                }
+                PromptKind::FriendRequest => {
+                    friends::send_request(state, prompt.input, tx_action);
+                }
+                // Already applied while typing
+                PromptKind::MemberFilter => {}
+            }
+        }
+        _ => return false,
+    }
+
+    if let Some(prompt) = &state.prompt
+        && prompt.kind == PromptKind::MemberFilter
+    {
+        state.member_list.filter = prompt.input.clone();
+    }
+
+    true
+}
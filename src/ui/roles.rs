@@ -0,0 +1,673 @@
+use std::collections::HashSet;
+
+use futures_util::future::join_all;
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction,
+    api::{
+        ApiClient,
+        channel::{PermissionContext, Role},
+        guild::GuildMember,
+        permissions::Permissions,
+    },
+    logs::{LogType, print_log},
+    ui::{
+        channel_admin::ensure_permission,
+        events::current_guild_id,
+        form::{FieldValue, Form, FormField, FormPurpose},
+    },
+};
+
+/// Members fetched at once for the authors of a page of messages, the others wait for
+/// the next page.
+const MAX_MEMBER_REQUESTS: usize = 10;
+
+/// Role list of the current guild, drawn over the main view.
+#[derive(Debug, Clone)]
+pub struct RoleView {
+    pub guild_id: String,
+    pub selected: usize,
+}
+
+/// Roles from the highest to @everyone, the order Discord lists them in.
+pub fn sorted_roles(context: &PermissionContext) -> Vec<&Role> {
+    let mut roles: Vec<&Role> = context.all_guild_roles.iter().collect();
+    roles.sort_by_key(|r| {
+        (
+            std::cmp::Reverse(r.position),
+            r.id.parse::<u64>().unwrap_or_default(),
+        )
+    });
+    roles
+}
+
+fn selected_role(state: &App) -> Option<Role> {
+    let view = state.role_view.as_ref()?;
+    let context = state.context.as_ref()?;
+    sorted_roles(context)
+        .get(view.selected)
+        .map(|r| (*r).clone())
+}
+
+/// Checks we may edit `role`: MANAGE_ROLES, and the role sits below our own highest role.
+fn ensure_manageable(state: &mut App, role: &Role) -> bool {
+    if !ensure_permission(state, None, Permissions::MANAGE_ROLES) {
+        return false;
+    }
+    if role.managed {
+        state.status_message = format!("@{} is managed by an integration.", role.name);
+        return false;
+    }
+    if state
+        .context
+        .as_ref()
+        .is_some_and(|context| !context.can_manage_role(role))
+    {
+        state.status_message = format!("@{} is not below your highest role.", role.name);
+        return false;
+    }
+    true
+}
+
+async fn report_error(tx_action: &Sender<AppAction>, what: &str, e: crate::Error) {
+    let _ = print_log(format!("Failed to {what}: {e}").into(), LogType::Error);
+    tx_action
+        .send(AppAction::UpdateStatus(format!("Failed to {what}: {e}")))
+        .await
+        .ok();
+}
+
+/// Reloads the guild roles after a change, so permissions are recomputed with them.
+async fn reload_roles(api_client: &ApiClient, guild_id: String, tx_action: &Sender<AppAction>) {
+    match api_client.get_guild_roles(&guild_id).await {
+        Ok(roles) => {
+            tx_action
+                .send(AppAction::ApiUpdateRoles(guild_id, roles))
+                .await
+                .ok();
+        }
+        Err(e) => report_error(tx_action, "reload roles", e).await,
+    }
+}
+
+/// `:roles`
+pub fn open_roles(state: &mut App) {
+    let Some(guild_id) = current_guild_id(state) else {
+        state.status_message = "Open a server to see its roles.".to_string();
+        return;
+    };
+    if state.context.is_none() {
+        state.status_message = "Roles are still loading.".to_string();
+        return;
+    }
+
+    state.role_view = Some(RoleView {
+        guild_id,
+        selected: 0,
+    });
+    state.status_message =
+        "Roles: n new, Enter edit, d delete, J/K move down/up, Esc closes".to_string();
+}
+
+/// Replaces the roles of the current guild, keeping the cached context in sync.
+pub fn apply_roles(state: &mut App, guild_id: &str, roles: Vec<Role>) {
+    let Some(context) = state.context.as_mut() else {
+        return;
+    };
+    if context.everyone_role_id != guild_id {
+        return;
+    }
+    context.all_guild_roles = roles;
+
+    if let Some(store) = state.store.as_ref()
+        && let Some(context) = &state.context
+    {
+        store.save_value(&format!("context:{guild_id}"), context);
+    }
+
+    if let Some(view) = state.role_view.as_mut() {
+        let last = state
+            .context
+            .as_ref()
+            .map_or(0, |c| c.all_guild_roles.len().saturating_sub(1));
+        view.selected = view.selected.min(last);
+    }
+}
+
+fn role_fields(role: Option<&Role>, is_everyone: bool) -> Vec<FormField> {
+    let permissions = role
+        .map(|r| Permissions::parse(&r.permissions))
+        .unwrap_or_default();
+
+    let mut fields = Vec::new();
+    if !is_everyone {
+        fields.push(FormField::text(
+            "name",
+            "Name",
+            role.map(|r| r.name.clone()).unwrap_or_default(),
+        ));
+        fields.push(FormField::text(
+            "color",
+            "Color (#rrggbb)",
+            role.filter(|r| r.color != 0)
+                .map(|r| format!("#{:06x}", r.color))
+                .unwrap_or_default(),
+        ));
+        fields.push(FormField::checkbox(
+            "hoist",
+            "Display separately",
+            role.is_some_and(|r| r.hoist),
+        ));
+        fields.push(FormField::checkbox(
+            "mentionable",
+            "Mentionable",
+            role.is_some_and(|r| r.mentionable),
+        ));
+    }
+
+    fields.extend(Permissions::all().iter_names().map(|(name, permission)| {
+        FormField::checkbox(name, name, permissions.contains(permission))
+    }));
+    fields
+}
+
+fn open_create_form(state: &mut App, guild_id: String) {
+    if !ensure_permission(state, None, Permissions::MANAGE_ROLES) {
+        return;
+    }
+
+    state.form = Some(Form::new(
+        "New role".to_string(),
+        FormPurpose::CreateRole { guild_id },
+        role_fields(None, false),
+    ));
+}
+
+fn open_edit_form(state: &mut App, guild_id: String, role: Role) {
+    if !ensure_manageable(state, &role) {
+        return;
+    }
+
+    let is_everyone = role.id == guild_id;
+    state.form = Some(Form::new(
+        format!("Edit @{}", role.name.trim_start_matches('@')),
+        FormPurpose::EditRole {
+            guild_id,
+            role_id: role.id.clone(),
+        },
+        role_fields(Some(&role), is_everyone),
+    ));
+}
+
+fn open_delete_form(state: &mut App, guild_id: String, role: Role) {
+    if role.id == guild_id {
+        state.status_message = "@everyone can't be deleted.".to_string();
+        return;
+    }
+    if !ensure_manageable(state, &role) {
+        return;
+    }
+
+    state.form = Some(Form::new(
+        format!("Delete @{}?", role.name),
+        FormPurpose::DeleteRole {
+            guild_id,
+            role_id: role.id,
+        },
+        vec![FormField::checkbox(
+            "confirm",
+            "Yes, delete it for everyone",
+            false,
+        )],
+    ));
+}
+
+/// Swaps the selected role with the one above (`step` -1) or below (`step` 1) it.
+fn move_role(state: &mut App, guild_id: String, step: isize, tx_action: &Sender<AppAction>) {
+    let Some(context) = state.context.as_ref() else {
+        return;
+    };
+    let Some(selected) = state.role_view.as_ref().map(|v| v.selected) else {
+        return;
+    };
+    let roles: Vec<Role> = sorted_roles(context).into_iter().cloned().collect();
+
+    let Some(other) = selected
+        .checked_add_signed(step)
+        // @everyone always stays at the bottom
+        .filter(|&i| i + 1 < roles.len() && selected + 1 < roles.len())
+    else {
+        return;
+    };
+    let (role, neighbour) = (roles[selected].clone(), roles[other].clone());
+    if !ensure_manageable(state, &role) || !ensure_manageable(state, &neighbour) {
+        return;
+    }
+
+    let positions = vec![
+        (role.id.clone(), neighbour.position),
+        (neighbour.id.clone(), role.position),
+    ];
+
+    // Reorder locally right away, Discord answers with the new order
+    let updated: Vec<Role> = roles
+        .into_iter()
+        .map(|mut r| {
+            if let Some((_, position)) = positions.iter().find(|(id, _)| id == &r.id) {
+                r.position = *position;
+            }
+            r
+        })
+        .collect();
+    apply_roles(state, &guild_id, updated);
+    if let Some(view) = state.role_view.as_mut() {
+        view.selected = other;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        match api_client
+            .modify_guild_role_positions(&guild_id, &positions)
+            .await
+        {
+            Ok(roles) => {
+                tx_action
+                    .send(AppAction::ApiUpdateRoles(guild_id, roles))
+                    .await
+                    .ok();
+            }
+            Err(e) => {
+                report_error(&tx_action, "move role", e).await;
+                reload_roles(&api_client, guild_id, &tx_action).await;
+            }
+        }
+    });
+}
+
+/// Routes input actions to the open role list.
+/// Returns `true` when the action was consumed by the view.
+pub fn handle_role_view_action(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    let last = state
+        .context
+        .as_ref()
+        .map_or(0, |c| c.all_guild_roles.len().saturating_sub(1));
+    let Some(view) = state.role_view.as_mut() else {
+        return false;
+    };
+    let guild_id = view.guild_id.clone();
+
+    match action {
+        AppAction::InputEscape | AppAction::InputChar('q') => {
+            state.role_view = None;
+        }
+        AppAction::SelectNext | AppAction::InputChar('j') => {
+            view.selected = (view.selected + 1).min(last);
+        }
+        AppAction::SelectPrevious | AppAction::InputChar('k') => {
+            view.selected = view.selected.saturating_sub(1);
+        }
+        AppAction::InputChar('n') => open_create_form(state, guild_id),
+        AppAction::InputSubmit | AppAction::InputChar('e') => {
+            if let Some(role) = selected_role(state) {
+                open_edit_form(state, guild_id, role);
+            }
+        }
+        AppAction::InputChar('d') => {
+            if let Some(role) = selected_role(state) {
+                open_delete_form(state, guild_id, role);
+            }
+        }
+        AppAction::InputChar('J') => move_role(state, guild_id, 1, tx_action),
+        AppAction::InputChar('K') => move_role(state, guild_id, -1, tx_action),
+        AppAction::InputChar(_)
+        | AppAction::InputBackspace
+        | AppAction::InputDelete
+        | AppAction::SelectLeft
+        | AppAction::SelectRight
+        | AppAction::Paste(_) => {}
+        _ => return false,
+    }
+
+    true
+}
+
+/// `:member [user]` opens the roles of a member, the selected message's author by default.
+pub fn open_member(state: &mut App, args: &[&str], tx_action: &Sender<AppAction>) {
+    let Some(guild_id) = current_guild_id(state) else {
+        state.status_message = "Open a server to manage its members.".to_string();
+        return;
+    };
+    if !ensure_permission(state, None, Permissions::MANAGE_ROLES) {
+        return;
+    }
+
+    let query = args.join(" ");
+    let user_id = if query.is_empty() {
+        state
+            .selection_index
+            .checked_sub(1)
+            .and_then(|i| state.messages.get(i))
+            .map(|m| m.author.id.clone())
+    } else {
+        state
+            .user_names
+            .iter()
+            .find(|(id, name)| name.eq_ignore_ascii_case(&query) || **id == query)
+            .map(|(id, _)| id.clone())
+            .or_else(|| query.chars().all(|c| c.is_ascii_digit()).then_some(query))
+    };
+    let Some(user_id) = user_id else {
+        state.status_message = "Select a message or name a member.".to_string();
+        return;
+    };
+
+    // Fetch the member again, their roles may have changed since they were cached
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    state.status_message = "Loading member...".to_string();
+    tokio::spawn(async move {
+        match api_client.get_member(&guild_id, &user_id).await {
+            Ok(member) => {
+                tx_action
+                    .send(AppAction::ApiOpenMember(guild_id, member))
+                    .await
+                    .ok();
+            }
+            Err(e) => report_error(&tx_action, "load member", e).await,
+        }
+    });
+}
+
+/// Shows a fetched member as a form with a checkbox per role.
+pub fn open_member_form(state: &mut App, guild_id: String, member: GuildMember) {
+    let Some(context) = state.context.as_ref() else {
+        return;
+    };
+    if context.everyone_role_id != guild_id {
+        return;
+    }
+
+    let roles: Vec<&Role> = sorted_roles(context)
+        .into_iter()
+        .filter(|r| r.id != guild_id)
+        .collect();
+    let role_ids = roles.iter().map(|role| role.id.clone()).collect();
+    let fields = roles
+        .into_iter()
+        .map(|role| {
+            let label = if role.managed || !context.can_manage_role(role) {
+                format!("@{} (locked)", role.name)
+            } else {
+                format!("@{}", role.name)
+            };
+            FormField::checkbox("role", &label, member.roles.contains(&role.id))
+        })
+        .collect();
+
+    let name = member
+        .nick
+        .clone()
+        .or(member.user.global_name.clone())
+        .unwrap_or(member.user.username.clone());
+    let joined = member
+        .joined_at
+        .as_deref()
+        .and_then(|date| date.split('T').next())
+        .map(|date| format!(", joined {date}"))
+        .unwrap_or_default();
+
+    state.form = Some(Form::new(
+        format!("Roles of {name}{joined}"),
+        FormPurpose::MemberRoles {
+            guild_id: guild_id.clone(),
+            user_id: member.user.id.clone(),
+            role_ids,
+        },
+        fields,
+    ));
+    state.status_message = format!("Editing the roles of {name}.");
+    state
+        .members
+        .entry(guild_id)
+        .or_default()
+        .insert(member.user.id.clone(), member);
+}
+
+fn role_body(form: &Form, state: &mut App, is_everyone: bool) -> Option<serde_json::Value> {
+    let mut permissions = Permissions::empty();
+    for (name, permission) in Permissions::all().iter_names() {
+        if form.checked(name) {
+            permissions |= permission;
+        }
+    }
+    let mut body = serde_json::json!({ "permissions": permissions.bits().to_string() });
+    if is_everyone {
+        return Some(body);
+    }
+
+    let name = form.text("name");
+    if name.is_empty() {
+        state.status_message = "A role needs a name.".to_string();
+        return None;
+    }
+    let color = form.text("color");
+    let Some(color) = (if color.is_empty() {
+        Some(0)
+    } else {
+        u32::from_str_radix(color.trim_start_matches('#'), 16)
+            .ok()
+            .filter(|&c| c <= 0xFFFFFF)
+    }) else {
+        state.status_message = format!("Invalid color: {color}");
+        return None;
+    };
+
+    body["name"] = serde_json::json!(name);
+    body["color"] = serde_json::json!(color);
+    body["hoist"] = serde_json::json!(form.checked("hoist"));
+    body["mentionable"] = serde_json::json!(form.checked("mentionable"));
+    Some(body)
+}
+
+/// Sends a submitted role or member form to the API.
+pub fn submit_form(state: &mut App, form: Form, tx_action: &Sender<AppAction>) {
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+
+    match form.purpose.clone() {
+        FormPurpose::CreateRole { guild_id } => {
+            let Some(body) = role_body(&form, state, false) else {
+                state.form = Some(form);
+                return;
+            };
+
+            state.status_message = "Creating role...".to_string();
+            tokio::spawn(async move {
+                match api_client.create_guild_role(&guild_id, body).await {
+                    Ok(role) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus(format!("Created @{}.", role.name)))
+                            .await
+                            .ok();
+                        reload_roles(&api_client, guild_id, &tx_action).await;
+                    }
+                    Err(e) => report_error(&tx_action, "create role", e).await,
+                }
+            });
+        }
+        FormPurpose::EditRole { guild_id, role_id } => {
+            let Some(body) = role_body(&form, state, role_id == guild_id) else {
+                state.form = Some(form);
+                return;
+            };
+
+            state.status_message = "Saving role...".to_string();
+            tokio::spawn(async move {
+                match api_client
+                    .modify_guild_role(&guild_id, &role_id, body)
+                    .await
+                {
+                    Ok(role) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus(format!("Saved @{}.", role.name)))
+                            .await
+                            .ok();
+                        reload_roles(&api_client, guild_id, &tx_action).await;
+                    }
+                    Err(e) => report_error(&tx_action, "edit role", e).await,
+                }
+            });
+        }
+        FormPurpose::DeleteRole { guild_id, role_id } => {
+            if !form.checked("confirm") {
+                state.status_message = "Not deleted: tick the confirmation first.".to_string();
+                return;
+            }
+
+            state.status_message = "Deleting role...".to_string();
+            tokio::spawn(async move {
+                match api_client.delete_guild_role(&guild_id, &role_id).await {
+                    Ok(()) => {
+                        tx_action
+                            .send(AppAction::UpdateStatus("Role deleted.".to_string()))
+                            .await
+                            .ok();
+                        reload_roles(&api_client, guild_id, &tx_action).await;
+                    }
+                    Err(e) => report_error(&tx_action, "delete role", e).await,
+                }
+            });
+        }
+        FormPurpose::MemberRoles {
+            guild_id,
+            user_id,
+            role_ids,
+        } => {
+            let (Some(context), Some(member)) = (
+                state.context.as_ref(),
+                state.members.get(&guild_id).and_then(|m| m.get(&user_id)),
+            ) else {
+                return;
+            };
+
+            let current: HashSet<&String> = member.roles.iter().collect();
+
+            let mut added = Vec::new();
+            let mut removed = Vec::new();
+            for (role_id, field) in role_ids.iter().zip(&form.fields) {
+                // Roles deleted while the form was open are left alone
+                let Some(role) = context.all_guild_roles.iter().find(|r| &r.id == role_id) else {
+                    continue;
+                };
+                let wanted = matches!(field.value, FieldValue::Checkbox(true));
+                if wanted == current.contains(&role.id) {
+                    continue;
+                }
+                if role.managed || !context.can_manage_role(role) {
+                    state.status_message = format!("@{} is locked, it can't change.", role.name);
+                    return;
+                }
+                if wanted {
+                    added.push(role.id.clone());
+                } else {
+                    removed.push(role.id.clone());
+                }
+            }
+
+            if added.is_empty() && removed.is_empty() {
+                state.status_message = "No role changed.".to_string();
+                return;
+            }
+
+            let mut member = member.clone();
+            state.status_message = "Saving roles...".to_string();
+            tokio::spawn(async move {
+                for role_id in &added {
+                    if let Err(e) = api_client
+                        .add_member_role(&guild_id, &user_id, role_id)
+                        .await
+                    {
+                        return report_error(&tx_action, "add role", e).await;
+                    }
+                    member.roles.push(role_id.clone());
+                }
+                for role_id in &removed {
+                    if let Err(e) = api_client
+                        .remove_member_role(&guild_id, &user_id, role_id)
+                        .await
+                    {
+                        return report_error(&tx_action, "remove role", e).await;
+                    }
+                    member.roles.retain(|r| r != role_id);
+                }
+
+                tx_action
+                    .send(AppAction::UpdateStatus(format!(
+                        "Roles saved: {} added, {} removed.",
+                        added.len(),
+                        removed.len()
+                    )))
+                    .await
+                    .ok();
+                tx_action
+                    .send(AppAction::ApiUpdateMembers(guild_id, vec![member]))
+                    .await
+                    .ok();
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Fetches the guild members behind the loaded messages, so names can take their role color.
+pub fn request_missing_members(state: &mut App, tx_action: &Sender<AppAction>) {
+    if state.offline || state.channel_permissions.is_none() {
+        return;
+    }
+    let Some(guild_id) = current_guild_id(state) else {
+        return;
+    };
+
+    // The authors of the newest messages first, the ones on screen
+    let cached = state.members.get(&guild_id);
+    let mut seen = HashSet::new();
+    let missing: Vec<String> = state
+        .messages
+        .iter()
+        .map(|m| m.author.id.clone())
+        .filter(|id| !cached.is_some_and(|members| members.contains_key(id)))
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+    // Users who left the guild are not found, only ask once
+    let missing: Vec<String> = missing
+        .into_iter()
+        .filter(|id| state.requested_members.insert(format!("{guild_id}:{id}")))
+        .take(MAX_MEMBER_REQUESTS)
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let tx_action = tx_action.clone();
+    tokio::spawn(async move {
+        let requests = missing
+            .iter()
+            .map(|user_id| api_client.get_member(&guild_id, user_id));
+        let members = join_all(requests)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect();
+        tx_action
+            .send(AppAction::ApiUpdateMembers(guild_id, members))
+            .await
+            .ok();
+    });
+}
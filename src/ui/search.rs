@@ -0,0 +1,282 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    api::Message,
+    ui::events::{HistoryGoal, load_history_until},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchDirection {
+    /// `/`: towards newer messages (down the screen)
+    Forward,
+    /// `?`: towards older messages (up the screen)
+    Backward,
+}
+
+impl SearchDirection {
+    fn opposite(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchState {
+    pub pattern: String,
+    pub direction: SearchDirection,
+}
+
+/// Returns the byte ranges of every non-overlapping occurrence of `pattern` in `text`.
+/// Matching is smart-case: case-insensitive unless the pattern contains an uppercase letter.
+pub fn find_matches(text: &str, pattern: &str) -> Vec<(usize, usize)> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    if pattern.chars().any(char::is_uppercase) {
+        return text
+            .match_indices(pattern)
+            .map(|(start, m)| (start, start + m.len()))
+            .collect();
+    }
+
+    let mut ranges = Vec::new();
+    let mut skip_until = 0;
+
+    for (start, _) in text.char_indices() {
+        if start < skip_until {
+            continue;
+        }
+
+        let mut text_chars = text[start..].char_indices();
+        let mut end = None;
+        let mut matched = true;
+
+        for p in pattern.chars() {
+            match text_chars.next() {
+                Some((offset, t)) if t.to_lowercase().eq(p.to_lowercase()) => {
+                    end = Some(start + offset + t.len_utf8());
+                }
+                _ => {
+                    matched = false;
+                    break;
+                }
+            }
+        }
+
+        if matched && let Some(end) = end {
+            ranges.push((start, end));
+            skip_until = end;
+        }
+    }
+
+    ranges
+}
+
+pub fn message_matches(message: &Message, pattern: &str) -> bool {
+    let author = message
+        .author
+        .global_name
+        .as_deref()
+        .unwrap_or(&message.author.username);
+
+    !find_matches(&message.map_mentions(), pattern).is_empty()
+        || !find_matches(author, pattern).is_empty()
+}
+
+/// Finds the next matching message from the current selection.
+/// `messages` are sorted newest first and `selection_index` is 0 for the input line,
+/// `n` for `messages[n - 1]`. Returns the message index and whether the search wrapped.
+fn next_match(
+    messages: &[Message],
+    selection_index: usize,
+    pattern: &str,
+    direction: SearchDirection,
+) -> Option<(usize, bool)> {
+    let len = messages.len();
+    let current = selection_index.checked_sub(1).filter(|&i| i < len);
+
+    let (ahead, behind): (Vec<usize>, Vec<usize>) = match (direction, current) {
+        (SearchDirection::Forward, Some(c)) => ((0..c).rev().collect(), (c..len).rev().collect()),
+        (SearchDirection::Forward, None) => (Vec::new(), (0..len).rev().collect()),
+        (SearchDirection::Backward, Some(c)) => ((c + 1..len).collect(), (0..=c).collect()),
+        (SearchDirection::Backward, None) => ((0..len).collect(), Vec::new()),
+    };
+
+    if let Some(&i) = ahead
+        .iter()
+        .find(|&&i| message_matches(&messages[i], pattern))
+    {
+        return Some((i, false));
+    }
+
+    behind
+        .iter()
+        .find(|&&i| message_matches(&messages[i], pattern))
+        .map(|&i| (i, true))
+}
+
+fn select_match(state: &mut MutexGuard<'_, App>, index: usize, wrapped: bool) {
+    let pattern = state.search.as_ref().map(|s| s.pattern.clone());
+    let Some(pattern) = pattern else {
+        return;
+    };
+
+    let matching: Vec<usize> = state
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| message_matches(m, &pattern))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Counted from the top of the screen, like Vim's [n/m] indicator
+    let position = matching.iter().filter(|&&i| i >= index).count();
+
+    state.selection_index = index + 1;
+    state.status_message = if wrapped {
+        format!(
+            "Search wrapped around. /{pattern} [{position}/{}]",
+            matching.len()
+        )
+    } else {
+        format!("/{pattern} [{position}/{}]", matching.len())
+    };
+}
+
+pub async fn start_search(
+    state: &mut MutexGuard<'_, App>,
+    pattern: String,
+    direction: SearchDirection,
+    tx_action: &Sender<AppAction>,
+) {
+    // An empty pattern repeats the last search in the new direction, like Vim
+    let pattern = if pattern.is_empty() {
+        match &state.search {
+            Some(search) => search.pattern.clone(),
+            None => {
+                state.status_message = "No previous search pattern".to_string();
+                return;
+            }
+        }
+    } else {
+        pattern
+    };
+
+    state.search = Some(SearchState { pattern, direction });
+    jump_to_match(state, false, tx_action).await;
+}
+
+/// Handles `n` (`reverse == false`) and `N` (`reverse == true`).
+pub async fn jump_to_match(
+    state: &mut MutexGuard<'_, App>,
+    reverse: bool,
+    tx_action: &Sender<AppAction>,
+) {
+    let Some(search) = state.search.clone() else {
+        state.status_message = "No previous search pattern".to_string();
+        return;
+    };
+
+    let direction = if reverse {
+        search.direction.opposite()
+    } else {
+        search.direction
+    };
+
+    if let Some((index, wrapped)) = next_match(
+        &state.messages,
+        state.selection_index,
+        &search.pattern,
+        direction,
+    ) {
+        select_match(state, index, wrapped);
+        return;
+    }
+
+    let AppState::Chatting(channel_id, _) = state.state.clone() else {
+        state.status_message = format!("Pattern not found: {}", search.pattern);
+        return;
+    };
+
+    // Nothing in the loaded messages, look further back through the on-disk cache when the
+    // search index is enabled
+    let oldest_loaded = state.messages.last().map(|m| m.id.clone());
+    let target = state
+        .store
+        .as_ref()
+        .filter(|_| state.config.search_index)
+        .and_then(|store| {
+            store.find_message_before(&channel_id, &search.pattern, oldest_loaded.as_deref())
+        });
+
+    let Some(target) = target else {
+        state.status_message = format!("Pattern not found: {}", search.pattern);
+        return;
+    };
+
+    state.status_message = format!("Searching older history for {}...", search.pattern);
+    load_history_until(
+        state,
+        tx_action,
+        &channel_id,
+        &target,
+        HistoryGoal::SearchMatch(target.clone()),
+    )
+    .await;
+}
+
+/// Older history was loaded up to the match the cache pointed at.
+pub fn history_loaded(state: &mut MutexGuard<'_, App>, message_id: &str) {
+    let pattern = state
+        .search
+        .as_ref()
+        .map(|s| s.pattern.clone())
+        .unwrap_or_default();
+    match state.messages.iter().position(|m| m.id == message_id) {
+        Some(index) => select_match(state, index, false),
+        None => {
+            state.status_message = format!("Pattern only found further back in history: {pattern}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing::{CHANNEL, FRIEND, ME, message, user};
+
+    #[test]
+    fn lowercase_patterns_ignore_case() {
+        assert_eq!(
+            find_matches("Deploy the deploy", "deploy"),
+            vec![(0, 6), (11, 17)]
+        );
+        assert_eq!(find_matches("ÉTÉ", "été"), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn uppercase_patterns_match_case() {
+        assert_eq!(find_matches("Deploy the deploy", "Deploy"), vec![(0, 6)]);
+        assert!(find_matches("deploy", "Deploy").is_empty());
+    }
+
+    #[test]
+    fn matches_do_not_overlap() {
+        assert_eq!(find_matches("aaaa", "aa"), vec![(0, 2), (2, 4)]);
+        assert!(find_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn mentions_match_by_the_name_shown() {
+        let mut msg = message("100", CHANNEL, None, ME, "ping <@2>");
+        msg.mentions = vec![user(FRIEND)];
+        assert!(message_matches(&msg, "@user2"));
+        assert!(!message_matches(&msg, "<@2>"));
+        // The author's name matches too
+        assert!(message_matches(&msg, "user1"));
+    }
+}
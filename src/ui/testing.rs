@@ -0,0 +1,135 @@
+//! Helpers for tests running actions through the reducer.
+
+use reqwest::Client;
+use serde_json::json;
+use tokio::sync::{Mutex, mpsc};
+
+use crate::{
+    App, AppAction,
+    api::{ApiClient, DM, Message, User},
+    config::Config,
+    ui::{command::execute_command, events::open_chat, handle_keys_events},
+};
+
+pub const ME: &str = "1";
+pub const FRIEND: &str = "2";
+pub const GUILD: &str = "10";
+pub const DM_CHANNEL: &str = "20";
+pub const CHANNEL: &str = "30";
+/// A second DM with `FRIEND`, for tabs and windows
+pub const OTHER_DM: &str = "21";
+
+/// An offline session logged in as `ME`, with nothing loaded.
+pub fn app() -> App {
+    // Nothing listens there, whatever the reducer spawns fails right away
+    let api_client = ApiClient::new(
+        Client::new(),
+        "token".to_string(),
+        "http://127.0.0.1:9".to_string(),
+    );
+    let mut app = App::new(api_client, Config::default(), None, true, true);
+    app.current_user = Some(user(ME));
+    app
+}
+
+pub fn user(id: &str) -> User {
+    serde_json::from_value(
+        json!({ "id": id, "username": format!("user{id}"), "global_name": null }),
+    )
+    .unwrap()
+}
+
+pub fn message(
+    id: &str,
+    channel_id: &str,
+    guild_id: Option<&str>,
+    author: &str,
+    content: &str,
+) -> Message {
+    serde_json::from_value(json!({
+        "id": id,
+        "channel_id": channel_id,
+        "guild_id": guild_id,
+        "author": user(author),
+        "content": content,
+        "timestamp": "2026-01-01T00:00:00+00:00",
+        "mentions": [],
+    }))
+    .unwrap()
+}
+
+/// A DM with `FRIEND`.
+pub fn dm(id: &str) -> DM {
+    serde_json::from_value(json!({ "id": id, "type": 1, "recipients": [user(FRIEND)] })).unwrap()
+}
+
+/// Runs the actions through the reducer, returning the state and what it sent back.
+pub async fn dispatch_sent(app: App, actions: Vec<AppAction>) -> (App, Vec<AppAction>) {
+    let state = Mutex::new(app);
+    let (tx_action, mut rx_action) = mpsc::channel(32);
+    for action in actions {
+        handle_keys_events(state.lock().await, action, tx_action.clone()).await;
+    }
+
+    let mut sent = Vec::new();
+    while let Ok(action) = rx_action.try_recv() {
+        sent.push(action);
+    }
+    (state.into_inner(), sent)
+}
+
+pub async fn dispatch(app: App, actions: Vec<AppAction>) -> App {
+    dispatch_sent(app, actions).await.0
+}
+
+pub async fn receive(app: App, msg: Message) -> App {
+    dispatch(app, vec![AppAction::GatewayMessageCreate(msg)]).await
+}
+
+/// Runs what the reducer sends until it settles, as the event loop would.
+pub async fn settle(app: App, actions: Vec<AppAction>) -> App {
+    let (mut app, mut sent) = dispatch_sent(app, actions).await;
+    while !sent.is_empty() {
+        (app, sent) = dispatch_sent(app, sent).await;
+    }
+    app
+}
+
+/// Runs a `:` command line, then what it sent.
+pub async fn command(app: App, line: &str) -> App {
+    let state = Mutex::new(app);
+    let (tx_action, mut rx_action) = mpsc::channel(8);
+    execute_command(&mut state.lock().await, line, &tx_action).await;
+    let mut sent = Vec::new();
+    while let Ok(action) = rx_action.try_recv() {
+        sent.push(action);
+    }
+    settle(state.into_inner(), sent).await
+}
+
+/// Runs `open_chat` the way `:b` and `gt` do, then what it sent.
+pub async fn open(app: App, channel_id: &str) -> App {
+    let state = Mutex::new(app);
+    let (tx_action, mut rx_action) = mpsc::channel(8);
+    open_chat(&mut state.lock().await, &tx_action, channel_id.to_string()).await;
+    let mut sent = Vec::new();
+    while let Ok(action) = rx_action.try_recv() {
+        sent.push(action);
+    }
+    settle(state.into_inner(), sent).await
+}
+
+/// Chatting in `DM_CHANNEL`, with `OTHER_DM` open in a tab behind it holding a draft.
+/// Each has a message, and the DM "22" has no tab.
+pub async fn two_tabs() -> App {
+    let mut app = app();
+    app.dms = vec![dm(DM_CHANNEL), dm(OTHER_DM), dm("22")];
+    let mut app = open(app, OTHER_DM).await;
+    app.messages = vec![message("100", OTHER_DM, None, FRIEND, "older")];
+    app.input = "unsent".to_string();
+    app.cursor_position = 6;
+
+    let mut app = open(app, DM_CHANNEL).await;
+    app.messages = vec![message("200", DM_CHANNEL, None, FRIEND, "hi")];
+    app
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    api::{Channel, Message, user::ReadState},
+    logs::{LogType, print_log},
+    ui::{
+        events::{HistoryGoal, load_history_until},
+        notifications::{self, channel_settings, guild_muted},
+    },
+};
+
+/// What we know of channels besides how far we read them, which is `App.last_message_ids`.
+#[derive(Debug, Default)]
+pub struct ReadStates {
+    /// channel_id -> newest message we know of
+    latest: HashMap<String, String>,
+    /// channel_id -> mentions since we last read it
+    mentions: HashMap<String, u32>,
+    /// channel_id -> guild_id, to roll badges up onto guilds
+    guild_of: HashMap<String, String>,
+}
+
+/// Unread state of a channel, a guild or a DM.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Badge {
+    pub unread: bool,
+    pub mentions: u32,
+    /// Muted ones are never unread, only mentions show
+    pub muted: bool,
+}
+
+impl Badge {
+    fn add(self, other: Badge) -> Badge {
+        Badge {
+            unread: self.unread || other.unread,
+            mentions: self.mentions + other.mentions,
+            muted: false,
+        }
+    }
+
+    /// The `(3)` counter after a name, empty without mentions.
+    pub fn counter(&self) -> String {
+        if self.mentions > 0 {
+            format!(" ({})", self.mentions)
+        } else {
+            String::new()
+        }
+    }
+}
+
+pub fn id_value(id: &str) -> u64 {
+    id.parse().unwrap_or_default()
+}
+
+/// Applies READY's read states and the newest message of every channel.
+pub fn apply_ready(
+    state: &mut App,
+    read_states: Vec<ReadState>,
+    heads: Vec<(String, Option<String>, String)>,
+) {
+    for (channel_id, guild_id, message_id) in heads {
+        if let Some(guild_id) = guild_id {
+            state
+                .read_states
+                .guild_of
+                .insert(channel_id.clone(), guild_id);
+        }
+        state.read_states.latest.insert(channel_id, message_id);
+    }
+
+    for read_state in read_states {
+        if let Some(message_id) = read_state.last_message_id {
+            state
+                .last_message_ids
+                .insert(read_state.channel_id.clone(), message_id);
+        }
+        if read_state.mention_count > 0 {
+            state
+                .read_states
+                .mentions
+                .insert(read_state.channel_id, read_state.mention_count);
+        } else {
+            state.read_states.mentions.remove(&read_state.channel_id);
+        }
+    }
+}
+
+/// Remembers the newest message of freshly loaded guild channels.
+pub fn apply_channels(state: &mut App, channels: &[Channel]) {
+    for channel in Channel::flatten_tree(channels) {
+        if let Some(guild_id) = &channel.guild_id {
+            state
+                .read_states
+                .guild_of
+                .insert(channel.id.clone(), guild_id.clone());
+        }
+        if let Some(message_id) = &channel.last_message_id {
+            record_head(state, &channel.id, message_id);
+        }
+    }
+}
+
+fn record_head(state: &mut App, channel_id: &str, message_id: &str) {
+    let latest = state
+        .read_states
+        .latest
+        .entry(channel_id.to_string())
+        .or_default();
+    if id_value(message_id) > id_value(latest) {
+        *latest = message_id.to_string();
+    }
+}
+
+/// Whether a message pings us: a DM, a direct mention, one of our roles or @everyone,
+/// unless the guild's settings suppress role or @everyone mentions.
+pub fn mentions_me(state: &App, msg: &Message) -> bool {
+    let Some(me) = state.current_user.as_ref() else {
+        return false;
+    };
+    if state.dms.iter().any(|dm| dm.id == msg.channel_id) {
+        return true;
+    }
+
+    let my_roles = msg
+        .guild_id
+        .as_ref()
+        .and_then(|guild_id| state.members.get(guild_id))
+        .and_then(|members| members.get(&me.id))
+        .map(|member| member.roles.as_slice())
+        .unwrap_or_default();
+
+    let settings = channel_settings(state, &msg.channel_id, msg.guild_id.as_deref());
+    (msg.mention_everyone && !settings.suppress_everyone)
+        || msg.mentions.iter().any(|u| u.id == me.id)
+        || (!settings.suppress_roles
+            && msg.mention_roles.iter().any(|role| my_roles.contains(role)))
+}
+
+/// MESSAGE_CREATE in any channel: a new head, a mention, or read when it is ours.
+pub fn record_message(state: &mut App, msg: &Message) {
+    if let Some(guild_id) = &msg.guild_id {
+        state
+            .read_states
+            .guild_of
+            .insert(msg.channel_id.clone(), guild_id.clone());
+    }
+    record_head(state, &msg.channel_id, &msg.id);
+
+    let is_self = state
+        .current_user
+        .as_ref()
+        .is_some_and(|u| u.id == msg.author.id);
+    if is_self {
+        mark_read(state, &msg.channel_id, &msg.id);
+    } else if mentions_me(state, msg) {
+        *state
+            .read_states
+            .mentions
+            .entry(msg.channel_id.clone())
+            .or_default() += 1;
+    }
+}
+
+/// We read a channel up to `message_id`, here or in another client (MESSAGE_ACK).
+pub fn mark_read(state: &mut App, channel_id: &str, message_id: &str) {
+    let read = state
+        .last_message_ids
+        .entry(channel_id.to_string())
+        .or_default();
+    if id_value(message_id) >= id_value(read) {
+        *read = message_id.to_string();
+        state.read_states.mentions.remove(channel_id);
+        notifications::channel_read(state, channel_id);
+    }
+}
+
+/// Whether we read the channel up to `message_id`. Like `channel_badge`, channels without
+/// a read state count as read.
+pub fn is_read(state: &App, channel_id: &str, message_id: &str) -> bool {
+    state
+        .last_message_ids
+        .get(channel_id)
+        .is_none_or(|read| id_value(read) >= id_value(message_id))
+}
+
+/// The guild a channel belongs to, as far as READY and loaded channels tell.
+pub fn guild_of(state: &App, channel_id: &str) -> Option<String> {
+    state.read_states.guild_of.get(channel_id).cloned()
+}
+
+pub fn channel_badge(state: &App, channel_id: &str) -> Badge {
+    let latest = state
+        .read_states
+        .latest
+        .get(channel_id)
+        .map(|id| id_value(id))
+        .or_else(|| {
+            state
+                .dms
+                .iter()
+                .find(|dm| dm.id == channel_id)
+                .and_then(|dm| dm.last_message_id.as_deref())
+                .map(id_value)
+        });
+    // Channels we never opened have no read state, Discord doesn't show those as unread
+    let read = state
+        .last_message_ids
+        .get(channel_id)
+        .map(|id| id_value(id));
+
+    let guild_id = state.read_states.guild_of.get(channel_id);
+    let muted = channel_settings(state, channel_id, guild_id.map(String::as_str)).muted;
+
+    Badge {
+        unread: !muted && matches!((latest, read), (Some(latest), Some(read)) if latest > read),
+        muted,
+        mentions: state
+            .read_states
+            .mentions
+            .get(channel_id)
+            .copied()
+            .unwrap_or_default(),
+    }
+}
+
+/// Every channel of the guild rolled up into one badge.
+pub fn guild_badge(state: &App, guild_id: &str) -> Badge {
+    let badge = state
+        .read_states
+        .guild_of
+        .iter()
+        .filter(|(_, channel_guild)| *channel_guild == guild_id)
+        .map(|(channel_id, _)| channel_badge(state, channel_id))
+        .fold(Badge::default(), Badge::add);
+    Badge {
+        muted: guild_muted(state, guild_id),
+        ..badge
+    }
+}
+
+pub fn guilds_badge<'a>(state: &App, guild_ids: impl IntoIterator<Item = &'a String>) -> Badge {
+    guild_ids
+        .into_iter()
+        .map(|guild_id| guild_badge(state, guild_id))
+        .fold(Badge::default(), Badge::add)
+}
+
+/// Where the "new messages" divider goes when opening a channel with unread messages.
+pub fn unread_marker(state: &App, channel_id: &str) -> Option<(String, String)> {
+    if !channel_badge(state, channel_id).unread {
+        return None;
+    }
+    let read = state.last_message_ids.get(channel_id)?;
+    Some((channel_id.to_string(), read.clone()))
+}
+
+/// Whether the divider goes right above `message`, `previous` being the message before it.
+pub fn is_first_unread(state: &App, message: &Message, previous: Option<&Message>) -> bool {
+    let Some((channel_id, read)) = &state.unread_marker else {
+        return false;
+    };
+    let read = id_value(read);
+
+    *channel_id == message.channel_id
+        && id_value(&message.id) > read
+        && previous.is_some_and(|previous| id_value(&previous.id) <= read)
+}
+
+/// Marks the channel read up to `message_id` here and on Discord.
+pub fn ack(state: &mut App, channel_id: &str, message_id: &str) {
+    mark_read(state, channel_id, message_id);
+    if state.offline {
+        return;
+    }
+
+    let api_client = state.api_client.clone();
+    let channel_id = channel_id.to_string();
+    let message_id = message_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = api_client.ack_message(&channel_id, &message_id).await {
+            let _ = print_log(format!("Failed to ack message: {e}").into(), LogType::Error);
+        }
+    });
+}
+
+/// `U` or `:unread`, selects the first unread message, loading older history until it is
+/// reached, then acks the channel.
+pub async fn jump_to_first_unread(state: &mut MutexGuard<'_, App>, tx_action: &Sender<AppAction>) {
+    let AppState::Chatting(channel_id, _) = state.state.clone() else {
+        return;
+    };
+    let Some(read) = state
+        .unread_marker
+        .as_ref()
+        .filter(|(marker_channel, _)| *marker_channel == channel_id)
+        .map(|(_, read)| read.clone())
+    else {
+        state.status_message = "No unread messages here.".to_string();
+        return;
+    };
+
+    load_history_until(
+        state,
+        tx_action,
+        &channel_id,
+        &read,
+        HistoryGoal::FirstUnread,
+    )
+    .await;
+}
+
+/// History was loaded back to the read marker: selects the first unread message.
+pub fn history_loaded(state: &mut MutexGuard<'_, App>) {
+    let Some((channel_id, read)) = state.unread_marker.clone() else {
+        return;
+    };
+    let read = id_value(&read);
+    let Some(index) = state.messages.iter().rposition(|m| id_value(&m.id) > read) else {
+        state.status_message = "No unread messages here.".to_string();
+        return;
+    };
+    state.selection_index = index + 1;
+    state.status_message = match index + 1 {
+        1 => "1 new message.".to_string(),
+        n => format!("{n} new messages."),
+    };
+
+    if let Some(newest) = state.messages.first().map(|m| m.id.clone()) {
+        ack(state, &channel_id, &newest);
+    }
+}
@@ -2,7 +2,17 @@ use std::time::Instant;
 use tokio::sync::{MutexGuard, mpsc::Sender};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::{App, AppAction, AppState, InputMode};
+use crate::{
+    App, AppAction, AppState, InputMode,
+    logs::{LogType, print_log},
+    ui::{
+        buffers,
+        events::{can_edit_message, load_older_messages},
+        explain, guild_list, members, moderation, profile,
+        prompt::{Prompt, PromptKind},
+        search, unread,
+    },
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VimOperator {
@@ -245,27 +255,37 @@ pub async fn handle_vim_keys(
     // or when mutating state later.
     let current_operator = state.vim_state.as_ref().unwrap().operator;
 
+    // `g` waits for the key after it: `gt` and `gT` switch tabs
+    let pending_keys = std::mem::take(&mut state.vim_state.as_mut().unwrap().pending_keys);
+    if pending_keys == "g" {
+        match c {
+            't' => buffers::cycle(&mut state, &tx_action, 1).await,
+            'T' => buffers::cycle(&mut state, &tx_action, -1).await,
+            _ => {}
+        }
+        return;
+    }
+
     if let AppState::Chatting(channel_id, _) = &state.state
         && state.selection_index > 0
         && ['i', 'I', 'a', 'A'].contains(&c)
     {
         let msg_index_in_slice = state.selection_index.saturating_sub(1);
 
-        if let Some(msg) = state.messages.get(msg_index_in_slice)
-            && state
-                .current_user
-                .as_ref()
-                .is_some_and(|user| user.id == msg.author.id)
-        {
-            tx_action
-                .send(AppAction::TransitionToEditing(
-                    channel_id.clone(),
-                    msg.clone(),
-                    msg.content.clone().unwrap_or_default(),
-                    c,
-                ))
-                .await
-                .ok();
+        if let Some(msg) = state.messages.get(msg_index_in_slice) {
+            if can_edit_message(&state, msg) {
+                tx_action
+                    .send(AppAction::TransitionToEditing(
+                        channel_id.clone(),
+                        msg.clone(),
+                        msg.content.clone().unwrap_or_default(),
+                        c,
+                    ))
+                    .await
+                    .ok();
+            } else {
+                state.status_message = "You can only edit your own messages.".to_string();
+            }
         }
         return;
     }
@@ -389,23 +409,12 @@ pub async fn handle_vim_keys(
                             .await
                             .ok();
 
-                        if let Some(oldest) = state.messages.last() {
-                            let older_msgs = state
-                                .api_client
-                                .get_channel_messages(
-                                    &channel_id.clone(),
-                                    None,
-                                    Some(oldest.id.clone()),
-                                    None,
-                                    Some(100),
-                                )
-                                .await;
-
-                            if let Ok(new_messages) = older_msgs {
-                                for msg in new_messages.into_iter() {
-                                    state.messages.push(msg);
-                                }
-                            }
+                        let channel_id = channel_id.clone();
+                        if let Err(e) = load_older_messages(&mut state, &channel_id).await {
+                            let _ = print_log(
+                                format!("Failed to load older messages: {e}").into(),
+                                LogType::Error,
+                            );
                         }
 
                         tx_action.send(AppAction::EndLoadingMessages).await.ok();
@@ -518,35 +527,29 @@ pub async fn handle_vim_keys(
                 state.cursor_position = end;
             }
         }
+        'V' => {
+            if let AppState::Chatting(_, _) = &state.state
+                && let Some(msg) = state.messages.get(state.selection_index.wrapping_sub(1))
+            {
+                state.visual_anchor = Some(msg.id.clone());
+                state.status_message =
+                    "-- VISUAL LINE -- d: delete selected messages, Esc: cancel".to_string();
+            }
+        }
+        'd' if state.visual_anchor.is_some() => {
+            moderation::delete_visual_selection(&mut state, &tx_action).await;
+        }
         'd' => {
-            if let AppState::Chatting(channel_id, _) = &state.state
+            if let AppState::Chatting(_, _) = &state.state
                 && state.selection_index > 0
             {
                 if let Some(VimOperator::Delete) = current_operator {
                     // User pressed dd on a historical message!
-                    let msg_index_in_slice = state.selection_index.saturating_sub(1);
-
-                    if let Some(msg) = state.messages.get(msg_index_in_slice) {
-                        // Check if the current user is the author
-                        if state
-                            .current_user
-                            .as_ref()
-                            .is_some_and(|user| user.id == msg.author.id)
-                        {
-                            let msg_id = msg.id.clone();
-                            let ch_id = channel_id.clone();
+                    moderation::delete_selected_message(&mut state, &tx_action).await;
 
-                            tx_action
-                                .send(AppAction::ApiDeleteMessage(ch_id, msg_id))
-                                .await
-                                .ok();
-
-                            // Reset the operator immediately to not trigger regular deletion on selection change later
-                            if let Some(vim_state) = &mut state.vim_state {
-                                vim_state.operator = None;
-                            }
-                        }
-                        // If they are not the author, do nothing (we could flash status natively).
+                    // Reset the operator immediately to not trigger regular deletion on selection change later
+                    if let Some(vim_state) = &mut state.vim_state {
+                        vim_state.operator = None;
                     }
                 } else if let Some(vim_state) = &mut state.vim_state {
                     vim_state.operator = Some(VimOperator::Delete);
@@ -616,9 +619,67 @@ pub async fn handle_vim_keys(
                 clamp_cursor(&mut state);
             }
         }
+        '/' | '?' => {
+            if let AppState::Chatting(_, _) = &state.state {
+                let kind = if c == '/' {
+                    PromptKind::SearchForward
+                } else {
+                    PromptKind::SearchBackward
+                };
+                state.prompt = Some(Prompt::new(kind));
+            }
+        }
+        'n' | 'N' => {
+            if let AppState::Chatting(_, _) = &state.state {
+                search::jump_to_match(&mut state, c == 'N', &tx_action).await;
+            }
+        }
         ':' => {
-            // In the future, this could enter command mode.
-            // For now, we do nothing to avoid conflict with standard Vim behavior.
+            state.prompt = Some(Prompt::new(PromptKind::Command));
+        }
+        'K' => match &state.state {
+            AppState::SelectingChannel(_, _) => explain::explain_selected_channel(&mut state),
+            AppState::SelectingGuild => guild_list::move_entry(&mut state, -1),
+            _ => {}
+        },
+        'J' => {
+            if let AppState::SelectingGuild = &state.state {
+                guild_list::move_entry(&mut state, 1);
+            }
+        }
+        'P' => {
+            if let AppState::SelectingGuild = &state.state {
+                guild_list::set_pin(&mut state, None);
+            }
+        }
+        'g' => {
+            if let Some(vim_state) = &mut state.vim_state {
+                vim_state.pending_keys.push('g');
+            }
+        }
+        'U' => {
+            if let AppState::Chatting(_, _) = &state.state {
+                unread::jump_to_first_unread(&mut state, &tx_action).await;
+            }
+        }
+        'p' => {
+            if let AppState::Chatting(_, _) = &state.state {
+                profile::open_profile(&mut state, &[], &tx_action);
+            }
+        }
+        'M' => {
+            if let AppState::Chatting(_, _) = &state.state {
+                members::toggle(&mut state, None, &tx_action);
+            }
+        }
+        'F' => {
+            if let AppState::Chatting(_, _) = &state.state
+                && state.member_list.visible
+            {
+                let mut prompt = Prompt::new(PromptKind::MemberFilter);
+                prompt.input = state.member_list.filter.clone();
+                state.prompt = Some(prompt);
+            }
         }
         _ => {
             if let Some(vim_state) = &mut state.vim_state {
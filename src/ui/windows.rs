@@ -0,0 +1,307 @@
+use tokio::sync::{MutexGuard, mpsc::Sender};
+
+use crate::{
+    App, AppAction, AppState,
+    ui::{
+        buffers::{self, current_chat},
+        events::open_chat,
+        layout,
+    },
+};
+
+/// Channels shown at once after `:split` or `:vsplit`. The focused window is the open
+/// chat, the others draw their tab's buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Windows {
+    /// Channel ids in screen order, empty without splits
+    pub channels: Vec<String>,
+    /// Side by side after `:vsplit`, stacked after `:split`
+    pub vertical: bool,
+}
+
+/// The chats to draw where the chat goes, the current one among them.
+pub fn visible(state: &App) -> Vec<String> {
+    let Some(current) = current_chat(state) else {
+        return Vec::new();
+    };
+    if state.windows.channels.iter().any(|id| id == current) {
+        state.windows.channels.clone()
+    } else {
+        vec![current.to_string()]
+    }
+}
+
+/// Whether a channel's messages are on screen, in the focused window or another one.
+pub fn is_shown(state: &App, channel_id: &str) -> bool {
+    let Some(chat) = layout::visible_chat(state) else {
+        return false;
+    };
+    chat == channel_id
+        || (state.windows.channels.iter().any(|id| id == chat)
+            && state.windows.channels.iter().any(|id| id == channel_id))
+}
+
+/// Opening another channel in the focused window replaces it there, as `:b` does in vim.
+pub fn chat_replaced(state: &mut App, old: &str, new: &str) {
+    if state.windows.channels.iter().any(|id| id == new) {
+        return;
+    }
+    if let Some(window) = state.windows.channels.iter_mut().find(|id| *id == old) {
+        *window = new.to_string();
+    }
+}
+
+/// A closed tab takes its window with it.
+pub fn remove(state: &mut App, channel_id: &str) {
+    state.windows.channels.retain(|id| id != channel_id);
+    if state.windows.channels.len() < 2 {
+        state.windows.channels.clear();
+    }
+}
+
+/// The window `step` away from the focused one, if there is one.
+fn neighbour(state: &App, step: isize, wrap: bool) -> Option<String> {
+    let windows = &state.windows.channels;
+    let current = current_chat(state)?;
+    let index = windows.iter().position(|id| id == current)? as isize + step;
+    let len = windows.len() as isize;
+    let index = if wrap {
+        index.rem_euclid(len)
+    } else if (0..len).contains(&index) {
+        index
+    } else {
+        return None;
+    };
+    windows.get(index as usize).cloned()
+}
+
+/// `:split` and `:vsplit`: opens a tab in a new window after the focused one and moves
+/// the focus there.
+async fn split(
+    state: &mut MutexGuard<'_, App>,
+    args: &[&str],
+    vertical: bool,
+    tx_action: &Sender<AppAction>,
+) {
+    let AppState::Chatting(current, _) = state.state.clone() else {
+        state.status_message = "Open a channel to split its window.".to_string();
+        return;
+    };
+    let query = args.join(" ");
+    if query.is_empty() {
+        state.status_message = format!(
+            "Usage: :{} <name|number>",
+            if vertical { "vsplit" } else { "split" }
+        );
+        return;
+    }
+    let Some(channel_id) = buffers::find_tab(state, &query) else {
+        state.status_message = format!("No open channel matches {query}");
+        return;
+    };
+    if state.windows.channels.contains(&channel_id) || channel_id == current {
+        state.status_message = "That channel already has a window.".to_string();
+        return;
+    }
+
+    let windows = &mut state.windows;
+    if windows.channels.is_empty() {
+        windows.channels.push(current.clone());
+    }
+    let index = windows
+        .channels
+        .iter()
+        .position(|id| *id == current)
+        .unwrap_or(windows.channels.len() - 1);
+    windows.channels.insert(index + 1, channel_id.clone());
+    windows.vertical = vertical;
+    open_chat(state, tx_action, channel_id).await;
+}
+
+/// `:close` or `Ctrl-w c`: the focused window goes away, its tab stays open.
+async fn close(state: &mut MutexGuard<'_, App>, tx_action: &Sender<AppAction>) {
+    let Some(current) = current_chat(state).map(str::to_string) else {
+        return;
+    };
+    if !state.windows.channels.contains(&current) {
+        state.status_message = "Cannot close the last window.".to_string();
+        return;
+    }
+    let next = neighbour(state, 1, true);
+    remove(state, &current);
+    if let Some(next) = next {
+        open_chat(state, tx_action, next).await;
+    }
+}
+
+/// `:only` or `Ctrl-w o`: keeps the focused window only.
+fn only(state: &mut App) {
+    state.windows.channels.clear();
+}
+
+/// `:split`, `:vsplit`, `:close` and `:only`.
+pub async fn run_command(
+    state: &mut MutexGuard<'_, App>,
+    name: &str,
+    args: &[&str],
+    tx_action: &Sender<AppAction>,
+) {
+    match name {
+        "split" | "sp" => split(state, args, false, tx_action).await,
+        "vsplit" | "vs" => split(state, args, true, tx_action).await,
+        "close" => close(state, tx_action).await,
+        _ => only(state),
+    }
+}
+
+/// The key after `Ctrl-w` when it concerns windows: `w`/`W` cycle through them, `c`
+/// closes one, `o` keeps one, and the direction keys move along the split. Returns
+/// whether the key was handled, the panes get it otherwise.
+pub async fn handle_window_key(
+    state: &mut MutexGuard<'_, App>,
+    action: &AppAction,
+    tx_action: &Sender<AppAction>,
+) -> bool {
+    if !matches!(state.state, AppState::Chatting(_, _)) || state.windows.channels.is_empty() {
+        return false;
+    }
+
+    let vertical = state.windows.vertical;
+    let next = match action {
+        AppAction::InputChar('w') => neighbour(state, 1, true),
+        AppAction::InputChar('W') => neighbour(state, -1, true),
+        AppAction::InputChar('c') => {
+            close(state, tx_action).await;
+            return true;
+        }
+        AppAction::InputChar('o') => {
+            only(state);
+            return true;
+        }
+        AppAction::InputChar('h') | AppAction::SelectLeft if vertical => {
+            neighbour(state, -1, false)
+        }
+        AppAction::InputChar('l') | AppAction::SelectRight if vertical => {
+            neighbour(state, 1, false)
+        }
+        AppAction::InputChar('k') | AppAction::SelectPrevious if !vertical => {
+            neighbour(state, -1, false)
+        }
+        AppAction::InputChar('j') | AppAction::SelectNext if !vertical => {
+            neighbour(state, 1, false)
+        }
+        _ => return false,
+    };
+    match next {
+        Some(channel_id) => open_chat(state, tx_action, channel_id).await,
+        // `Ctrl-w h` from the leftmost window still reaches the channel list
+        None => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        notifier::RecordingNotifier,
+        ui::{
+            testing::{
+                self, DM_CHANNEL, FRIEND, OTHER_DM, command, message, open, settle, two_tabs,
+            },
+            unread,
+        },
+    };
+
+    fn current(app: &App) -> Option<&str> {
+        current_chat(app)
+    }
+
+    #[tokio::test]
+    async fn vsplit_opens_a_window_and_focuses_it() {
+        let app = command(two_tabs().await, "vsplit 1").await;
+        assert_eq!(app.windows.channels, vec![DM_CHANNEL, OTHER_DM]);
+        assert!(app.windows.vertical);
+        assert_eq!(current(&app), Some(OTHER_DM));
+        assert_eq!(app.messages[0].id, "100");
+        assert_eq!(app.buffers.buffers[DM_CHANNEL].messages[0].id, "200");
+    }
+
+    #[tokio::test]
+    async fn messages_in_another_window_are_read() {
+        let mut app = command(two_tabs().await, "vsplit 1").await;
+        let recorder = RecordingNotifier::default();
+        app.notifier = Box::new(recorder.clone());
+        let app = testing::receive(app, message("201", DM_CHANNEL, None, FRIEND, "seen")).await;
+        assert!(unread::is_read(&app, DM_CHANNEL, "201"));
+        assert!(recorder.shown.lock().unwrap().is_empty());
+
+        // Without the split the other tab is out of sight again
+        let app = command(app, "only").await;
+        let app = testing::receive(app, message("202", DM_CHANNEL, None, FRIEND, "unseen")).await;
+        assert!(!unread::is_read(&app, DM_CHANNEL, "202"));
+        assert_eq!(recorder.shown.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn windows_keep_their_own_selection() {
+        let mut app = command(two_tabs().await, "split 1").await;
+        app.selection_index = 1;
+        let app = settle(
+            app,
+            vec![AppAction::WindowCommand, AppAction::InputChar('k')],
+        )
+        .await;
+        assert_eq!(current(&app), Some(DM_CHANNEL));
+        assert_eq!(app.selection_index, 0);
+        assert_eq!(
+            app.buffers.buffers[OTHER_DM]
+                .view
+                .as_ref()
+                .map(|view| view.selection_index),
+            Some(1)
+        );
+
+        let app = settle(
+            app,
+            vec![AppAction::WindowCommand, AppAction::InputChar('w')],
+        )
+        .await;
+        assert_eq!(current(&app), Some(OTHER_DM));
+        assert_eq!(app.selection_index, 1);
+    }
+
+    #[tokio::test]
+    async fn closing_a_window_focuses_the_other() {
+        let app = command(two_tabs().await, "vsplit 1").await;
+        let app = command(app, "close").await;
+        assert!(app.windows.channels.is_empty());
+        assert_eq!(current(&app), Some(DM_CHANNEL));
+        assert_eq!(app.buffers.tabs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn opening_another_channel_replaces_the_focused_window() {
+        let app = command(two_tabs().await, "vsplit 1").await;
+        let app = open(app, "22").await;
+        assert_eq!(app.windows.channels, vec![DM_CHANNEL, "22"]);
+        assert_eq!(app.buffers.buffers[OTHER_DM].messages[0].id, "100");
+    }
+
+    #[tokio::test]
+    async fn both_windows_are_drawn() {
+        let mut app = command(two_tabs().await, "vsplit 1").await;
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| crate::ui::draw_ui(f, &mut app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains(": older"));
+        assert!(screen.contains(": hi"));
+    }
+}